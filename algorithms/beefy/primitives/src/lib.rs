@@ -50,6 +50,18 @@ pub trait HostFunctions: light_client_common::HostFunctions {
 		signature: &[u8; 65],
 		value: &[u8; 32],
 	) -> Option<Vec<u8>>;
+
+	/// Calculates the root of an MMR of `mmr_size` nodes from the `leaves`, given as
+	/// `(position, hash)`, and the proof `items`. Defaults to the in-crate implementation,
+	/// hosts with native MMR support can override it.
+	fn calculate_mmr_root(
+		mmr_size: u64,
+		leaves: Vec<(u64, H256)>,
+		items: Vec<H256>,
+	) -> Result<H256, error::BeefyClientError> {
+		let proof = mmr_lib::MerkleProof::<_, MerkleHasher<Self>>::new(mmr_size, items);
+		Ok(proof.calculate_root(leaves)?)
+	}
 }
 
 /// Hash length definition for hashing algorithms used
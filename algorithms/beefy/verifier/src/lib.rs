@@ -159,8 +159,6 @@ where
 	let node = mmr_update.latest_mmr_leaf.using_encoded(|leaf| H::keccak_256(leaf));
 
 	let mmr_size = NodesUtils::new(mmr_update.mmr_proof.leaf_count).size();
-	let proof =
-		mmr_lib::MerkleProof::<_, MerkleHasher<H>>::new(mmr_size, mmr_update.mmr_proof.items);
 
 	// We are trying to verify the proof for the latest mmr leaf so we expect the proof to contain a
 	// singular leaf index
@@ -172,7 +170,8 @@ where
 
 	let leaf_pos = mmr_lib::leaf_index_to_pos(*leaf_index);
 
-	let root = proof.calculate_root(vec![(leaf_pos, node.into())])?;
+	let root =
+		H::calculate_mmr_root(mmr_size, vec![(leaf_pos, node.into())], mmr_update.mmr_proof.items)?;
 	if root != mmr_root_hash {
 		return Err(BeefyClientError::InvalidMmrProof {
 			expected: mmr_root_hash,
@@ -258,9 +257,7 @@ where
 	}
 
 	let mmr_size = NodesUtils::new(mmr_proof.leaf_count).size();
-	let proof = mmr_lib::MerkleProof::<_, MerkleHasher<H>>::new(mmr_size, mmr_proof.items);
-
-	let root = proof.calculate_root(mmr_leaves)?;
+	let root = H::calculate_mmr_root(mmr_size, mmr_leaves, mmr_proof.items)?;
 	if root != trusted_client_state.mmr_root_hash {
 		return Err(BeefyClientError::InvalidMmrProof {
 			expected: trusted_client_state.mmr_root_hash,
@@ -14,8 +14,8 @@
 // limitations under the License.
 
 use beefy_light_client_primitives::{
-	error::BeefyClientError, EncodedVersionedFinalityProof, MmrUpdateProof, ParachainsUpdateProof,
-	SignatureWithAuthorityIndex, SignedCommitment,
+	error::BeefyClientError, EncodedVersionedFinalityProof, HostFunctions, MmrUpdateProof,
+	ParachainsUpdateProof, SignatureWithAuthorityIndex, SignedCommitment,
 };
 use beefy_primitives::{
 	known_payloads::MMR_ROOT_ID,
@@ -26,7 +26,8 @@ use beefy_prover::{Crypto, Prover};
 use futures::stream::StreamExt;
 use hyperspace_core::substrate::DefaultConfig as PolkadotConfig;
 use pallet_mmr_primitives::Proof;
-use sp_core::bytes::to_hex;
+use sp_core::{bytes::to_hex, H256};
+use sp_runtime::traits::BlakeTwo256;
 use subxt::rpc::{rpc_params, Subscription};
 
 #[tokio::test]
@@ -93,9 +94,16 @@ async fn test_verify_mmr_with_proof() {
 			.await
 			.unwrap();
 
+		// a host that calculates MMR roots natively has to accept the same update
+		let native_state = crate::verify_mmr_root_with_proof::<NativeMmr>(
+			client_state.clone(),
+			mmr_update.clone(),
+		)
+		.unwrap();
 		client_state =
 			crate::verify_mmr_root_with_proof::<Crypto>(client_state.clone(), mmr_update.clone())
 				.unwrap();
+		assert_eq!(native_state, client_state);
 
 		let mmr_root_hash = signed_commitment.commitment.payload.get_raw(&MMR_ROOT_ID).unwrap();
 
@@ -203,6 +211,86 @@ async fn should_fail_with_invalid_validator_set_id() {
 	}
 }
 
+/// Keccak256 merge of MMR nodes, implemented on `sp_core` rather than on the host functions.
+#[derive(Clone, Debug)]
+struct NativeKeccak;
+
+impl mmr_lib::Merge for NativeKeccak {
+	type Item = H256;
+
+	fn merge(left: &H256, right: &H256) -> H256 {
+		sp_core::keccak_256(&[left.as_bytes(), right.as_bytes()].concat()).into()
+	}
+}
+
+/// Host functions of a host that calculates MMR roots natively.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct NativeMmr;
+
+impl light_client_common::HostFunctions for NativeMmr {
+	type BlakeTwo256 = BlakeTwo256;
+}
+
+impl HostFunctions for NativeMmr {
+	fn keccak_256(input: &[u8]) -> [u8; 32] {
+		Crypto::keccak_256(input)
+	}
+
+	fn secp256k1_ecdsa_recover_compressed(
+		signature: &[u8; 65],
+		value: &[u8; 32],
+	) -> Option<Vec<u8>> {
+		Crypto::secp256k1_ecdsa_recover_compressed(signature, value)
+	}
+
+	fn calculate_mmr_root(
+		mmr_size: u64,
+		leaves: Vec<(u64, H256)>,
+		items: Vec<H256>,
+	) -> Result<H256, BeefyClientError> {
+		let proof = mmr_lib::MerkleProof::<_, NativeKeccak>::new(mmr_size, items);
+		Ok(proof.calculate_root(leaves)?)
+	}
+}
+
+#[test]
+fn mmr_root_is_calculated_by_host_functions() {
+	let store = mmr_lib::util::MemStore::default();
+	let mut mmr = mmr_lib::MMR::<_, NativeKeccak, _>::new(0, &store);
+	let leaves = (0u32..100)
+		.map(|i| {
+			let hash = H256::from(sp_core::keccak_256(&codec::Encode::encode(&i)));
+			(mmr.push(hash).unwrap(), hash)
+		})
+		.collect::<Vec<_>>();
+	let mmr_size = mmr.mmr_size();
+	let root = mmr.get_root().unwrap();
+	mmr.commit().unwrap();
+	let mmr = mmr_lib::MMR::<_, NativeKeccak, _>::new(mmr_size, &store);
+
+	for proven in [vec![0], vec![99], vec![3, 4, 50, 98]] {
+		let proven = proven.into_iter().map(|i| leaves[i]).collect::<Vec<_>>();
+		let proof = mmr.gen_proof(proven.iter().map(|(pos, _)| *pos).collect()).unwrap();
+		let items = proof.proof_items().to_vec();
+
+		let default_root =
+			Crypto::calculate_mmr_root(mmr_size, proven.clone(), items.clone()).unwrap();
+		let native_root =
+			NativeMmr::calculate_mmr_root(mmr_size, proven.clone(), items.clone()).unwrap();
+		assert_eq!(default_root, root);
+		assert_eq!(native_root, root);
+
+		// a leaf that isn't in the MMR leads both implementations to the same wrong root
+		let mut tampered = proven;
+		tampered[0].1 = H256::repeat_byte(1);
+		let default_root =
+			Crypto::calculate_mmr_root(mmr_size, tampered.clone(), items.clone()).unwrap();
+		let native_root = NativeMmr::calculate_mmr_root(mmr_size, tampered, items).unwrap();
+		assert_ne!(default_root, root);
+		assert_eq!(default_root, native_root);
+	}
+}
+
 #[tokio::test]
 #[ignore]
 async fn verify_parachain_headers() {
@@ -284,9 +372,20 @@ async fn verify_parachain_headers() {
 		let mmr_update =
 			parachain_client.fetch_mmr_update_proof_for(signed_commitment).await.unwrap();
 
+		let native_state = crate::verify_mmr_root_with_proof::<NativeMmr>(
+			client_state.clone(),
+			mmr_update.clone(),
+		)
+		.expect("verify_mmr_root_with_proof should not panic with native MMR roots!");
 		client_state = crate::verify_mmr_root_with_proof::<Crypto>(client_state, mmr_update)
 			.expect("verify_mmr_root_with_proof should not panic!");
+		assert_eq!(native_state, client_state);
 
+		crate::verify_parachain_headers::<NativeMmr>(
+			client_state.clone(),
+			parachain_update_proof.clone(),
+		)
+		.expect("verify_parachain_headers should not panic with native MMR roots!");
 		crate::verify_parachain_headers::<Crypto>(client_state.clone(), parachain_update_proof)
 			.expect("verify_parachain_headers should not panic!");
 
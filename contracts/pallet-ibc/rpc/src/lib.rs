@@ -2,6 +2,7 @@
 
 //! IBC RPC Implementation.
 
+use bytes::Bytes;
 use codec::Encode;
 use ibc::{
 	core::{
@@ -126,21 +127,21 @@ pub struct PacketInfo {
 	pub destination_channel: String,
 	/// Channel order
 	pub channel_order: String,
-	/// Opaque packet data
-	pub data: Vec<u8>,
+	/// Opaque packet data, shared between clones of the packet info
+	pub data: Bytes,
 	/// Timeout height
 	pub timeout_height: Height,
 	/// Timeout timestamp
 	pub timeout_timestamp: u64,
 	/// Packet acknowledgement
-	pub ack: Option<Vec<u8>>,
+	pub ack: Option<Bytes>,
 }
 
 impl TryFrom<RawPacketInfo> for PacketInfo {
 	type Error = ();
 
 	fn try_from(info: RawPacketInfo) -> core::result::Result<Self, ()> {
-		log::info!("RawPacketInfo: {:?}", info);
+		log::trace!("RawPacketInfo: {:?}", info);
 		Ok(Self {
 			height: info.height,
 			sequence: info.sequence,
@@ -149,13 +150,13 @@ impl TryFrom<RawPacketInfo> for PacketInfo {
 			destination_port: String::from_utf8(info.destination_port).map_err(|_| ())?,
 			destination_channel: String::from_utf8(info.destination_channel).map_err(|_| ())?,
 			channel_order: info.channel_order.to_string(),
-			data: info.data,
+			data: info.data.into(),
 			timeout_height: Height {
 				revision_number: info.timeout_height.0,
 				revision_height: info.timeout_height.1,
 			},
 			timeout_timestamp: info.timeout_timestamp,
-			ack: info.ack,
+			ack: info.ack.map(Into::into),
 		})
 	}
 }
@@ -525,7 +526,7 @@ where
 					destination_channel: String::from_utf8(packet.destination_channel).map_err(
 						|_| runtime_error_into_rpc_error("Failed to decode destination channel"),
 					)?,
-					data: packet.data,
+					data: packet.data.into(),
 					timeout_height: Height {
 						revision_number: packet.timeout_height.0,
 						revision_height: packet.timeout_height.1,
@@ -541,7 +542,7 @@ where
 							})?
 							.to_string()
 					},
-					ack: packet.ack,
+					ack: packet.ack.map(Into::into),
 				})
 			})
 			.collect()
@@ -583,7 +584,7 @@ where
 					destination_channel: String::from_utf8(packet.destination_channel).map_err(
 						|_| runtime_error_into_rpc_error("Failed to decode destination channel"),
 					)?,
-					data: packet.data,
+					data: packet.data.into(),
 					timeout_height: Height {
 						revision_number: packet.timeout_height.0,
 						revision_height: packet.timeout_height.1,
@@ -599,7 +600,7 @@ where
 							})?
 							.to_string()
 					},
-					ack: packet.ack,
+					ack: packet.ack.map(Into::into),
 				})
 			})
 			.collect()
@@ -0,0 +1,82 @@
+//! Packet data is shared between clones of [`PacketInfo`], so moving packets through the relayer
+//! doesn't copy it.
+
+use ibc_proto::ibc::core::client::v1::Height;
+use ibc_rpc::PacketInfo;
+use std::{
+	alloc::{GlobalAlloc, Layout, System},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const PACKETS: u64 = 10_000;
+const PACKET_SIZE: usize = 4096;
+
+/// Mirrors what the relayer does with queried packets: they are sorted and deduplicated and every
+/// packet is cloned into the task that builds its message.
+fn pipeline<T: Clone + Ord>(packets: &[T]) -> Vec<T> {
+	let mut packets = packets.to_vec();
+	packets.sort();
+	packets.dedup();
+	packets.iter().cloned().collect()
+}
+
+/// Returns the number of bytes allocated by `f`.
+fn allocated_by<T>(f: impl FnOnce() -> T) -> usize {
+	let before = ALLOCATED.load(Ordering::SeqCst);
+	let result = f();
+	let allocated = ALLOCATED.load(Ordering::SeqCst) - before;
+	drop(result);
+	allocated
+}
+
+fn packet_info(sequence: u64) -> PacketInfo {
+	PacketInfo {
+		height: Some(sequence),
+		sequence,
+		source_port: "transfer".to_string(),
+		source_channel: "channel-0".to_string(),
+		destination_port: "transfer".to_string(),
+		destination_channel: "channel-1".to_string(),
+		channel_order: "ORDER_UNORDERED".to_string(),
+		data: vec![sequence as u8; PACKET_SIZE].into(),
+		timeout_height: Height { revision_number: 1, revision_height: 1000 },
+		timeout_timestamp: 0,
+		ack: None,
+	}
+}
+
+#[test]
+fn packet_data_is_not_copied_through_the_pipeline() {
+	let packets = (0..PACKETS).map(packet_info).collect::<Vec<_>>();
+	// packet data owned by every clone, as before it was shared
+	let baseline_packets = packets
+		.iter()
+		.map(|packet| (packet.sequence, packet.data.to_vec()))
+		.collect::<Vec<_>>();
+
+	let baseline = allocated_by(|| pipeline(&baseline_packets));
+	let allocated = allocated_by(|| pipeline(&packets));
+	assert!(baseline >= 2 * PACKETS as usize * PACKET_SIZE);
+	assert!(
+		allocated * 2 <= baseline,
+		"{allocated} bytes allocated, expected at most half of the {baseline} bytes baseline"
+	);
+}
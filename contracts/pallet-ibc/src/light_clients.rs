@@ -1,4 +1,10 @@
-use alloc::{borrow::ToOwned, boxed::Box, format, string::ToString, vec::Vec};
+use alloc::{
+	borrow::ToOwned,
+	boxed::Box,
+	format,
+	string::{String, ToString},
+	vec::Vec,
+};
 use frame_support::{
 	pallet_prelude::{StorageValue, ValueQuery},
 	traits::StorageInstance,
@@ -247,27 +253,75 @@ pub enum AnyClientState {
 	Mock(ibc::mock::client_state::MockClientState),
 }
 
+/// Maximum number of `Wasm` client states [`AnyClientState::decode_recursive`] unpacks before it
+/// gives up on a client state.
+pub const MAX_WASM_NESTING: usize = 4;
+
+/// Reason why [`AnyClientState::try_decode_recursive`] didn't find the expected client state.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeRecursiveError {
+	/// The client state, or one of the client states wrapped in it, couldn't be decoded.
+	Decode(String),
+	/// The client state is wrapped in more than [`MAX_WASM_NESTING`] `Wasm` client states.
+	TooDeep,
+	/// The innermost client state is of another type than expected. `code_id` is the hex encoded
+	/// checksum of the wasm client the state was wrapped in, if any.
+	Unexpected { found: String, code_id: Option<String> },
+}
+
+impl core::fmt::Display for DecodeRecursiveError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			DecodeRecursiveError::Decode(e) => write!(f, "failed to decode client state: {e}"),
+			DecodeRecursiveError::TooDeep => write!(
+				f,
+				"client state is wrapped in more than {MAX_WASM_NESTING} wasm client states"
+			),
+			DecodeRecursiveError::Unexpected { found, code_id: Some(code_id) } =>
+				write!(f, "unexpected {found} client state in wasm client {code_id}"),
+			DecodeRecursiveError::Unexpected { found, code_id: None } =>
+				write!(f, "unexpected {found} client state"),
+		}
+	}
+}
+
 impl AnyClientState {
 	/// Recursively decode the client state from the given `Any` type, until it
 	/// matches the given predicate `f`. Only `Wasm` variant may be unpacked recursively.
-	pub fn decode_recursive<F>(mut any: Any, f: F) -> Option<Self>
+	pub fn decode_recursive<F>(any: Any, f: F) -> Option<Self>
 	where
 		F: Fn(&Self) -> bool,
 	{
-		loop {
-			let client_state = AnyClientState::try_from(any).ok()?;
+		Self::try_decode_recursive(any, f).ok()
+	}
+
+	/// Like [`AnyClientState::decode_recursive`], but tells why no client state matched.
+	pub fn try_decode_recursive<F>(mut any: Any, f: F) -> Result<Self, DecodeRecursiveError>
+	where
+		F: Fn(&Self) -> bool,
+	{
+		let mut code_id = None;
+		for _ in 0..=MAX_WASM_NESTING {
+			let client_state = AnyClientState::try_from(any)
+				.map_err(|e| DecodeRecursiveError::Decode(format!("{e:?}")))?;
 
 			match client_state {
-				AnyClientState::Wasm(wasm_client_state) =>
-					any = Any::decode(&*wasm_client_state.data).ok()?,
+				AnyClientState::Wasm(wasm_client_state) => {
+					code_id = Some(
+						wasm_client_state.code_id.iter().map(|b| format!("{b:02x}")).collect(),
+					);
+					any = Any::decode(&*wasm_client_state.data)
+						.map_err(|e| DecodeRecursiveError::Decode(e.to_string()))?;
+				},
+				c if f(&c) => return Ok(c),
 				c =>
-					if f(&c) {
-						break Some(c)
-					} else {
-						return None
-					},
-			};
+					return Err(DecodeRecursiveError::Unexpected {
+						found: c.client_type().to_string(),
+						code_id,
+					}),
+			}
 		}
+		Err(DecodeRecursiveError::TooDeep)
 	}
 
 	pub fn unpack_recursive(&self) -> &Self {
@@ -508,3 +562,37 @@ mod mocks {
 	pub const MOCK_CLIENT_MESSAGE_TYPE_URL: &str = "/ibc.mock.ClientMessage";
 	pub const MOCK_CONSENSUS_STATE_TYPE_URL: &str = "/ibc.mock.ConsensusState";
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn grandpa_in_wasm() -> Any {
+		let grandpa = AnyClientState::Grandpa(Default::default());
+		AnyClientState::wasm(grandpa, vec![0xab, 0x01]).unwrap().into()
+	}
+
+	#[test]
+	fn decode_recursive_unpacks_wasm_client_state() {
+		let client_state = AnyClientState::try_decode_recursive(grandpa_in_wasm(), |c| {
+			matches!(c, AnyClientState::Grandpa(_))
+		});
+		assert!(matches!(client_state, Ok(AnyClientState::Grandpa(_))));
+	}
+
+	#[test]
+	fn decode_recursive_names_unexpected_client_state() {
+		let error = AnyClientState::try_decode_recursive(grandpa_in_wasm(), |c| {
+			matches!(c, AnyClientState::Beefy(_))
+		})
+		.unwrap_err();
+		assert_eq!(
+			error,
+			DecodeRecursiveError::Unexpected {
+				found: "10-grandpa".to_string(),
+				code_id: Some("ab01".to_string())
+			}
+		);
+		assert_eq!(error.to_string(), "unexpected 10-grandpa client state in wasm client ab01");
+	}
+}
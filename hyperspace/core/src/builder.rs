@@ -0,0 +1,234 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Programmatic setup of the relayer, for embedding it in other applications.
+//!
+//! ```no_run
+//! use hyperspace_core::{AnyConfig, RelayerBuilder};
+//!
+//! # async fn example(config_a: AnyConfig, config_b: AnyConfig) -> anyhow::Result<()> {
+//! let registry = prometheus::Registry::new();
+//! let relayer = RelayerBuilder::new()
+//! 	.with_chain_a(config_a)
+//! 	.with_chain_b(config_b)
+//! 	.with_metrics(registry)
+//! 	.build()
+//! 	.await?;
+//! relayer.run().await
+//! # }
+//! ```
+
+use crate::{
+	chain::{AnyChain, AnyConfig},
+	middleware::{MiddlewareStack, PacketMiddleware},
+	relay_with_middlewares, Mode,
+};
+use anyhow::{anyhow, Result};
+use futures::Future;
+use metrics::{data::Metrics, handler::MetricsHandler};
+use primitives::Chain;
+use prometheus::Registry;
+use std::{
+	pin::Pin,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, Mutex,
+	},
+};
+use tokio::sync::watch;
+
+/// Chain of a [`RelayerBuilder`], resolved in [`RelayerBuilder::build`]. Either connects to the
+/// chain or returns a client that is already connected.
+type Connect<C> = Pin<Box<dyn Future<Output = Result<C>> + Send>>;
+
+/// Builds a [`Relayer`] between two chains. Chains are usually given as [`AnyConfig`]s, but any
+/// [`Chain`] implementation can be relayed with [`RelayerBuilder::with_chains`].
+pub struct RelayerBuilder<A = AnyChain, B = AnyChain> {
+	chain_a: Option<Connect<A>>,
+	chain_b: Option<Connect<B>>,
+	registry: Option<Registry>,
+	middlewares: Vec<Arc<dyn PacketMiddleware>>,
+	mode: Option<Mode>,
+}
+
+impl<A, B> Default for RelayerBuilder<A, B> {
+	fn default() -> Self {
+		Self { chain_a: None, chain_b: None, registry: None, middlewares: vec![], mode: None }
+	}
+}
+
+impl RelayerBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl<B> RelayerBuilder<AnyChain, B> {
+	/// Chain A, connected to in [`RelayerBuilder::build`].
+	pub fn with_chain_a(mut self, config: AnyConfig) -> Self {
+		self.chain_a = Some(Box::pin(config.into_client()));
+		self
+	}
+}
+
+impl<A> RelayerBuilder<A, AnyChain> {
+	/// Chain B, connected to in [`RelayerBuilder::build`].
+	pub fn with_chain_b(mut self, config: AnyConfig) -> Self {
+		self.chain_b = Some(Box::pin(config.into_client()));
+		self
+	}
+}
+
+impl<A, B> RelayerBuilder<A, B> {
+	/// Chain clients that are already connected, e.g. after their client ids were checked.
+	pub fn with_chains<C, D>(self, chain_a: C, chain_b: D) -> RelayerBuilder<C, D>
+	where
+		C: Send + 'static,
+		D: Send + 'static,
+	{
+		RelayerBuilder {
+			chain_a: Some(Box::pin(async move { Ok(chain_a) })),
+			chain_b: Some(Box::pin(async move { Ok(chain_b) })),
+			registry: self.registry,
+			middlewares: self.middlewares,
+			mode: self.mode,
+		}
+	}
+
+	/// Registers the metrics of both chains in `registry`. Serving them is left to the caller.
+	pub fn with_metrics(mut self, registry: Registry) -> Self {
+		self.registry = Some(registry);
+		self
+	}
+
+	/// Adds a middleware that is consulted after the ones added before it. Without middlewares,
+	/// the relayer logs and forwards every packet.
+	pub fn with_middleware(mut self, middleware: impl PacketMiddleware + 'static) -> Self {
+		self.middlewares.push(Arc::new(middleware));
+		self
+	}
+
+	pub fn with_mode(mut self, mode: Mode) -> Self {
+		self.mode = Some(mode);
+		self
+	}
+}
+
+impl<A: Chain, B: Chain> RelayerBuilder<A, B> {
+	/// Connects to both chains and registers their metrics.
+	pub async fn build(self) -> Result<Relayer<A, B>> {
+		let chain_a = self.chain_a.ok_or_else(|| anyhow!("Chain A is not configured"))?;
+		let chain_b = self.chain_b.ok_or_else(|| anyhow!("Chain B is not configured"))?;
+		let chain_a = chain_a.await?;
+		let chain_b = chain_b.await?;
+
+		let metrics = match self.registry {
+			Some(registry) => {
+				let metrics_a = Metrics::register(chain_a.name(), &registry)?;
+				let metrics_b = Metrics::register(chain_b.name(), &registry)?;
+				let mut handler_a = MetricsHandler::new(registry.clone(), metrics_a);
+				let mut handler_b = MetricsHandler::new(registry, metrics_b);
+				handler_a.link_with_counterparty(&mut handler_b);
+				Some((handler_a, handler_b))
+			},
+			None => None,
+		};
+		let middlewares = if self.middlewares.is_empty() {
+			MiddlewareStack::default()
+		} else {
+			MiddlewareStack::new(self.middlewares)
+		};
+
+		Ok(Relayer {
+			chain_a,
+			chain_b,
+			mode: self.mode,
+			middlewares,
+			metrics: Mutex::new(metrics),
+			started: AtomicBool::new(false),
+			shutdown: watch::channel(false).0,
+		})
+	}
+}
+
+/// A relayer between two chains, created by [`RelayerBuilder`].
+pub struct Relayer<A = AnyChain, B = AnyChain> {
+	chain_a: A,
+	chain_b: B,
+	mode: Option<Mode>,
+	middlewares: MiddlewareStack,
+	metrics: Mutex<Option<(MetricsHandler, MetricsHandler)>>,
+	started: AtomicBool,
+	shutdown: watch::Sender<bool>,
+}
+
+impl<A: Chain, B: Chain> Relayer<A, B> {
+	pub fn chain_a(&self) -> &A {
+		&self.chain_a
+	}
+
+	pub fn chain_b(&self) -> &B {
+		&self.chain_b
+	}
+
+	/// Both chains, e.g. to adjust their client ids before relaying.
+	pub fn chains_mut(&mut self) -> (&mut A, &mut B) {
+		(&mut self.chain_a, &mut self.chain_b)
+	}
+
+	/// Runs the relayer loop until it fails or [`Relayer::shutdown`] is called. A relayer can
+	/// only be run once.
+	pub async fn run(&self) -> Result<()> {
+		if self.started.swap(true, Ordering::SeqCst) {
+			return Err(anyhow!("Relayer was already started"))
+		}
+		let mut shutdown = self.shutdown.subscribe();
+		if *shutdown.borrow() {
+			return Ok(())
+		}
+		let (metrics_a, metrics_b) = self.metrics.lock().unwrap().take().unzip();
+		let relay = relay_with_middlewares(
+			self.chain_a.clone(),
+			self.chain_b.clone(),
+			metrics_a,
+			metrics_b,
+			self.mode,
+			self.middlewares.clone(),
+		);
+		tokio::select! {
+			result = relay => result,
+			_ = shutdown.changed() => {
+				log::info!(target: "hyperspace", "Relayer for {}-{} was shut down", self.chain_a.name(), self.chain_b.name());
+				Ok(())
+			}
+		}
+	}
+
+	/// Stops the relayer loop. A relayer that wasn't run yet won't start anymore.
+	pub fn shutdown(&self) {
+		self.shutdown.send_replace(true);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+
+	#[test]
+	fn build_requires_both_chains() {
+		let error = block_on(RelayerBuilder::new().build()).err().unwrap();
+		assert_eq!(error.to_string(), "Chain A is not configured");
+	}
+}
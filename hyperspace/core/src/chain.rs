@@ -64,12 +64,16 @@ use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState, AnyConsensusSt
 #[cfg(any(test, feature = "testing"))]
 use pallet_ibc::Timeout;
 use parachain::{ParachainClient, ParachainClientConfig};
+#[cfg(feature = "testing")]
+use primitives::mock::{MockChain, MockConfig};
 use primitives::{
 	mock::LocalClientTypes, Chain, CommonClientState, IbcProvider, KeyProvider, LightClientSync,
 	MisbehaviourHandler, UpdateType,
 };
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
-use std::{pin::Pin, time::Duration};
+use serde_json::{json, Value};
+use std::{fmt, path::Path, pin::Pin, time::Duration};
 use tendermint_proto::Protobuf;
 use thiserror::Error;
 
@@ -85,6 +89,71 @@ pub struct CoreConfig {
 	pub prometheus_endpoint: Option<String>,
 }
 
+/// A part of a config file that doesn't match the config schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+	/// JSON pointer to the invalid value, empty for the whole file
+	pub path: String,
+	pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.path.is_empty() {
+			write!(f, "{}", self.message)
+		} else {
+			write!(f, "{}: {}", self.path, self.message)
+		}
+	}
+}
+
+/// Returns the JSON Schema of the chain config files.
+pub fn generate_config_schema() -> RootSchema {
+	schema_for!(AnyConfig)
+}
+
+/// Validates the chain config file at `path` against [`generate_config_schema`].
+pub fn validate_config_file(path: &Path) -> Result<(), Vec<SchemaViolation>> {
+	let violation = |message: String| vec![SchemaViolation { path: String::new(), message }];
+	let content = std::fs::read_to_string(path)
+		.map_err(|e| violation(format!("Failed to read {}: {e}", path.display())))?;
+	let config: Value = toml::from_str(&content)
+		.map_err(|e| violation(format!("Failed to parse {}: {e}", path.display())))?;
+	validate_config(&config)
+}
+
+fn validate_config(config: &Value) -> Result<(), Vec<SchemaViolation>> {
+	let mut schema =
+		serde_json::to_value(generate_config_schema()).expect("schema is always serializable; qed");
+	let variants = schema
+		.as_object_mut()
+		.and_then(|schema| schema.remove("oneOf"))
+		.and_then(|variants| variants.as_array().cloned())
+		.unwrap_or_default();
+	// Validate against the schema of the configured chain type only, since a mismatch with every
+	// variant of `AnyConfig` isn't a useful error
+	let chain_type = &config["type"];
+	let variant = variants
+		.into_iter()
+		.find(|variant| &variant["properties"]["type"]["enum"][0] == chain_type)
+		.ok_or_else(|| {
+			vec![SchemaViolation {
+				path: "/type".to_string(),
+				message: format!("Unknown chain type {chain_type}"),
+			}]
+		})?;
+	schema["allOf"] = json!([variant]);
+
+	let schema = jsonschema::JSONSchema::compile(&schema)
+		.expect("schema generated by schemars is valid; qed");
+	if let Err(errors) = schema.validate(config) {
+		return Err(errors
+			.map(|e| SchemaViolation { path: e.instance_path.to_string(), message: e.to_string() })
+			.collect())
+	}
+	Ok(())
+}
+
 impl From<String> for AnyError {
 	fn from(s: String) -> Self {
 		Self::Other(s)
@@ -99,6 +168,8 @@ chains! {
 	PicassoKusama(ParachainClientConfig, ParachainClient<PicassoKusamaConfig>),
 	#[cfg(feature = "cosmos")]
 	Cosmos(CosmosClientConfig, CosmosClient<DefaultConfig>),
+	#[cfg(feature = "testing")]
+	Mock(MockConfig, MockChain),
 }
 
 fn wrap_any_msg_into_wasm(msg: Any, code_id: Bytes) -> Result<Any, anyhow::Error> {
@@ -149,3 +220,44 @@ pub struct WasmChain {
 	pub inner: Box<AnyChain>,
 	pub code_id: Bytes,
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(config: &str) -> Value {
+		toml::from_str(config).unwrap()
+	}
+
+	#[test]
+	fn example_config_matches_schema() {
+		let config = parse(include_str!("../../../config/rococo-local-local.toml"));
+		assert_eq!(validate_config(&config), Ok(()));
+	}
+
+	#[test]
+	fn schema_violations_point_to_invalid_fields() {
+		let mut config = parse(include_str!("../../../config/rococo-local-local.toml"));
+		config["para_id"] = json!("2000");
+		config.as_object_mut().unwrap().remove("private_key");
+		let violations = validate_config(&config).unwrap_err();
+		assert_eq!(violations.len(), 2);
+		assert!(violations.iter().any(|violation| violation.path == "/para_id"));
+		assert!(violations.iter().any(|violation| violation.message.contains("private_key")));
+
+		config["type"] = json!("ethereum");
+		let violations = validate_config(&config).unwrap_err();
+		assert_eq!(violations[0].path, "/type");
+	}
+
+	#[test]
+	fn zero_timeout_sweep_interval_is_rejected() {
+		let mut config = parse(include_str!("../../../config/rococo-local-local.toml"));
+		config["timeout_sweep_interval"] = json!(30);
+		assert_eq!(validate_config(&config), Ok(()));
+
+		config["timeout_sweep_interval"] = json!(0);
+		let violations = validate_config(&config).unwrap_err();
+		assert_eq!(violations[0].path, "/timeout_sweep_interval");
+	}
+}
@@ -0,0 +1,159 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use primitives::CircuitBreakerConfig;
+use std::time::{Duration, Instant};
+
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+	/// Relaying works normally.
+	Closed,
+	/// Relaying is paused until the given instant.
+	Open { until: Instant },
+	/// The pause is over, a single trial cycle decides whether the circuit closes or re-opens.
+	HalfOpen,
+}
+
+impl CircuitState {
+	/// Numeric value of the state reported in the metrics.
+	pub fn as_metric(&self) -> u64 {
+		match self {
+			CircuitState::Closed => 0,
+			CircuitState::HalfOpen => 1,
+			CircuitState::Open { .. } => 2,
+		}
+	}
+}
+
+/// Pauses relaying from a chain after `failure_threshold` consecutive failed cycles, so that an
+/// unavailable RPC node doesn't make the relayer loop on errors.
+///
+/// The circuit stays open for `reset_timeout`, then turns half-open and lets one cycle through,
+/// which has to finish within `half_open_timeout`. If it succeeds the circuit closes, otherwise
+/// it opens again for another `reset_timeout`.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+	pub failure_threshold: u32,
+	pub reset_timeout: Duration,
+	pub half_open_timeout: Duration,
+	state: CircuitState,
+	consecutive_failures: u32,
+}
+
+impl CircuitBreaker {
+	pub fn new(config: &CircuitBreakerConfig) -> Self {
+		Self {
+			failure_threshold: config.failure_threshold,
+			reset_timeout: Duration::from_secs(config.reset_timeout),
+			half_open_timeout: Duration::from_secs(config.half_open_timeout),
+			state: CircuitState::Closed,
+			consecutive_failures: 0,
+		}
+	}
+
+	pub fn state(&self) -> CircuitState {
+		self.state
+	}
+
+	/// Returns whether a cycle may run at `now`. An open circuit turns half-open once its
+	/// `reset_timeout` has passed.
+	pub fn allow(&mut self, now: Instant) -> bool {
+		match self.state {
+			CircuitState::Open { until } if now < until => false,
+			CircuitState::Open { .. } => {
+				self.state = CircuitState::HalfOpen;
+				true
+			},
+			CircuitState::Closed | CircuitState::HalfOpen => true,
+		}
+	}
+
+	pub fn on_success(&mut self) {
+		self.consecutive_failures = 0;
+		self.state = CircuitState::Closed;
+	}
+
+	/// Records a failed cycle. Returns `true` if the circuit opened because of it.
+	pub fn on_failure(&mut self, now: Instant) -> bool {
+		self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+		let open = match self.state {
+			CircuitState::HalfOpen => true,
+			CircuitState::Closed =>
+				self.failure_threshold != 0 && self.consecutive_failures >= self.failure_threshold,
+			CircuitState::Open { .. } => false,
+		};
+		if open {
+			self.state = CircuitState::Open { until: now + self.reset_timeout };
+		}
+		open
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn breaker() -> CircuitBreaker {
+		CircuitBreaker::new(&CircuitBreakerConfig {
+			failure_threshold: 3,
+			reset_timeout: 60,
+			half_open_timeout: 10,
+		})
+	}
+
+	#[test]
+	fn circuit_opens_after_consecutive_failures() {
+		let mut breaker = breaker();
+		let now = Instant::now();
+		assert!(!breaker.on_failure(now));
+		breaker.on_success();
+		assert!(!breaker.on_failure(now));
+		assert!(!breaker.on_failure(now));
+		assert!(breaker.on_failure(now));
+		assert_eq!(breaker.state(), CircuitState::Open { until: now + Duration::from_secs(60) });
+		assert!(!breaker.allow(now + Duration::from_secs(59)));
+	}
+
+	#[test]
+	fn half_open_circuit_closes_on_success_and_reopens_on_failure() {
+		let mut breaker = breaker();
+		let now = Instant::now();
+		(0..3).for_each(|_| {
+			breaker.on_failure(now);
+		});
+
+		let later = now + Duration::from_secs(60);
+		assert!(breaker.allow(later));
+		assert_eq!(breaker.state(), CircuitState::HalfOpen);
+		assert!(breaker.on_failure(later));
+		assert_eq!(breaker.state(), CircuitState::Open { until: later + Duration::from_secs(60) });
+
+		assert!(breaker.allow(later + Duration::from_secs(60)));
+		breaker.on_success();
+		assert_eq!(breaker.state(), CircuitState::Closed);
+		assert!(!breaker.on_failure(later));
+	}
+
+	#[test]
+	fn zero_threshold_disables_the_breaker() {
+		let mut breaker = CircuitBreaker::new(&CircuitBreakerConfig {
+			failure_threshold: 0,
+			..Default::default()
+		});
+		let now = Instant::now();
+		assert!((0..100).all(|_| !breaker.on_failure(now)));
+		assert!(breaker.allow(now));
+	}
+}
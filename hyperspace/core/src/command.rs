@@ -13,24 +13,48 @@
 // limitations under the License.
 
 use crate::{
-	chain::{AnyConfig, Config, CoreConfig},
-	fish, relay, Mode,
+	chain::{
+		generate_config_schema, validate_config_file, AnyChain, AnyConfig, Config, CoreConfig,
+	},
+	doctor::{diagnose, ChainReport},
+	fish,
+	reconcile::reconcile_client_id,
+	relay, Mode, RelayerBuilder,
 };
 use anyhow::{anyhow, Result};
 use clap::Parser;
-use ibc::core::{ics04_channel::channel::Order, ics24_host::identifier::PortId};
-use metrics::{data::Metrics, handler::MetricsHandler, init_prometheus};
+#[cfg(feature = "cosmos")]
+use cosmos::key_source::{read_env, EncryptedKeyFile};
+use ibc::core::{
+	ics04_channel::channel::Order,
+	ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+};
+use metrics::init_prometheus;
 use primitives::{
 	utils::{create_channel, create_clients, create_connection},
 	Chain, IbcProvider,
 };
 use prometheus::Registry;
-use std::{num::NonZeroU64, path::PathBuf, str::FromStr, time::Duration};
+use serde::Serialize;
+use std::{
+	num::NonZeroU64,
+	path::{Path, PathBuf},
+	str::FromStr,
+	time::Duration,
+};
+
+/// How often the relay subcommand prints its status in JSON mode.
+const STATUS_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Parser)]
 pub struct Cli {
 	#[structopt(subcommand)]
 	pub subcommand: Subcommand,
+	/// Print the result of the subcommand to stdout as a single JSON object. Logs are always
+	/// written to stderr. `relay` prints its status periodically instead, and `fish` doesn't
+	/// print anything, since it has no result before it fails.
+	#[clap(long, global = true)]
+	pub json: bool,
 }
 
 /// Possible subcommands of the main binary.
@@ -51,6 +75,13 @@ pub enum Subcommand {
 	CreateConnection(Cmd),
 	#[clap(name = "create-channel", about = "Creates a channel on the specified port")]
 	CreateChannel(Cmd),
+	#[clap(name = "schema", about = "Prints the JSON Schema of the chain config files")]
+	Schema,
+	#[clap(name = "doctor", about = "Checks the connectivity and setup of the configured chains")]
+	Doctor(DoctorCmd),
+	#[cfg(feature = "cosmos")]
+	#[clap(name = "keys", about = "Manages the signing keys of Cosmos chains")]
+	Keys(KeysCmd),
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -65,15 +96,15 @@ pub struct Cmd {
 	#[clap(long)]
 	config_core: String,
 	/// Port id for channel creation
-	#[clap(long)]
+	#[clap(long, alias = "port")]
 	port_id: Option<String>,
 	/// Connection delay period in seconds
 	#[clap(long)]
 	delay_period: Option<std::num::NonZeroU32>,
-	/// Channel order
+	/// Channel order, `ordered` or `unordered`
 	#[clap(long)]
 	order: Option<String>,
-	/// Channel version
+	/// Channel version proposed to the counterparty, e.g. `ics20-1` for transfer channels
 	#[clap(long)]
 	version: Option<String>,
 	/// New config path for A to avoid overriding existing configuration
@@ -82,6 +113,52 @@ pub struct Cmd {
 	/// New config path for B to avoid overriding existing configuration
 	#[clap(long)]
 	pub out_config_b: Option<String>,
+	/// Use the newest matching client on the counterparty if the configured client id is unset
+	/// or stale
+	#[clap(long)]
+	auto_select_client: bool,
+}
+
+/// Result of a subcommand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum Output {
+	/// Code id of the uploaded WASM blob
+	WasmCode { code_id: String },
+	/// Ids of the light clients created on chain A and chain B
+	Clients { client_a: ClientId, client_b: ClientId },
+	/// Connection ids on chain A and chain B
+	Connection { connection_a: ConnectionId, connection_b: ConnectionId },
+	/// Channel ids on chain A and chain B, and the port and connections they were opened on
+	Channel {
+		channel_a: ChannelId,
+		channel_b: ChannelId,
+		port_id: PortId,
+		connection_a: ConnectionId,
+		connection_b: Option<ConnectionId>,
+	},
+	/// Latest heights of both chains, printed periodically while relaying
+	RelayStatus { chain_a: String, height_a: String, chain_b: String, height_b: String },
+	/// Health checks of the configured chains
+	Doctor { reports: Vec<ChainReport> },
+	/// Path of the written key file
+	KeyFile { key_file: PathBuf },
+}
+
+impl Output {
+	/// Prints the output to stdout. Without `json`, only the WASM code id, the doctor reports and
+	/// the key file path are printed, the other results are logged by the subcommands. There is
+	/// no output for `fish`, it only logs.
+	pub fn print(&self, json: bool) -> Result<()> {
+		match self {
+			_ if json => println!("{}", serde_json::to_string(self)?),
+			Output::WasmCode { code_id } => println!("{code_id}"),
+			Output::Doctor { reports } => reports.iter().for_each(|report| print!("{report}")),
+			Output::KeyFile { key_file } => println!("{}", key_file.display()),
+			_ => (),
+		}
+		Ok(())
+	}
 }
 
 #[derive(Debug, Clone, Parser)]
@@ -98,7 +175,7 @@ pub struct UploadWasmCmd {
 }
 
 impl UploadWasmCmd {
-	pub async fn run(&self) -> Result<AnyConfig> {
+	pub async fn run(&self) -> Result<(AnyConfig, Output)> {
 		use tokio::fs::read_to_string;
 		let path: PathBuf = self.config.parse()?;
 		let file_content = read_to_string(path).await?;
@@ -107,9 +184,8 @@ impl UploadWasmCmd {
 		let wasm = tokio::fs::read(&self.wasm_path).await?;
 		let code_id = client.upload_wasm(wasm).await?;
 		let code_id_str = hex::encode(code_id);
-		println!("{code_id_str}");
-		config.set_wasm_code_id(code_id_str);
-		Ok(config)
+		config.set_wasm_code_id(code_id_str.clone());
+		Ok((config, Output::WasmCode { code_id: code_id_str }))
 	}
 
 	pub async fn save_config(&self, new_config: &AnyConfig) -> Result<()> {
@@ -118,7 +194,125 @@ impl UploadWasmCmd {
 	}
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct DoctorCmd {
+	/// Chain config path, can be repeated.
+	#[clap(long = "config", required = true)]
+	configs: Vec<String>,
+	/// Minimum balance of the signer in the fee denom. Only checked on Cosmos chains.
+	#[clap(long, default_value = "0")]
+	min_balance: u128,
+}
+
+impl DoctorCmd {
+	/// Runs the checks of every configured chain. Fails if any of the checks failed, after the
+	/// report is printed.
+	pub async fn run(&self, json: bool) -> Result<()> {
+		let mut reports = vec![];
+		for path in &self.configs {
+			let config = tokio::fs::read_to_string(path)
+				.await
+				.map_err(|e| anyhow!("Failed to read {path}: {e}"))
+				.and_then(|content| {
+					toml::from_str::<AnyConfig>(&content)
+						.map_err(|e| anyhow!("Failed to parse {path}: {e}"))
+				});
+			let report = match config {
+				Ok(config) => diagnose(path, config, self.min_balance).await,
+				Err(e) => {
+					let mut report = ChainReport::new(path.as_str());
+					report.record("config", Err(e.to_string()));
+					report
+				},
+			};
+			reports.push(report);
+		}
+
+		let failed = reports.iter().filter(|report| !report.passed()).count();
+		let total = reports.len();
+		Output::Doctor { reports }.print(json)?;
+		if failed != 0 {
+			return Err(anyhow!("{failed} of {total} chains failed the checks"))
+		}
+		Ok(())
+	}
+}
+
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Clone, Parser)]
+pub struct KeysCmd {
+	#[clap(subcommand)]
+	pub subcommand: KeysSubcommand,
+}
+
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Clone, Parser)]
+pub enum KeysSubcommand {
+	#[clap(
+		name = "encrypt",
+		about = "Encrypts a mnemonic read from stdin into a key file for the `key_file` option"
+	)]
+	Encrypt(EncryptKeyCmd),
+}
+
+#[cfg(feature = "cosmos")]
+#[derive(Debug, Clone, Parser)]
+pub struct EncryptKeyCmd {
+	/// Path of the key file to create, an existing file is not overwritten.
+	#[clap(long)]
+	out: PathBuf,
+	/// Name of the environment variable that holds the passphrase. The chain config refers to it
+	/// with `key_file_passphrase_env`.
+	#[clap(long)]
+	passphrase_env: String,
+}
+
+#[cfg(feature = "cosmos")]
+impl EncryptKeyCmd {
+	/// Reads the mnemonic from the first line of stdin and writes it to the key file, encrypted
+	/// with the passphrase.
+	pub fn run(&self) -> Result<Output> {
+		let mut mnemonic = String::new();
+		std::io::stdin().read_line(&mut mnemonic)?;
+		let mnemonic = mnemonic.trim();
+		if mnemonic.is_empty() {
+			return Err(anyhow!("Expected the mnemonic on stdin"))
+		}
+		let passphrase = read_env(&self.passphrase_env)?;
+		let key_file = EncryptedKeyFile::encrypt(mnemonic, passphrase.expose())?;
+
+		let mut options = std::fs::OpenOptions::new();
+		options.write(true).create_new(true);
+		#[cfg(unix)]
+		std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+		let mut file = options
+			.open(&self.out)
+			.map_err(|e| anyhow!("Failed to create key file {}: {e}", self.out.display()))?;
+		std::io::Write::write_all(&mut file, serde_json::to_string_pretty(&key_file)?.as_bytes())?;
+		log::info!(target: "hyperspace", "Wrote key file {}", self.out.display());
+		Ok(Output::KeyFile { key_file: self.out.clone() })
+	}
+}
+
+/// Prints the JSON Schema of the chain config files to stdout.
+pub fn print_config_schema() -> Result<()> {
+	println!("{}", serde_json::to_string_pretty(&generate_config_schema())?);
+	Ok(())
+}
+
 impl Cmd {
+	/// Validates the chain configs against the config schema.
+	fn validate_config(&self) -> Result<()> {
+		for path in [&self.config_a, &self.config_b] {
+			if let Err(violations) = validate_config_file(Path::new(path)) {
+				let violations =
+					violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n  ");
+				return Err(anyhow!("Invalid config {path}:\n  {violations}"))
+			}
+		}
+		Ok(())
+	}
+
 	async fn parse_config(&self) -> Result<Config> {
 		use tokio::fs::read_to_string;
 		let path_a: PathBuf = self.config_a.parse()?;
@@ -134,38 +328,57 @@ impl Cmd {
 		Ok(Config { chain_a: config_a, chain_b: config_b, core: config_core })
 	}
 
+	/// Verifies the client ids of both chains against the counterparty before relaying.
+	async fn reconcile_client_ids(
+		&self,
+		chain_a: &mut AnyChain,
+		chain_b: &mut AnyChain,
+	) -> Result<()> {
+		reconcile_client_id(chain_a, chain_b, self.auto_select_client).await?;
+		reconcile_client_id(chain_b, chain_a, self.auto_select_client).await?;
+		Ok(())
+	}
+
 	// todo: IntoClient, since clients are generic, users must configure clients themselves.
-	/// Run the command
-	pub async fn run(&self) -> Result<()> {
+	/// Run the command. With `json`, the latest heights of both chains are periodically printed
+	/// to stdout.
+	pub async fn run(&self, json: bool) -> Result<()> {
+		self.validate_config()?;
 		let config = self.parse_config().await?;
-		let chain_a = config.chain_a.into_client().await?;
-		let chain_b = config.chain_b.into_client().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		self.reconcile_client_ids(&mut chain_a, &mut chain_b).await?;
+
+		if json {
+			tokio::spawn(print_relay_status(chain_a.clone(), chain_b.clone()));
+		}
 
 		let registry =
 			Registry::new_custom(None, None).expect("this can only fail if the prefix is empty");
-		let metrics_a = Metrics::register(chain_a.name(), &registry)?;
-		let metrics_b = Metrics::register(chain_b.name(), &registry)?;
-		let mut metrics_handler_a = MetricsHandler::new(registry.clone(), metrics_a);
-		let mut metrics_handler_b = MetricsHandler::new(registry.clone(), metrics_b);
-		metrics_handler_a.link_with_counterparty(&mut metrics_handler_b);
+		let relayer = RelayerBuilder::new()
+			.with_chains(chain_a, chain_b)
+			.with_metrics(registry.clone())
+			.build()
+			.await?;
 
 		if let Some(addr) = config.core.prometheus_endpoint.and_then(|s| s.parse().ok()) {
 			tokio::spawn(init_prometheus(addr, registry.clone()));
 		}
 
-		relay(chain_a, chain_b, Some(metrics_handler_a), Some(metrics_handler_b), None).await
+		relayer.run().await
 	}
 
 	/// Run fisherman
 	pub async fn fish(&self) -> Result<()> {
 		let config = self.parse_config().await?;
-		let chain_a = config.chain_a.into_client().await?;
-		let chain_b = config.chain_b.into_client().await?;
+		let mut chain_a = config.chain_a.into_client().await?;
+		let mut chain_b = config.chain_b.into_client().await?;
+		self.reconcile_client_ids(&mut chain_a, &mut chain_b).await?;
 
 		fish(chain_a, chain_b).await
 	}
 
-	pub async fn create_clients(&self) -> Result<Config> {
+	pub async fn create_clients(&self) -> Result<(Config, Output)> {
 		let mut config = self.parse_config().await?;
 		let mut chain_a = config.chain_a.clone().into_client().await?;
 		let mut chain_b = config.chain_b.clone().into_client().await?;
@@ -184,13 +397,13 @@ impl Cmd {
 			chain_b.name(),
 			client_id_a_on_b
 		);
-		config.chain_a.set_client_id(client_id_a_on_b);
-		config.chain_b.set_client_id(client_id_b_on_a);
+		config.chain_a.set_client_id(client_id_a_on_b.clone());
+		config.chain_b.set_client_id(client_id_b_on_a.clone());
 
-		Ok(config)
+		Ok((config, Output::Clients { client_a: client_id_b_on_a, client_b: client_id_a_on_b }))
 	}
 
-	pub async fn create_connection(&self) -> Result<Config> {
+	pub async fn create_connection(&self) -> Result<(Config, Output)> {
 		let delay_period_seconds: NonZeroU64 = self
 			.delay_period
 			.expect("delay_period should be provided when creating a connection")
@@ -214,26 +427,31 @@ impl Cmd {
 		log::info!("ConnectionId on Chain {}: {}", chain_b.name(), connection_id_b);
 		handle.abort();
 
-		config.chain_a.set_connection_id(connection_id_a);
-		config.chain_b.set_connection_id(connection_id_b);
+		config.chain_a.set_connection_id(connection_id_a.clone());
+		config.chain_b.set_connection_id(connection_id_b.clone());
 
-		Ok(config)
+		let output =
+			Output::Connection { connection_a: connection_id_a, connection_b: connection_id_b };
+		Ok((config, output))
 	}
 
-	pub async fn create_channel(&self) -> Result<Config> {
-		let port_id = PortId::from_str(
-			self.port_id
-				.as_ref()
-				.expect("port_id must be specified when creating a channel")
-				.as_str(),
-		)
-		.expect("Port id was invalid");
+	pub async fn create_channel(&self) -> Result<(Config, Output)> {
+		let port_id = self
+			.port_id
+			.as_ref()
+			.ok_or_else(|| anyhow!("--port-id must be specified when creating a channel"))?;
+		let port_id =
+			PortId::from_str(port_id).map_err(|e| anyhow!("Invalid port id {port_id}: {e}"))?;
 		let version = self
 			.version
+			.clone()
+			.ok_or_else(|| anyhow!("--version must be specified when creating a channel"))?;
+		let order = self
+			.order
 			.as_ref()
-			.expect("version must be specified when creating a channel")
-			.clone();
-		let order = self.order.as_ref().expect("order must be specified when creating a channel, expected one of 'ordered' or 'unordered'").as_str();
+			.ok_or_else(|| anyhow!("--order must be specified when creating a channel"))?;
+		let order = Order::from_str(order)
+			.map_err(|_| anyhow!("Invalid order {order}, expected 'ordered' or 'unordered'"))?;
 		let mut config = self.parse_config().await?;
 		let mut chain_a = config.chain_a.clone().into_client().await?;
 		let mut chain_b = config.chain_b.clone().into_client().await?;
@@ -246,12 +464,13 @@ impl Cmd {
 				.unwrap();
 		});
 
-		let order = Order::from_str(order).expect("Expected one of 'ordered' or 'unordered'");
-		let connection_id = chain_a.connection_id().expect("Connection id should be defined");
+		let connection_id = chain_a
+			.connection_id()
+			.ok_or_else(|| anyhow!("connection_id of {} is not configured", chain_a.name()))?;
 		let (channel_id_a, channel_id_b) = create_channel(
 			&mut chain_a,
 			&mut chain_b,
-			connection_id,
+			connection_id.clone(),
 			port_id.clone(),
 			version,
 			order,
@@ -262,9 +481,16 @@ impl Cmd {
 		handle.abort();
 
 		config.chain_a.set_channel_whitelist(channel_id_a, port_id.clone());
-		config.chain_b.set_channel_whitelist(channel_id_b, port_id);
+		config.chain_b.set_channel_whitelist(channel_id_b, port_id.clone());
 
-		Ok(config)
+		let output = Output::Channel {
+			channel_a: channel_id_a,
+			channel_b: channel_id_b,
+			port_id,
+			connection_a: connection_id,
+			connection_b: chain_b.connection_id(),
+		};
+		Ok((config, output))
 	}
 
 	pub async fn save_config(&self, new_config: &Config) -> Result<()> {
@@ -275,8 +501,109 @@ impl Cmd {
 	}
 }
 
+/// Prints the latest heights of both chains every [`STATUS_INTERVAL`].
+async fn print_relay_status(chain_a: AnyChain, chain_b: AnyChain) {
+	let mut interval = tokio::time::interval(STATUS_INTERVAL);
+	loop {
+		interval.tick().await;
+		let status = Output::RelayStatus {
+			chain_a: chain_a.name().to_string(),
+			height_a: latest_height(&chain_a).await,
+			chain_b: chain_b.name().to_string(),
+			height_b: latest_height(&chain_b).await,
+		};
+		if let Err(e) = status.print(true) {
+			log::error!(target: "hyperspace", "Failed to print relay status: {:?}", e);
+		}
+	}
+}
+
+async fn latest_height(chain: &AnyChain) -> String {
+	match chain.latest_height_and_timestamp().await {
+		Ok((height, _)) => height.to_string(),
+		Err(e) => {
+			log::debug!(target: "hyperspace", "Failed to query the latest height of {}: {:?}", chain.name(), e);
+			"unknown".to_string()
+		},
+	}
+}
+
 async fn write_config(path: String, config: &AnyConfig) -> Result<()> {
 	tokio::fs::write(path.parse::<PathBuf>()?, toml::to_string(config)?)
 		.await
 		.map_err(|e| anyhow!(e))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn json_flag_is_accepted_after_subcommand() {
+		let cli = Cli::try_parse_from([
+			"hyperspace",
+			"create-clients",
+			"--config-a",
+			"a.toml",
+			"--config-b",
+			"b.toml",
+			"--config-core",
+			"core.toml",
+			"--json",
+		])
+		.unwrap();
+		assert!(cli.json);
+		assert!(matches!(cli.subcommand, Subcommand::CreateClients(_)));
+	}
+
+	#[cfg(feature = "cosmos")]
+	#[test]
+	fn keys_encrypt_is_a_subcommand() {
+		let cli = Cli::try_parse_from([
+			"hyperspace",
+			"keys",
+			"encrypt",
+			"--out",
+			"relayer.key",
+			"--passphrase-env",
+			"RELAYER_KEY_PASSPHRASE",
+		])
+		.unwrap();
+		let Subcommand::Keys(KeysCmd { subcommand: KeysSubcommand::Encrypt(cmd) }) = cli.subcommand
+		else {
+			panic!("expected keys encrypt")
+		};
+		assert_eq!(cmd.out, PathBuf::from("relayer.key"));
+		assert_eq!(cmd.passphrase_env, "RELAYER_KEY_PASSPHRASE");
+	}
+
+	#[test]
+	fn outputs_are_flat_json_objects() {
+		let output = Output::Clients {
+			client_a: ClientId::from_str("07-tendermint-5").unwrap(),
+			client_b: ClientId::from_str("08-wasm-2").unwrap(),
+		};
+		assert_eq!(
+			serde_json::to_string(&output).unwrap(),
+			r#"{"client_a":"07-tendermint-5","client_b":"08-wasm-2"}"#
+		);
+
+		let output = Output::Channel {
+			channel_a: ChannelId::new(0),
+			channel_b: ChannelId::new(3),
+			port_id: PortId::transfer(),
+			connection_a: ConnectionId::new(1),
+			connection_b: None,
+		};
+		assert_eq!(
+			serde_json::to_value(&output).unwrap(),
+			serde_json::json!({
+				"channel_a": "channel-0",
+				"channel_b": "channel-3",
+				"port_id": "transfer",
+				"connection_a": "connection-1",
+				"connection_b": null,
+			})
+		);
+	}
+}
@@ -0,0 +1,251 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Health checks of the chain configs, run by the `doctor` subcommand.
+
+use crate::chain::{AnyAssetId, AnyChain, AnyConfig};
+use ibc::{
+	applications::transfer::PrefixedCoin,
+	core::{
+		ics03_connection::connection::ConnectionEnd,
+		ics04_channel::channel::{ChannelEnd, State},
+	},
+	Height,
+};
+use primitives::{Chain, IbcProvider};
+use serde::Serialize;
+use std::{fmt, time::Instant};
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckResult {
+	pub check: String,
+	pub passed: bool,
+	pub detail: String,
+}
+
+/// Checks of one configured chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainReport {
+	pub chain: String,
+	pub checks: Vec<CheckResult>,
+}
+
+impl ChainReport {
+	pub fn new(chain: impl Into<String>) -> Self {
+		Self { chain: chain.into(), checks: vec![] }
+	}
+
+	/// Records the result of `check`, `Ok` and `Err` carry the detail shown in the report.
+	pub fn record(&mut self, check: &str, result: Result<String, String>) {
+		let (passed, detail) = match result {
+			Ok(detail) => (true, detail),
+			Err(detail) => (false, detail),
+		};
+		self.checks.push(CheckResult { check: check.to_string(), passed, detail });
+	}
+
+	pub fn passed(&self) -> bool {
+		self.checks.iter().all(|check| check.passed)
+	}
+}
+
+impl fmt::Display for ChainReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let status = if self.passed() { "PASS" } else { "FAIL" };
+		writeln!(f, "{} [{status}]", self.chain)?;
+		for check in &self.checks {
+			let status = if check.passed { "PASS" } else { "FAIL" };
+			writeln!(f, "  [{status}] {}: {}", check.check, check.detail)?;
+		}
+		Ok(())
+	}
+}
+
+/// Asset the signer pays fees with, if its balance can be queried for the chain type.
+fn fee_asset(config: &AnyConfig) -> Option<AnyAssetId> {
+	match config {
+		#[cfg(feature = "cosmos")]
+		AnyConfig::Cosmos(config) => Some(AnyAssetId::Cosmos(config.fee_denom.clone())),
+		_ => None,
+	}
+}
+
+/// Runs the checks of the chain configured by `config`: RPC reachability and latency, the signer
+/// balance and whether the configured connection and channels exist on chain and match the
+/// configured client id.
+pub async fn diagnose(name: &str, config: AnyConfig, min_balance: u128) -> ChainReport {
+	let fee_asset = fee_asset(&config);
+	let mut report = ChainReport::new(name);
+	let chain = match config.into_client().await {
+		Ok(chain) => chain,
+		Err(e) => {
+			report.record("connect", Err(format!("{e:?}")));
+			return report
+		},
+	};
+	report.chain = chain.name().to_string();
+
+	let started = Instant::now();
+	let height = match chain.latest_height_and_timestamp().await {
+		Ok((height, _)) => {
+			report.record(
+				"rpc",
+				Ok(format!("latest height {height} in {} ms", started.elapsed().as_millis())),
+			);
+			height
+		},
+		Err(e) => {
+			report.record("rpc", Err(format!("{e:?}")));
+			return report
+		},
+	};
+
+	let balance = match fee_asset {
+		Some(asset) => match chain.query_ibc_balance(asset).await {
+			Ok(coins) => check_balance(&coins, min_balance),
+			Err(e) => Err(format!("{e:?}")),
+		},
+		None => Ok("skipped, not supported for this chain type".to_string()),
+	};
+	report.record("signer balance", balance);
+
+	check_handshake(&chain, height, &mut report).await;
+	report
+}
+
+fn check_balance(coins: &[PrefixedCoin], min_balance: u128) -> Result<String, String> {
+	let amount = coins.first().map(|coin| coin.amount.as_u256()).unwrap_or_default();
+	let balance =
+		u128::try_from(amount).map_err(|_| format!("{amount} doesn't fit into 128 bits"))?;
+	if balance < min_balance {
+		return Err(format!("{balance} is below the threshold of {min_balance}"))
+	}
+	Ok(balance.to_string())
+}
+
+/// Checks that the configured connection and channels exist and belong together.
+async fn check_handshake(chain: &AnyChain, height: Height, report: &mut ChainReport) {
+	let Some(connection_id) = chain.connection_id() else {
+		report.record("connection", Ok("skipped, no connection id configured".to_string()));
+		return
+	};
+	let connection = chain
+		.query_connection_end(height, connection_id.clone())
+		.await
+		.map_err(|e| format!("{e:?}"))
+		.and_then(|response| {
+			response
+				.connection
+				.ok_or_else(|| format!("{connection_id} not found"))
+				.and_then(|connection| {
+					ConnectionEnd::try_from(connection).map_err(|e| format!("{e:?}"))
+				})
+		});
+	let connection = match connection {
+		Ok(connection) => connection,
+		Err(e) => {
+			report.record("connection", Err(e));
+			return
+		},
+	};
+	report.record("connection", Ok(format!("{connection_id} is {:?}", connection.state)));
+
+	let client_id = connection.counterparty().client_id();
+	let client = match chain.try_client_id() {
+		Ok(configured) if &configured == client_id =>
+			Ok(format!("{configured} is the counterparty client of {connection_id}")),
+		Ok(configured) => Err(format!(
+			"{configured} is configured, but the counterparty client of {connection_id} is {client_id}"
+		)),
+		Err(_) => Err(format!("not configured, {connection_id} uses {client_id}")),
+	};
+	report.record("client", client);
+
+	for (channel_id, port_id) in chain.channel_whitelist() {
+		let channel = chain
+			.query_channel_end(height, channel_id, port_id.clone())
+			.await
+			.map_err(|e| format!("{e:?}"))
+			.and_then(|response| {
+				response
+					.channel
+					.ok_or_else(|| format!("{channel_id}/{port_id} not found"))
+					.and_then(|channel| ChannelEnd::try_from(channel).map_err(|e| format!("{e:?}")))
+			})
+			.and_then(|channel| {
+				if channel.connection_hops.first() != Some(&connection_id) {
+					return Err(format!(
+						"{channel_id}/{port_id} is on {:?}, not {connection_id}",
+						channel.connection_hops
+					))
+				}
+				if channel.state != State::Open {
+					return Err(format!("{channel_id}/{port_id} is {:?}", channel.state))
+				}
+				let common_state = chain.common_state();
+				let direction = match (
+					common_state.relays_packets(channel_id, &port_id),
+					common_state.relays_acks(channel_id, &port_id),
+				) {
+					(true, true) => "",
+					(true, false) => ", acknowledgements are not relayed",
+					(false, true) => ", outgoing packets are not relayed",
+					(false, false) => ", only timeouts are relayed",
+				};
+				Ok(format!("{channel_id}/{port_id} is open{direction}"))
+			});
+		report.record("channel", channel);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn report_fails_if_any_check_fails() {
+		let mut healthy = ChainReport::new("picasso");
+		healthy.record("rpc", Ok("latest height 1-100 in 20 ms".to_string()));
+		healthy.record("signer balance", Ok("skipped, not supported for this chain type".into()));
+		assert!(healthy.passed());
+		assert_eq!(
+			healthy.to_string(),
+			"picasso [PASS]\n  [PASS] rpc: latest height 1-100 in 20 ms\n  [PASS] signer balance: skipped, not supported for this chain type\n"
+		);
+
+		let mut failing = ChainReport::new("centauri");
+		failing.record("rpc", Ok("latest height 1-5 in 900 ms".to_string()));
+		failing.record("signer balance", check_balance(&[], 1000));
+		assert!(!failing.passed());
+		assert!(failing.to_string().starts_with("centauri [FAIL]\n"));
+		assert!(failing
+			.to_string()
+			.contains("  [FAIL] signer balance: 0 is below the threshold of 1000\n"));
+	}
+
+	#[test]
+	fn balance_above_u128_fails() {
+		let coin = |amount: &str| PrefixedCoin {
+			denom: "stake".parse().unwrap(),
+			amount: amount.parse().unwrap(),
+		};
+		let max = u128::MAX.to_string();
+		assert_eq!(check_balance(&[coin(&max)], 1000), Ok(max));
+		assert_eq!(
+			check_balance(&[coin("340282366920938463463374607431768211456")], 1000),
+			Err("340282366920938463463374607431768211456 doesn't fit into 128 bits".to_string())
+		);
+	}
+}
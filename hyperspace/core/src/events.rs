@@ -44,7 +44,7 @@ use ibc::{
 };
 use ibc_proto::google::protobuf::Any;
 use pallet_ibc::light_clients::AnyClientState;
-use primitives::{error::Error, mock::LocalClientTypes, Chain};
+use primitives::{error::Error, mock::LocalClientTypes, summary::PacketSummary, Chain};
 use std::str::FromStr;
 use tendermint_proto::Protobuf;
 
@@ -512,7 +512,7 @@ pub async fn parse_events(
 				let value = msg.encode_vec()?;
 				let msg = Any { value, type_url: msg.type_url() };
 				messages.push(msg);
-				log::debug!(target: "hyperspace", "Sending packet {:?}", packet);
+				log::debug!(target: "hyperspace", "Sending {}", PacketSummary(&packet));
 			},
 			IbcEvent::WriteAcknowledgement(write_ack) => {
 				let port_id = &write_ack.packet.destination_port.clone();
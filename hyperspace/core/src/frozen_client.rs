@@ -0,0 +1,119 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use ibc::{core::ics02_client::client_state::ClientState, Height};
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::Chain;
+use std::time::{Duration, Instant};
+
+/// Interval at which a frozen client is checked for recovery.
+pub const FROZEN_CLIENT_POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Change of the frozen state reported by [`FrozenClientMonitor::update`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrozenClientChange {
+	/// The client was frozen at the given height.
+	Frozen(Height),
+	/// The client was frozen and is active again, e.g. after it was substituted.
+	Recovered,
+	Unchanged,
+}
+
+/// Pauses relaying from a chain while its client on the counterparty is frozen. Every message to
+/// a frozen client fails, so instead of relaying the client is only checked every
+/// `poll_interval` until it's recovered. Timeouts of packets sent from the chain are still
+/// relayed in the meantime, since they're submitted to the chain itself.
+#[derive(Debug, Clone)]
+pub struct FrozenClientMonitor {
+	pub poll_interval: Duration,
+	/// Frozen height of the client and when to check it again.
+	frozen: Option<(Height, Instant)>,
+}
+
+impl FrozenClientMonitor {
+	pub fn new(poll_interval: Duration) -> Self {
+		Self { poll_interval, frozen: None }
+	}
+
+	pub fn frozen_height(&self) -> Option<Height> {
+		self.frozen.map(|(height, _)| height)
+	}
+
+	/// Returns whether the client should be checked at `now`. An active client is checked in
+	/// every cycle, a frozen one once per `poll_interval`.
+	pub fn should_check(&self, now: Instant) -> bool {
+		self.frozen.map_or(true, |(_, next_check)| now >= next_check)
+	}
+
+	/// Records the `frozen_height` of the client checked at `now`.
+	pub fn update(&mut self, frozen_height: Option<Height>, now: Instant) -> FrozenClientChange {
+		let was_frozen = self.frozen.is_some();
+		self.frozen = frozen_height.map(|height| (height, now + self.poll_interval));
+		match (was_frozen, frozen_height) {
+			(false, Some(height)) => FrozenClientChange::Frozen(height),
+			(true, None) => FrozenClientChange::Recovered,
+			_ => FrozenClientChange::Unchanged,
+		}
+	}
+}
+
+/// Frozen height of the client that tracks `source` on `sink`, `None` if it's active.
+pub async fn query_frozen_height(
+	source: &impl Chain,
+	sink: &impl Chain,
+) -> anyhow::Result<Option<Height>> {
+	let client_id = source.try_client_id().map_err(|e| anyhow!("{e:?}"))?;
+	let (height, ..) = sink.latest_height_and_timestamp().await.map_err(|e| anyhow!("{e:?}"))?;
+	let response = sink
+		.query_client_state(height, client_id.clone())
+		.await
+		.map_err(|e| anyhow!("{e:?}"))?;
+	let client_state = response
+		.client_state
+		.ok_or_else(|| anyhow!("Client {client_id} not found on {}", sink.name()))?;
+	let client_state = AnyClientState::try_decode_recursive(client_state, |_| true)
+		.map_err(|e| anyhow!("Client {client_id} on {}: {e}", sink.name()))?;
+	Ok(client_state.frozen_height())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn frozen_client_is_polled_until_it_recovers() {
+		let mut monitor = FrozenClientMonitor::new(Duration::from_secs(300));
+		let now = Instant::now();
+		assert!(monitor.should_check(now));
+		assert_eq!(monitor.update(None, now), FrozenClientChange::Unchanged);
+
+		// the client is frozen mid-run, relaying pauses until the next poll
+		let frozen_at = Height::new(1, 120);
+		assert_eq!(monitor.update(Some(frozen_at), now), FrozenClientChange::Frozen(frozen_at));
+		assert_eq!(monitor.frozen_height(), Some(frozen_at));
+		assert!(!monitor.should_check(now + Duration::from_secs(299)));
+
+		let later = now + Duration::from_secs(300);
+		assert!(monitor.should_check(later));
+		assert_eq!(monitor.update(Some(frozen_at), later), FrozenClientChange::Unchanged);
+		assert!(!monitor.should_check(later + Duration::from_secs(1)));
+
+		// after the client is substituted, it's checked in every cycle again
+		let recovered = later + Duration::from_secs(300);
+		assert_eq!(monitor.update(None, recovered), FrozenClientChange::Recovered);
+		assert_eq!(monitor.frozen_height(), None);
+		assert!(monitor.should_check(recovered));
+	}
+}
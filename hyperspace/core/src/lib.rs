@@ -14,25 +14,56 @@
 
 #![warn(unused_variables)]
 
+pub mod builder;
 pub mod chain;
+pub mod circuit_breaker;
 pub mod command;
+pub mod doctor;
 pub mod events;
+pub mod frozen_client;
 pub mod logging;
 mod macros;
+pub mod middleware;
 pub mod packets;
 pub mod queue;
+pub mod reconcile;
 pub mod substrate;
 mod utils;
 
-use crate::utils::RecentStream;
+pub use builder::{Relayer, RelayerBuilder};
+pub use chain::{AnyChain, AnyConfig, Config, CoreConfig};
+#[cfg(feature = "cosmos")]
+pub use cosmos::client::CosmosClientConfig;
+pub use middleware::{PacketContext, PacketDecision, PacketKind, PacketMiddleware};
+pub use parachain::ParachainClientConfig;
+pub use primitives::CommonClientConfig;
+
+use crate::{
+	circuit_breaker::{CircuitBreaker, CircuitState},
+	frozen_client::{
+		query_frozen_height, FrozenClientChange, FrozenClientMonitor, FROZEN_CLIENT_POLL_INTERVAL,
+	},
+	middleware::MiddlewareStack,
+	utils::RecentStream,
+};
 use anyhow::anyhow;
 use events::{has_packet_events, parse_events};
 use futures::{future::ready, StreamExt, TryFutureExt};
 use ibc::{events::IbcEvent, Height};
 use ibc_proto::google::protobuf::Any;
 use metrics::handler::MetricsHandler;
-use primitives::{Chain, IbcProvider, UndeliveredType, UpdateType};
-use std::collections::HashSet;
+use primitives::{
+	find_suitable_proof_height_for_client, summary::AnySummary, Chain, IbcProvider,
+	UndeliveredType, UpdateType,
+};
+use std::{
+	collections::HashSet,
+	time::{Duration, Instant},
+};
+use tokio::{
+	task::JoinHandle,
+	time::{Interval, MissedTickBehavior},
+};
 
 #[derive(Copy, Debug, Clone)]
 pub enum Mode {
@@ -43,11 +74,35 @@ pub enum Mode {
 /// Core relayer loop, waits for new finality events and forwards any new [`ibc::IbcEvents`]
 /// to the counter party chain.
 pub async fn relay<A, B>(
+	chain_a: A,
+	chain_b: B,
+	chain_a_metrics: Option<MetricsHandler>,
+	chain_b_metrics: Option<MetricsHandler>,
+	mode: Option<Mode>,
+) -> Result<(), anyhow::Error>
+where
+	A: Chain,
+	B: Chain,
+{
+	relay_with_middlewares(
+		chain_a,
+		chain_b,
+		chain_a_metrics,
+		chain_b_metrics,
+		mode,
+		MiddlewareStack::default(),
+	)
+	.await
+}
+
+/// Same as [`relay`], but offers every packet to the given `middlewares` before relaying it.
+pub async fn relay_with_middlewares<A, B>(
 	mut chain_a: A,
 	mut chain_b: B,
 	mut chain_a_metrics: Option<MetricsHandler>,
 	mut chain_b_metrics: Option<MetricsHandler>,
 	mode: Option<Mode>,
+	middlewares: MiddlewareStack,
 ) -> Result<(), anyhow::Error>
 where
 	A: Chain,
@@ -56,6 +111,12 @@ where
 	let stream_a = RecentStream::new(chain_a.finality_notifications().await?);
 	let stream_b = RecentStream::new(chain_b.finality_notifications().await?);
 	let (mut chain_a_finality, mut chain_b_finality) = (stream_a, stream_b);
+	let mut chain_a_breaker = CircuitBreaker::new(&chain_a.common_state().circuit_breaker);
+	let mut chain_b_breaker = CircuitBreaker::new(&chain_b.common_state().circuit_breaker);
+	let mut chain_a_frozen = FrozenClientMonitor::new(FROZEN_CLIENT_POLL_INTERVAL);
+	let mut chain_b_frozen = FrozenClientMonitor::new(FROZEN_CLIENT_POLL_INTERVAL);
+	let mut chain_a_sweeper = timeout_sweeper(&chain_a, mode);
+	let mut chain_b_sweeper = timeout_sweeper(&chain_b, mode);
 
 	// Introduce altering between branches so that each branch gets a chance to execute first after
 	// another one
@@ -67,12 +128,20 @@ where
 			// new finality event from chain A
 			result = chain_a_finality.next(), if !first_executed => {
 				first_executed = true;
-				process_finality_event(&mut chain_a, &mut chain_b, &mut chain_a_metrics, mode, result, &mut chain_a_finality, &mut chain_b_finality).await?;
+				process_finality_event(&mut chain_a, &mut chain_b, &mut chain_a_metrics, &mut chain_a_breaker, &mut chain_a_frozen, &middlewares, mode, result, &mut chain_a_finality, &mut chain_b_finality).await?;
 			}
 			// new finality event from chain B
 			result = chain_b_finality.next() => {
 				first_executed = false;
-				process_finality_event(&mut chain_b, &mut chain_a, &mut chain_b_metrics, mode, result, &mut chain_b_finality, &mut chain_a_finality).await?;
+				process_finality_event(&mut chain_b, &mut chain_a, &mut chain_b_metrics, &mut chain_b_breaker, &mut chain_b_frozen, &middlewares, mode, result, &mut chain_b_finality, &mut chain_a_finality).await?;
+			}
+			// timeouts of the packets sent from chain A, in case no finality events arrive
+			_ = next_sweep(&mut chain_a_sweeper) => {
+				sweep_timeouts(&mut chain_a, &chain_b, &mut chain_a_metrics, &middlewares).await;
+			}
+			// timeouts of the packets sent from chain B
+			_ = next_sweep(&mut chain_b_sweeper) => {
+				sweep_timeouts(&mut chain_b, &chain_a, &mut chain_b_metrics, &middlewares).await;
 			}
 			else => {
 				first_executed = false;
@@ -81,6 +150,75 @@ where
 	}
 }
 
+/// Relayer loops of several chain pairs started by [`relay_multi`]. Every pair runs in its own
+/// task, so a pair that fails or is shut down doesn't affect the others.
+pub struct RelayPairs {
+	handles: Vec<(String, JoinHandle<Result<(), anyhow::Error>>)>,
+}
+
+impl RelayPairs {
+	/// Names of the pairs, in the `{chain_a}-{chain_b}` format.
+	pub fn names(&self) -> Vec<String> {
+		self.handles.iter().map(|(name, _)| name.clone()).collect()
+	}
+
+	/// Stops the relayer loop of the pair `name`, the other pairs keep running. Returns `false`
+	/// if there is no such pair.
+	pub fn shutdown(&mut self, name: &str) -> bool {
+		match self.handles.iter().position(|(pair, _)| pair == name) {
+			Some(index) => {
+				let (_, handle) = self.handles.remove(index);
+				handle.abort();
+				log::info!(target: "hyperspace", "Relayer for {name} was shut down");
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Stops the relayer loops of all pairs.
+	pub fn shutdown_all(&mut self) {
+		for (_, handle) in self.handles.drain(..) {
+			handle.abort();
+		}
+	}
+
+	/// Waits until the relayer loops of all pairs exit and returns their results.
+	pub async fn join(self) -> Vec<(String, Result<(), anyhow::Error>)> {
+		let mut results = Vec::with_capacity(self.handles.len());
+		for (name, handle) in self.handles {
+			let result = handle.await.unwrap_or_else(|e| Err(anyhow!("Relayer task failed: {e}")));
+			if let Err(e) = &result {
+				log::error!(target: "hyperspace", "Relayer for {name} exited: {e:?}");
+			}
+			results.push((name, result));
+		}
+		results
+	}
+}
+
+/// Runs the [`relay`] loop for every pair of chains, e.g. for a hub chain connected to several
+/// other chains.
+///
+/// Clones of a chain client share their RPC connections, so a hub can be passed in multiple
+/// pairs by cloning it. The client id, connection id and channel whitelist refer to a single
+/// counterparty though, so the hub client of every pair should be created from its own config.
+pub fn relay_multi<A, B>(pairs: Vec<(A, B)>, mode: Option<Mode>) -> RelayPairs
+where
+	A: Chain,
+	B: Chain,
+{
+	let handles = pairs
+		.into_iter()
+		.map(|(chain_a, chain_b)| {
+			let name = format!("{}-{}", chain_a.name(), chain_b.name());
+			log::info!(target: "hyperspace", "Starting relayer for {name}");
+			(name, tokio::spawn(relay(chain_a, chain_b, None, None, mode)))
+		})
+		.collect();
+	RelayPairs { handles }
+}
+
 pub async fn fish<A, B>(chain_a: A, chain_b: B) -> Result<(), anyhow::Error>
 where
 	A: Chain,
@@ -88,18 +226,19 @@ where
 	B: Chain,
 	B::Error: From<A::Error>,
 {
+	let (client_id_a, client_id_b) = (chain_a.try_client_id()?, chain_b.try_client_id()?);
 	// we only care about events where the counterparty light client is updated.
 	let (mut chain_a_client_updates, mut chain_b_client_updates) = (
 		chain_a.ibc_events().await.filter_map(|ev| {
 			ready(match ev {
-				IbcEvent::UpdateClient(update) if chain_b.client_id() == *update.client_id() =>
+				IbcEvent::UpdateClient(update) if client_id_b == *update.client_id() =>
 					Some(update),
 				_ => None,
 			})
 		}),
 		chain_b.ibc_events().await.filter_map(|ev| {
 			ready(match ev {
-				IbcEvent::UpdateClient(update) if chain_a.client_id() == *update.client_id() =>
+				IbcEvent::UpdateClient(update) if client_id_a == *update.client_id() =>
 					Some(update),
 				_ => None,
 			})
@@ -145,6 +284,9 @@ async fn process_finality_event<A: Chain, B: Chain>(
 	source: &mut A,
 	sink: &mut B,
 	metrics: &mut Option<MetricsHandler>,
+	breaker: &mut CircuitBreaker,
+	frozen: &mut FrozenClientMonitor,
+	middlewares: &MiddlewareStack,
 	mode: Option<Mode>,
 	result: Option<A::FinalityEvent>,
 	stream_source: &mut RecentStream<A::FinalityEvent>,
@@ -176,14 +318,44 @@ async fn process_finality_event<A: Chain, B: Chain>(
 			};
 		},
 		Some(finality_event) => {
+			#[cfg(feature = "testing")]
+			if send_packet_relay::is_halted(source.name()) {
+				log::debug!(target: "hyperspace", "{} is halted, skipping finality notification", source.name());
+				return Ok(())
+			}
+			if !breaker.allow(Instant::now()) {
+				log::debug!(target: "hyperspace", "Circuit breaker of {} is open, skipping finality notification", source.name());
+				return Ok(())
+			}
+			if !counterparty_client_active(source, sink, metrics, frozen).await {
+				log::debug!(target: "hyperspace", "Client of {} on {} is frozen, only relaying timeouts", source.name(), sink.name());
+				// timeouts are submitted to `source` and proven against its client of `sink`,
+				// which the frozen client doesn't affect
+				sweep_timeouts(source, &*sink, metrics, middlewares).await;
+				return Ok(())
+			}
 			log::info!("=======================================================");
 			log::info!("Received finality notification from {}", source.name(),);
 
-			let result =
-				process_some_finality_event(source, sink, metrics, mode, finality_event).await;
+			let cycle = process_some_finality_event(
+				source,
+				sink,
+				metrics,
+				middlewares,
+				mode,
+				finality_event,
+			);
+			let result = if breaker.state() == CircuitState::HalfOpen {
+				tokio::time::timeout(breaker.half_open_timeout, cycle)
+					.await
+					.unwrap_or_else(|_| Err(anyhow!("Trial cycle of {} timed out", source.name())))
+			} else {
+				cycle.await
+			};
 
 			match result {
 				Ok(()) => {
+					breaker.on_success();
 					let sink_initial_rpc_call_delay = sink.initial_rpc_call_delay();
 					let source_initial_rpc_call_delay = source.initial_rpc_call_delay();
 					sink.set_rpc_call_delay(sink_initial_rpc_call_delay);
@@ -191,6 +363,13 @@ async fn process_finality_event<A: Chain, B: Chain>(
 				},
 				Err(e) => {
 					log::error!("{}", e);
+					if breaker.on_failure(Instant::now()) {
+						log::warn!(
+							target: "hyperspace",
+							"Circuit breaker of {} opened, pausing relaying for {:?}",
+							source.name(), breaker.reset_timeout
+						);
+					}
 					match sink.handle_error(&e).and_then(|_| source.handle_error(&e)).await {
 						Ok(_) => (),
 						Err(e) => {
@@ -199,15 +378,66 @@ async fn process_finality_event<A: Chain, B: Chain>(
 					}
 				},
 			}
+			if let Some(metrics) = metrics.as_ref() {
+				metrics.set_circuit_state(breaker.state().as_metric());
+			}
 		},
 	}
 	Ok(())
 }
 
+/// Checks whether the client of `source` on `sink` is frozen. Every message to a frozen client
+/// fails, so relaying from `source` is paused until the client is recovered, e.g. by a governance
+/// substitution. Only timeouts, which are submitted to `source`, are still relayed. A frozen client
+/// is only checked once per poll interval.
+async fn counterparty_client_active<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+	metrics: &Option<MetricsHandler>,
+	monitor: &mut FrozenClientMonitor,
+) -> bool {
+	let now = Instant::now();
+	if !monitor.should_check(now) {
+		return false
+	}
+	let frozen_height = match query_frozen_height(source, sink).await {
+		Ok(frozen_height) => frozen_height,
+		Err(e) => {
+			log::debug!(target: "hyperspace", "Failed to check whether the client of {} is frozen: {:?}", source.name(), e);
+			return monitor.frozen_height().is_none()
+		},
+	};
+	match monitor.update(frozen_height, now) {
+		FrozenClientChange::Frozen(height) => {
+			log::error!(
+				target: "hyperspace",
+				"Client of {} on {} is frozen at {}, relaying from {} is paused until the client is recovered",
+				source.name(), sink.name(), height, source.name()
+			);
+			if let Some(metrics) = metrics.as_ref() {
+				metrics.set_counterparty_client_frozen(true);
+			}
+		},
+		FrozenClientChange::Recovered => {
+			log::info!(
+				target: "hyperspace",
+				"Client of {} on {} is no longer frozen, resuming relaying",
+				source.name(), sink.name()
+			);
+			if let Some(metrics) = metrics.as_ref() {
+				metrics.set_counterparty_client_frozen(false);
+			}
+		},
+		FrozenClientChange::Unchanged => (),
+	}
+	monitor.frozen_height().is_none()
+}
+
 async fn process_some_finality_event<A: Chain, B: Chain>(
 	source: &mut A,
 	sink: &mut B,
 	metrics: &mut Option<MetricsHandler>,
+	middlewares: &MiddlewareStack,
 	mode: Option<Mode>,
 	finality_event: <A as IbcProvider>::FinalityEvent,
 ) -> anyhow::Result<()> {
@@ -218,8 +448,8 @@ async fn process_some_finality_event<A: Chain, B: Chain>(
 	log::trace!(target: "hyperspace", "Received updates count: {}", updates.len());
 	// query packets that can now be sent, at this sink height because of connection
 	// delay.
-	let (ready_packets, timeout_msgs) =
-		packets::query_ready_and_timed_out_packets(&*source, &*sink)
+	let (mut ready_packets, mut timeout_msgs, required_client_height) =
+		packets::query_ready_and_timed_out_packets(&*source, &*sink, middlewares)
 			.await
 			.map_err(|e| anyhow!("Failed to parse events: {:?}", e))?;
 
@@ -247,13 +477,99 @@ async fn process_some_finality_event<A: Chain, B: Chain>(
 		timeout_msgs.len()
 	);
 
-	process_updates(source, sink, metrics, mode, updates, &mut msgs).await?;
+	let latest_update_height =
+		process_updates(source, sink, metrics, mode, updates, &mut msgs).await?;
+
+	// Packets that wait for a client update can be proven as soon as the sink has a consensus
+	// state at their height, so submit the updates first and query the packets again instead of
+	// waiting for the next finality event.
+	if let (Some(required_height), Some(max_proof_age)) =
+		(required_client_height, source.common_state().max_proof_age)
+	{
+		let is_reached = latest_update_height.map_or(false, |height| height >= required_height);
+		if is_reached ||
+			historical_client_update(source, sink, required_height, max_proof_age, &mut msgs)
+				.await?
+		{
+			log::info!(
+				target: "hyperspace",
+				"Updating the client of {} on {} before relaying packets from {}",
+				source.name(), sink.name(), required_height
+			);
+			process_messages(sink, metrics, std::mem::take(&mut msgs)).await?;
+			(ready_packets, timeout_msgs, _) =
+				packets::query_ready_and_timed_out_packets(&*source, &*sink, middlewares)
+					.await
+					.map_err(|e| anyhow!("Failed to parse events: {:?}", e))?;
+		}
+	}
+
+	let decisions = middlewares.take_decisions();
+	if let Some(metrics) = metrics.as_ref() {
+		metrics.record_packet_decisions(decisions.held, decisions.dropped);
+	}
 
 	msgs.extend(ready_packets);
 
-	process_messages(sink, metrics, msgs).await?;
+	let result = process_messages(sink, metrics, msgs).await;
+	// Timeouts are submitted to the source, so they don't depend on the sink accepting messages,
+	// e.g. while it's halted
 	process_timeouts(source, metrics, timeout_msgs).await?;
-	Ok(())
+	result
+}
+
+/// Adds the client updates that let `sink` verify proofs of `source` at `height` to `msgs`, if
+/// the sink has no consensus state there and `height` is at most `max_proof_age` blocks old.
+/// Returns whether updates were added.
+async fn historical_client_update<A: Chain, B: Chain>(
+	source: &A,
+	sink: &B,
+	height: Height,
+	max_proof_age: u64,
+	msgs: &mut Vec<Any>,
+) -> anyhow::Result<bool> {
+	let (source_height, ..) = source.latest_height_and_timestamp().await?;
+	if source_height.revision_height.saturating_sub(height.revision_height) > max_proof_age {
+		log::debug!(
+			target: "hyperspace",
+			"Not updating the client of {} on {} to {}, it's older than {} blocks",
+			source.name(), sink.name(), height, max_proof_age
+		);
+		return Ok(false)
+	}
+
+	let client_id = source.try_client_id()?;
+	let (sink_height, ..) = sink.latest_height_and_timestamp().await?;
+	let has_consensus_state = find_suitable_proof_height_for_client(
+		source,
+		sink,
+		sink_height,
+		client_id.clone(),
+		height,
+		None,
+		height,
+	)
+	.await
+	.is_some();
+	if has_consensus_state {
+		return Ok(false)
+	}
+
+	match source.query_client_updates_at(sink, height).await {
+		Ok(updates) => {
+			log::info!(
+				target: "hyperspace",
+				"Built {} updates of {client_id} on {} for packets from {}",
+				updates.len(), sink.name(), height
+			);
+			msgs.extend(updates);
+			Ok(true)
+		},
+		Err(e) => {
+			log::debug!(target: "hyperspace", "Can't update the client of {} on {} to {}: {e:?}", source.name(), sink.name(), height);
+			Ok(false)
+		},
+	}
 }
 
 async fn process_updates<A: Chain, B: Chain>(
@@ -263,7 +579,7 @@ async fn process_updates<A: Chain, B: Chain>(
 	mode: Option<Mode>,
 	updates: Vec<(Any, Height, Vec<IbcEvent>, UpdateType)>,
 	msgs: &mut Vec<Any>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<Height>> {
 	// for timeouts we need both chains to be up to date
 	let sink_has_undelivered_acks = sink.has_undelivered_sequences(UndeliveredType::Recvs) ||
 		sink.has_undelivered_sequences(UndeliveredType::Acks) ||
@@ -277,6 +593,7 @@ async fn process_updates<A: Chain, B: Chain>(
 			HashSet::new()
 		};
 
+	let mut latest_update_height = None;
 	for (msg_update_client, height, events, update_type) in updates {
 		if let Some(metrics) = metrics.as_mut() {
 			if let Err(e) = metrics.handle_events(events.as_slice()).await {
@@ -327,10 +644,12 @@ async fn process_updates<A: Chain, B: Chain>(
 				},
 			_ => log::info!("Received finalized events from: {} {event_types:#?}", source.name()),
 		};
+		log::debug!(target: "hyperspace", "Client update for {}: {}", sink.name(), AnySummary(&msg_update_client));
 		msgs.push(msg_update_client);
 		msgs.append(&mut messages);
+		latest_update_height = latest_update_height.max(Some(height));
 	}
-	Ok(())
+	Ok(latest_update_height)
 }
 
 async fn process_messages<B: Chain>(
@@ -339,20 +658,74 @@ async fn process_messages<B: Chain>(
 	msgs: Vec<Any>,
 ) -> anyhow::Result<()> {
 	if !msgs.is_empty() {
+		#[cfg(feature = "testing")]
+		if send_packet_relay::is_halted(sink.name()) {
+			return Err(anyhow!("{} is halted", sink.name()))
+		}
 		if let Some(metrics) = metrics.as_ref() {
 			metrics.handle_messages(msgs.as_slice()).await;
 		}
 		let type_urls = msgs.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
 		log::info!("Submitting messages to {}: {type_urls:#?}", sink.name());
 
-		queue::flush_message_batch(msgs, metrics.as_ref(), &*sink)
-			.await
-			.map_err(|e| anyhow!("Failed to submit messages: {:?}", e))?;
+		let result = queue::flush_message_batch(msgs, metrics.as_ref(), &*sink).await;
+		queue::record_excluded_messages(metrics.as_ref(), &*sink);
+		result.map_err(|e| anyhow!("Failed to submit messages: {:?}", e))?;
 		log::debug!(target: "hyperspace", "Successfully submitted messages to {}", sink.name());
 	}
 	Ok(())
 }
 
+/// Interval of the timeout sweeper of `chain`, if it's enabled.
+fn timeout_sweeper(chain: &impl Chain, mode: Option<Mode>) -> Option<Interval> {
+	if matches!(mode, Some(Mode::Light)) {
+		return None
+	}
+	let period = Duration::from_secs(chain.common_state().timeout_sweep_interval?.get());
+	let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+	interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+	Some(interval)
+}
+
+/// Waits for the next tick of the sweeper, or forever if the sweeper is disabled.
+async fn next_sweep(sweeper: &mut Option<Interval>) {
+	match sweeper {
+		Some(interval) => {
+			interval.tick().await;
+		},
+		None => futures::future::pending().await,
+	}
+}
+
+/// Submits the timeouts of packets sent from `source` that can be proven with the current state
+/// of the `sink`'s client on `source`.
+///
+/// Timeouts are otherwise only relayed while processing finality events of `source`, which stop
+/// when its finality stream stalls or its circuit breaker is open, e.g. because the halted `sink`
+/// rejects every message, or while the client of `source` on `sink` is frozen. The sweep only
+/// queries the `sink` and never submits to it. Packets that are ready to be received are left to
+/// the next cycle. Failures are logged, the sweep is retried on the next tick.
+async fn sweep_timeouts<A: Chain, B: Chain>(
+	source: &mut A,
+	sink: &B,
+	metrics: &mut Option<MetricsHandler>,
+	middlewares: &MiddlewareStack,
+) {
+	log::debug!(target: "hyperspace", "Sweeping timed out packets of {}", source.name());
+	let result = async {
+		let timeout_msgs = packets::query_timed_out_packets(&*source, sink, middlewares).await?;
+		let decisions = middlewares.take_decisions();
+		if let Some(metrics) = metrics.as_ref() {
+			metrics.record_packet_decisions(decisions.held, decisions.dropped);
+		}
+		process_timeouts(source, metrics, timeout_msgs).await
+	}
+	.await;
+	if let Err(e) = result {
+		log::warn!(target: "hyperspace", "Failed to sweep timed out packets of {}: {e:?}", source.name());
+	}
+}
+
 async fn process_timeouts<A: Chain>(
 	source: &mut A,
 	metrics: &mut Option<MetricsHandler>,
@@ -364,9 +737,9 @@ async fn process_timeouts<A: Chain>(
 		}
 		let type_urls = timeout_msgs.iter().map(|msg| msg.type_url.as_str()).collect::<Vec<_>>();
 		log::info!("Submitting timeout messages to {}: {type_urls:#?}", source.name());
-		queue::flush_message_batch(timeout_msgs, metrics.as_ref(), &*source)
-			.await
-			.map_err(|e| anyhow!("Failed to submit timeout messages: {:?}", e))?;
+		let result = queue::flush_message_batch(timeout_msgs, metrics.as_ref(), &*source).await;
+		queue::record_excluded_messages(metrics.as_ref(), &*source);
+		result.map_err(|e| anyhow!("Failed to submit timeout messages: {:?}", e))?;
 		log::debug!(target: "hyperspace", "Successfully submitted timeout messages to {}", source.name());
 	}
 	Ok(())
@@ -405,8 +778,13 @@ async fn find_mandatory_heights_for_undelivered_sequences<A: Chain>(
 
 #[cfg(feature = "testing")]
 pub mod send_packet_relay {
-	use std::sync::atomic::{AtomicBool, Ordering};
+	use std::sync::{
+		atomic::{AtomicBool, Ordering},
+		Mutex,
+	};
 	static RELAY_PACKETS: AtomicBool = AtomicBool::new(true);
+	static RELAY_TIMEOUTS: AtomicBool = AtomicBool::new(true);
+	static HALTED_CHAIN: Mutex<Option<String>> = Mutex::new(None);
 
 	/// Returns status of send packet relay
 	pub fn packet_relay_status() -> bool {
@@ -417,4 +795,25 @@ pub mod send_packet_relay {
 	pub fn set_relay_status(status: bool) {
 		RELAY_PACKETS.store(status, Ordering::SeqCst);
 	}
+
+	/// Returns status of timeout relay
+	pub fn timeout_relay_status() -> bool {
+		RELAY_TIMEOUTS.load(Ordering::SeqCst)
+	}
+
+	/// Sets timeout relay status
+	pub fn set_timeout_relay_status(status: bool) {
+		RELAY_TIMEOUTS.store(status, Ordering::SeqCst);
+	}
+
+	/// Returns whether the chain named `name` is treated as halted. The finality events of a
+	/// halted chain are ignored and messages to it are rejected.
+	pub fn is_halted(name: &str) -> bool {
+		HALTED_CHAIN.lock().unwrap().as_deref() == Some(name)
+	}
+
+	/// Sets the chain that is treated as halted, `None` resumes it
+	pub fn set_halted_chain(name: Option<String>) {
+		*HALTED_CHAIN.lock().unwrap() = name;
+	}
 }
@@ -14,6 +14,8 @@
 
 use log::LevelFilter;
 
+/// Logs are written to stderr, env_logger's default target, so that stdout only contains the
+/// output of the subcommands.
 pub fn setup_logging() {
 	env_logger::builder()
 		.filter_module("hyper", LevelFilter::Info)
@@ -18,7 +18,7 @@ macro_rules! chains {
         $(#[$($meta:meta)*])*
 		$name:ident($config:path, $client:path),
 	)*) => {
-		#[derive(Debug, Serialize, Deserialize, Clone)]
+		#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 		#[serde(tag = "type", rename_all = "snake_case")]
 		#[allow(clippy::large_enum_variant)]
 		pub enum AnyConfig {
@@ -107,6 +107,23 @@ macro_rules! chains {
 				}
 			}
 
+			async fn query_client_updates_at<T>(
+				&self,
+				counterparty: &T,
+				height: Height,
+			) -> Result<Vec<Any>, anyhow::Error>
+			where
+				T: Chain,
+			{
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain.query_client_updates_at(counterparty, height).await,
+					)*
+					AnyChain::Wasm(c) => c.inner.query_client_updates_at(counterparty, height).await,
+				}
+			}
+
 			async fn ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>> {
 				match self {
 					$(
@@ -366,7 +383,7 @@ macro_rules! chains {
 				}
 			}
 
-			fn channel_whitelist(&self) -> std::collections::HashSet<(ChannelId, PortId)> {
+			fn channel_whitelist(&self) -> std::collections::BTreeSet<(ChannelId, PortId)> {
 				match self {
 					$(
 						$(#[$($meta)*])*
@@ -488,6 +505,25 @@ macro_rules! chains {
 				}
 			}
 
+			async fn query_ibc_balance_at_height(
+				&self,
+				asset_id: AnyAssetId,
+				at: Height,
+			) -> Result<Vec<PrefixedCoin>, Self::Error> {
+				match (self, asset_id) {
+					$(
+						$(#[$($meta)*])*
+						(Self::$name(chain), AnyAssetId::$name(asset_id)) => chain
+							.query_ibc_balance_at_height(asset_id.into(), at)
+							.await
+							.map_err(AnyError::$name),
+					)*
+					(Self::Wasm(c), asset_id) => c.inner.query_ibc_balance_at_height(asset_id, at).await,
+					(chain, _) =>
+						panic!("query_ibc_balance_at_height is not implemented for {}", chain.name()),
+				}
+			}
+
 			fn connection_prefix(&self) -> CommitmentPrefix {
 				match self {
 					$(
@@ -508,6 +544,16 @@ macro_rules! chains {
 				}
 			}
 
+			fn try_client_id(&self) -> Result<ClientId, Self::Error> {
+				match self {
+					$(
+						$(#[$($meta)*])*
+						Self::$name(chain) => chain.try_client_id().map_err(AnyError::$name),
+					)*
+					AnyChain::Wasm(c) => c.inner.try_client_id(),
+				}
+			}
+
 			fn set_client_id(&mut self, client_id: ClientId) {
 				match self {
 					$(
@@ -683,7 +729,7 @@ macro_rules! chains {
 				}
 			}
 
-			fn set_channel_whitelist(&mut self, channel_whitelist: std::collections::HashSet<(ChannelId, PortId)>) {
+			fn set_channel_whitelist(&mut self, channel_whitelist: std::collections::BTreeSet<(ChannelId, PortId)>) {
 				match self {
 					$(
 						$(#[$($meta)*])*
@@ -0,0 +1,373 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hooks that let library users inspect packets before the relayer builds messages for them.
+
+use ibc::core::{
+	ics04_channel::packet::{Packet, Sequence},
+	ics24_host::identifier::{ChannelId, PortId},
+};
+use std::{
+	collections::{HashSet, VecDeque},
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+};
+
+/// Message the relayer is about to build for a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketKind {
+	/// `MsgRecvPacket` to the sink
+	Recv,
+	/// `MsgAcknowledgement` to the sink
+	Ack,
+	/// `MsgTimeout` to the source
+	Timeout,
+}
+
+/// A packet offered to the [`PacketMiddleware`]s. It borrows the packet, so that its data isn't
+/// copied for every middleware.
+#[derive(Debug, Clone, Copy)]
+pub struct PacketContext<'a> {
+	pub kind: PacketKind,
+	/// Name of the chain the packet event was emitted on
+	pub source: &'a str,
+	/// Name of the chain the packet is relayed to
+	pub sink: &'a str,
+	pub packet: &'a Packet,
+}
+
+/// What the relayer should do with a packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PacketDecision {
+	/// Build and submit the message.
+	Forward,
+	/// Skip the packet in this cycle, it's offered to the middlewares again in the next one.
+	Hold,
+	/// Never relay the packet.
+	Drop { reason: String },
+}
+
+/// Custom logic that runs before the relayer builds a message for a packet, acknowledgement or
+/// timeout, e.g. to collect metrics or to divert packets to a manual approval queue.
+#[async_trait::async_trait]
+pub trait PacketMiddleware: Send + Sync {
+	async fn on_packet(&self, ctx: &PacketContext<'_>) -> PacketDecision;
+}
+
+/// Logs every packet and forwards it. Used when no middlewares are registered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoggingMiddleware;
+
+#[async_trait::async_trait]
+impl PacketMiddleware for LoggingMiddleware {
+	async fn on_packet(&self, ctx: &PacketContext<'_>) -> PacketDecision {
+		log::debug!(
+			target: "hyperspace",
+			"Relaying {:?} of packet {} on {}/{} from {} to {}",
+			ctx.kind, ctx.packet.sequence, ctx.packet.source_channel, ctx.packet.source_port,
+			ctx.source, ctx.sink
+		);
+		PacketDecision::Forward
+	}
+}
+
+/// Number of packets held and dropped by the middlewares since the last
+/// [`MiddlewareStack::take_decisions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecisionCounts {
+	pub held: u64,
+	pub dropped: u64,
+}
+
+/// Maximum number of dropped packets that are remembered, the oldest ones are offered to the
+/// middlewares again once it's exceeded.
+pub const MAX_DROPPED_PACKETS: usize = 10_000;
+
+/// Identifies a dropped packet. One stack serves both directions of a relayer, so the key includes
+/// the chain the packet was sent from and both channel ends.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DroppedPacket {
+	kind: PacketKind,
+	source: String,
+	source_channel: ChannelId,
+	source_port: PortId,
+	destination_channel: ChannelId,
+	destination_port: PortId,
+	sequence: Sequence,
+}
+
+impl DroppedPacket {
+	fn new(ctx: &PacketContext) -> Self {
+		Self {
+			kind: ctx.kind,
+			source: ctx.source.to_string(),
+			source_channel: ctx.packet.source_channel,
+			source_port: ctx.packet.source_port.clone(),
+			destination_channel: ctx.packet.destination_channel,
+			destination_port: ctx.packet.destination_port.clone(),
+			sequence: ctx.packet.sequence,
+		}
+	}
+}
+
+/// Dropped packets in the order they were dropped, bounded by [`MAX_DROPPED_PACKETS`].
+#[derive(Debug, Default)]
+struct DroppedPackets {
+	keys: HashSet<DroppedPacket>,
+	order: VecDeque<DroppedPacket>,
+}
+
+impl DroppedPackets {
+	fn contains(&self, key: &DroppedPacket) -> bool {
+		self.keys.contains(key)
+	}
+
+	fn insert(&mut self, key: DroppedPacket) {
+		if !self.keys.insert(key.clone()) {
+			return
+		}
+		self.order.push_back(key);
+		while self.order.len() > MAX_DROPPED_PACKETS {
+			if let Some(oldest) = self.order.pop_front() {
+				self.keys.remove(&oldest);
+			}
+		}
+	}
+}
+
+/// The middlewares of a relayer, consulted in the order they were registered. The first decision
+/// other than [`PacketDecision::Forward`] wins.
+///
+/// Dropped packets are remembered and not offered to the middlewares again, up to
+/// [`MAX_DROPPED_PACKETS`] of them.
+#[derive(Clone)]
+pub struct MiddlewareStack {
+	middlewares: Arc<Vec<Arc<dyn PacketMiddleware>>>,
+	dropped: Arc<Mutex<DroppedPackets>>,
+	held_count: Arc<AtomicU64>,
+	dropped_count: Arc<AtomicU64>,
+}
+
+impl fmt::Debug for MiddlewareStack {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MiddlewareStack")
+			.field("middlewares", &self.middlewares.len())
+			.finish()
+	}
+}
+
+impl Default for MiddlewareStack {
+	fn default() -> Self {
+		Self::new(vec![Arc::new(LoggingMiddleware)])
+	}
+}
+
+impl MiddlewareStack {
+	pub fn new(middlewares: Vec<Arc<dyn PacketMiddleware>>) -> Self {
+		Self {
+			middlewares: Arc::new(middlewares),
+			dropped: Default::default(),
+			held_count: Default::default(),
+			dropped_count: Default::default(),
+		}
+	}
+
+	/// Asks the middlewares what to do with the packet.
+	pub async fn decide(&self, ctx: &PacketContext<'_>) -> PacketDecision {
+		let key = DroppedPacket::new(ctx);
+		if self.dropped.lock().unwrap().contains(&key) {
+			return PacketDecision::Drop { reason: "dropped in an earlier cycle".to_string() }
+		}
+
+		for middleware in self.middlewares.iter() {
+			match middleware.on_packet(ctx).await {
+				PacketDecision::Forward => continue,
+				PacketDecision::Hold => {
+					log::info!(
+						target: "hyperspace",
+						"{:?} of packet {} on {}/{} from {} is held by a middleware",
+						ctx.kind, ctx.packet.sequence, ctx.packet.source_channel, ctx.packet.source_port, ctx.source
+					);
+					self.held_count.fetch_add(1, Ordering::SeqCst);
+					return PacketDecision::Hold
+				},
+				PacketDecision::Drop { reason } => {
+					log::warn!(
+						target: "hyperspace",
+						"{:?} of packet {} on {}/{} from {} is dropped by a middleware: {reason}",
+						ctx.kind, ctx.packet.sequence, ctx.packet.source_channel, ctx.packet.source_port, ctx.source
+					);
+					self.dropped.lock().unwrap().insert(key);
+					self.dropped_count.fetch_add(1, Ordering::SeqCst);
+					return PacketDecision::Drop { reason }
+				},
+			}
+		}
+		PacketDecision::Forward
+	}
+
+	/// Returns whether the middlewares let the message for `packet` be built.
+	pub async fn forwards(
+		&self,
+		kind: PacketKind,
+		source: &str,
+		sink: &str,
+		packet: &Packet,
+	) -> bool {
+		let ctx = PacketContext { kind, source, sink, packet };
+		self.decide(&ctx).await == PacketDecision::Forward
+	}
+
+	/// Returns the decisions made since the last call and resets the counts.
+	pub fn take_decisions(&self) -> DecisionCounts {
+		DecisionCounts {
+			held: self.held_count.swap(0, Ordering::SeqCst),
+			dropped: self.dropped_count.swap(0, Ordering::SeqCst),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use ibc::{timestamp::Timestamp, Height};
+	use std::sync::atomic::AtomicBool;
+
+	/// Holds one sequence until it's released.
+	struct HoldSequence {
+		sequence: u64,
+		released: AtomicBool,
+	}
+
+	#[async_trait::async_trait]
+	impl PacketMiddleware for HoldSequence {
+		async fn on_packet(&self, ctx: &PacketContext<'_>) -> PacketDecision {
+			if u64::from(ctx.packet.sequence) == self.sequence &&
+				!self.released.load(Ordering::SeqCst)
+			{
+				return PacketDecision::Hold
+			}
+			PacketDecision::Forward
+		}
+	}
+
+	struct DropAll;
+
+	#[async_trait::async_trait]
+	impl PacketMiddleware for DropAll {
+		async fn on_packet(&self, _: &PacketContext<'_>) -> PacketDecision {
+			PacketDecision::Drop { reason: "not allowed".to_string() }
+		}
+	}
+
+	fn packet(sequence: u64) -> Packet {
+		Packet {
+			sequence: sequence.into(),
+			source_port: PortId::transfer(),
+			source_channel: ChannelId::new(0),
+			destination_port: PortId::transfer(),
+			destination_channel: ChannelId::new(1),
+			data: vec![],
+			timeout_height: Height::zero(),
+			timeout_timestamp: Timestamp::none(),
+		}
+	}
+
+	fn ctx(packet: &Packet) -> PacketContext<'_> {
+		PacketContext { kind: PacketKind::Recv, source: "chain_a", sink: "chain_b", packet }
+	}
+
+	#[test]
+	fn held_packet_is_relayed_after_release() {
+		let hold = Arc::new(HoldSequence { sequence: 2, released: AtomicBool::new(false) });
+		let stack = MiddlewareStack::new(vec![Arc::new(LoggingMiddleware), hold.clone()]);
+		let (first, second) = (packet(1), packet(2));
+
+		// every cycle offers the held packet again until it's released
+		for _ in 0..3 {
+			assert_eq!(block_on(stack.decide(&ctx(&first))), PacketDecision::Forward);
+			assert_eq!(block_on(stack.decide(&ctx(&second))), PacketDecision::Hold);
+		}
+		assert_eq!(stack.take_decisions(), DecisionCounts { held: 3, dropped: 0 });
+
+		hold.released.store(true, Ordering::SeqCst);
+		assert_eq!(block_on(stack.decide(&ctx(&second))), PacketDecision::Forward);
+		assert_eq!(stack.take_decisions(), DecisionCounts::default());
+	}
+
+	#[test]
+	fn dropped_packet_is_not_offered_again() {
+		let stack = MiddlewareStack::new(vec![Arc::new(DropAll)]);
+		let packet = packet(1);
+		assert_eq!(
+			block_on(stack.decide(&ctx(&packet))),
+			PacketDecision::Drop { reason: "not allowed".to_string() }
+		);
+		assert!(matches!(block_on(stack.decide(&ctx(&packet))), PacketDecision::Drop { .. }));
+		assert_eq!(stack.take_decisions(), DecisionCounts { held: 0, dropped: 1 });
+	}
+
+	#[test]
+	fn dropped_packet_does_not_affect_the_other_direction() {
+		let stack = MiddlewareStack::default();
+		let packet = packet(5);
+		stack.dropped.lock().unwrap().insert(DroppedPacket::new(&ctx(&packet)));
+
+		// the same channel, port and sequence, but sent from the other chain
+		let reverse = PacketContext { source: "chain_b", sink: "chain_a", ..ctx(&packet) };
+		assert_eq!(block_on(stack.decide(&reverse)), PacketDecision::Forward);
+		assert!(matches!(block_on(stack.decide(&ctx(&packet))), PacketDecision::Drop { .. }));
+	}
+
+	#[test]
+	fn dropped_packets_are_bounded() {
+		let mut dropped = DroppedPackets::default();
+		for sequence in 1..=MAX_DROPPED_PACKETS as u64 + 1 {
+			dropped.insert(DroppedPacket::new(&ctx(&packet(sequence))));
+		}
+		assert_eq!(dropped.keys.len(), MAX_DROPPED_PACKETS);
+		assert!(!dropped.contains(&DroppedPacket::new(&ctx(&packet(1)))));
+		let last = packet(MAX_DROPPED_PACKETS as u64 + 1);
+		assert!(dropped.contains(&DroppedPacket::new(&ctx(&last))));
+	}
+
+	/// Records the address of the packet data every middleware sees.
+	#[derive(Default)]
+	struct RecordData(Mutex<Vec<usize>>);
+
+	#[async_trait::async_trait]
+	impl PacketMiddleware for RecordData {
+		async fn on_packet(&self, ctx: &PacketContext<'_>) -> PacketDecision {
+			self.0.lock().unwrap().push(ctx.packet.data.as_ptr() as usize);
+			PacketDecision::Forward
+		}
+	}
+
+	#[test]
+	fn packet_data_is_not_copied_for_middlewares() {
+		let (first, second) = (Arc::new(RecordData::default()), Arc::new(RecordData::default()));
+		let stack = MiddlewareStack::new(vec![first.clone(), second.clone()]);
+		let mut packet = packet(1);
+		packet.data = vec![7; 1024];
+		assert!(block_on(stack.forwards(PacketKind::Recv, "chain_a", "chain_b", &packet)));
+
+		let data = packet.data.as_ptr() as usize;
+		assert_eq!(*first.0.lock().unwrap(), vec![data]);
+		assert_eq!(*second.0.lock().unwrap(), vec![data]);
+	}
+}
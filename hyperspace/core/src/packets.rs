@@ -13,28 +13,36 @@
 // limitations under the License.
 
 #[cfg(feature = "testing")]
-use crate::send_packet_relay::packet_relay_status;
+use crate::send_packet_relay::{packet_relay_status, timeout_relay_status};
 use rand::Rng;
 use sp_runtime::Either::{Left, Right};
 use std::{
+	collections::BTreeMap,
 	sync::{
-		atomic::{AtomicUsize, Ordering},
+		atomic::{AtomicU64, AtomicUsize, Ordering},
 		Arc,
 	},
 	time::Duration,
 };
-use tokio::{task::JoinSet, time::sleep};
-
-use crate::packets::utils::{
-	construct_ack_message, construct_recv_message, construct_timeout_message,
-	get_timeout_proof_height, verify_delay_passed, VerifyDelayOn,
+use tokio::{sync::Semaphore, task::JoinSet, time::sleep};
+
+use crate::{
+	middleware::{MiddlewareStack, PacketKind},
+	packets::{
+		scheduler::{by_sequence, in_sequence, interleave, plan_channels, ChannelPlan},
+		utils::{
+			construct_ack_message, construct_recv_message, construct_timeout_message,
+			get_timeout_proof_height, verify_delay_passed, VerifyDelayOn,
+		},
+	},
 };
 use ibc::{
 	applications::transfer::packet::PacketData,
 	core::{
 		ics02_client::client_state::ClientState as ClientStateT,
 		ics03_connection::connection::ConnectionEnd,
-		ics04_channel::channel::{ChannelEnd, State},
+		ics04_channel::channel::{ChannelEnd, Order, State},
+		ics24_host::identifier::{ChannelId, PortId},
 	},
 	Height,
 };
@@ -42,14 +50,13 @@ use ibc_proto::google::protobuf::Any;
 use pallet_ibc::light_clients::AnyClientState;
 use primitives::{
 	error::Error, find_suitable_proof_height_for_client, packet_info_to_packet,
-	query_undelivered_acks, query_undelivered_sequences, Chain, UndeliveredType,
+	summary::PacketSummary, Chain, ChannelLifecycle, UndeliveredType,
 };
 
 pub mod connection_delay;
+pub mod scheduler;
 pub mod utils;
 
-pub const PROCESS_PACKETS_BATCH_SIZE: usize = 100;
-
 /// Returns a tuple of messages, with the first item being packets that are ready to be sent to the
 /// sink chain. And the second item being packet timeouts that should be sent to the source.
 ///
@@ -59,17 +66,76 @@ pub const PROCESS_PACKETS_BATCH_SIZE: usize = 100;
 /// source -> ack_packet     -> sink   => sink has undelivered acks
 /// source -> timeout_packet -> source => source & sink has undelivered timeouts (since timeouts
 /// need both clients to be up to date)
+///
+/// The `max_packets_to_process` budget of the source is shared between the whitelisted channels
+/// (see [`plan_channels`]) and the resulting messages are interleaved per channel.
+///
+/// Every packet, acknowledgement and timeout is offered to the `middlewares` before its message
+/// is built, held and dropped ones are skipped.
+///
+/// Besides the messages, returns the highest source height of the packets and acknowledgements
+/// that were held back because the sink's client of the source hasn't reached it yet.
 pub async fn query_ready_and_timed_out_packets(
 	source: &impl Chain,
 	sink: &impl Chain,
-) -> Result<(Vec<Any>, Vec<Any>), anyhow::Error> {
-	let mut messages = vec![];
-	let mut timeout_messages = vec![];
+	middlewares: &MiddlewareStack,
+) -> Result<(Vec<Any>, Vec<Any>, Option<Height>), anyhow::Error> {
+	query_packets(source, sink, middlewares, false).await
+}
+
+/// Returns the timeouts of packets sent from the `source` that can be proven with the current state
+/// of the sink's client on the source.
+///
+/// Unlike [`query_ready_and_timed_out_packets`], no proofs of received packets or
+/// acknowledgements are queried, only timeouts are offered to the `middlewares` and the
+/// undelivered sequence flags of both chains are left as they are.
+pub async fn query_timed_out_packets(
+	source: &impl Chain,
+	sink: &impl Chain,
+	middlewares: &MiddlewareStack,
+) -> Result<Vec<Any>, anyhow::Error> {
+	let (_, timeout_messages, _) = query_packets(source, sink, middlewares, true).await?;
+	Ok(timeout_messages)
+}
+
+async fn query_packets(
+	source: &impl Chain,
+	sink: &impl Chain,
+	middlewares: &MiddlewareStack,
+	timeouts_only: bool,
+) -> Result<(Vec<Any>, Vec<Any>, Option<Height>), anyhow::Error> {
+	let mut messages = BTreeMap::<_, Vec<Any>>::new();
+	let mut timeout_messages = BTreeMap::<_, Vec<(u64, Any)>>::new();
+	let required_client_height = Arc::new(AtomicU64::new(0));
 	let (source_height, source_timestamp) = source.latest_height_and_timestamp().await?;
 	let (sink_height, sink_timestamp) = sink.latest_height_and_timestamp().await?;
-	let channel_whitelist = source.channel_whitelist();
+	// channels that were closed and drained are not scheduled anymore
+	let channel_whitelist = source
+		.channel_whitelist()
+		.into_iter()
+		.filter(|(channel_id, port_id)| {
+			source.common_state().channel_lifecycle(*channel_id, port_id) !=
+				ChannelLifecycle::Closed
+		})
+		.collect();
+	let mut plans =
+		plan_channels(source, sink, source_height, sink_height, &channel_whitelist, !timeouts_only)
+			.await?;
+
+	// The proofs of all channels are queried concurrently, bounded by the semaphore. The messages
+	// are collected once every channel is planned and ordered by sequence per channel.
+	let proof_queries =
+		Arc::new(Semaphore::new(source.common_state().max_concurrent_proof_queries.max(1)));
+	let mut recv_packets_join_set: JoinSet<Result<_, anyhow::Error>> = JoinSet::new();
+	let mut acknowledgements_join_set: JoinSet<Result<_, anyhow::Error>> = JoinSet::new();
+	// next receive sequences of the ordered channels on the sink
+	let mut ordered_channels = BTreeMap::new();
+	let timeout_packets_count = Arc::new(AtomicUsize::new(0));
+	let send_packets_count = Arc::new(AtomicUsize::new(0));
+	let mut has_acknowledgements = false;
+	let source = Arc::new(source.clone());
+	let sink = Arc::new(sink.clone());
 
-	// TODO: parallelize this
 	for (channel_id, port_id) in channel_whitelist {
 		let source_channel_response = match source
 			.query_channel_end(source_height, channel_id, port_id.clone())
@@ -134,12 +200,34 @@ pub async fn query_ready_and_timed_out_packets(
 			},
 		};
 
+		let closed =
+			source_channel_end.state == State::Closed || sink_channel_end.state == State::Closed;
+		let lifecycle = update_channel_lifecycle(
+			&*source,
+			&*sink,
+			channel_id,
+			&port_id,
+			sink_channel_id,
+			&sink_port_id,
+			closed,
+			source_height,
+			sink_height,
+		)
+		.await?;
+		if lifecycle == ChannelLifecycle::Closed {
+			continue
+		}
+
 		let next_sequence_recv = sink
 			.query_next_sequence_recv(sink_height, &sink_port_id, &sink_channel_id)
 			.await?;
+		if sink_channel_end.ordering == Order::Ordered {
+			ordered_channels
+				.insert((channel_id, port_id.clone()), next_sequence_recv.next_sequence_receive);
+		}
 
 		let source_client_state_on_sink =
-			sink.query_client_state(sink_height, source.client_id()).await?;
+			sink.query_client_state(sink_height, source.try_client_id()?).await?;
 		let source_client_state_on_sink = AnyClientState::try_from(
 			source_client_state_on_sink.client_state.ok_or_else(|| {
 				Error::Custom(format!(
@@ -158,7 +246,7 @@ pub async fn query_ready_and_timed_out_packets(
 		})?;
 
 		let sink_client_state_on_source =
-			source.query_client_state(source_height, sink.client_id()).await?;
+			source.query_client_state(source_height, sink.try_client_id()?).await?;
 		let sink_client_state_on_source = AnyClientState::try_from(
 			sink_client_state_on_source.client_state.ok_or_else(|| {
 				Error::Custom(format!(
@@ -178,155 +266,70 @@ pub async fn query_ready_and_timed_out_packets(
 		let latest_sink_height_on_source = sink_client_state_on_source.latest_height();
 		let latest_source_height_on_sink = source_client_state_on_sink.latest_height();
 
-		let max_packets_to_process = source.common_state().max_packets_to_process;
-
-		// query packets that are waiting for connection delay.
-		let seqs = query_undelivered_sequences(
-			source_height,
-			sink_height,
-			channel_id,
-			port_id.clone(),
-			source,
-			sink,
-		)
-		.await?
-		.into_iter()
-		.take(max_packets_to_process)
-		.collect::<Vec<_>>();
+		// packets and acknowledgements that fit into this channel's share of the budget
+		let ChannelPlan { seqs, acks } =
+			plans.remove(&(channel_id, port_id.clone())).unwrap_or_default();
 
 		log::debug!(target: "hyperspace", "Found {} undelivered packets for {:?}/{:?} for {seqs:?}", seqs.len(), channel_id, port_id.clone());
+		let relays_packets = source.common_state().relays_packets(channel_id, &port_id);
 
 		let mut send_packets = source.query_send_packets(channel_id, port_id.clone(), seqs).await?;
 		log::trace!(target: "hyperspace", "SendPackets count before deduplication: {}", send_packets.len());
 		send_packets.sort();
 		send_packets.dedup();
 		log::trace!(target: "hyperspace", "SendPackets count after deduplication: {}", send_packets.len());
-		let mut recv_packets_join_set: JoinSet<Result<_, anyhow::Error>> = JoinSet::new();
-		let source = Arc::new(source.clone());
-		let sink = Arc::new(sink.clone());
-		let timeout_packets_count = Arc::new(AtomicUsize::new(0));
-		let send_packets_count = Arc::new(AtomicUsize::new(0));
-		for send_packets in send_packets.chunks(PROCESS_PACKETS_BATCH_SIZE) {
-			for send_packet in send_packets.iter().cloned() {
-				let source_connection_end = source_connection_end.clone();
-				let sink_channel_end = sink_channel_end.clone();
-				let source_connection_end = source_connection_end.clone();
-				let source = source.clone();
-				let sink = sink.clone();
-				let duration = Duration::from_millis(
-					rand::thread_rng().gen_range(1..source.rpc_call_delay().as_millis() as u64),
-				);
-				let timeout_packets_count = timeout_packets_count.clone();
-				let recv_packets_count = send_packets_count.clone();
-				recv_packets_join_set.spawn(async move {
-					sleep(duration).await;
-					let source = &source;
-					let sink = &sink;
-					let packet = packet_info_to_packet(&send_packet);
-					// Check if packet has timed out
-					let packet_height = send_packet.height.ok_or_else(|| {
-						Error::Custom(format!("Packet height not found for packet {packet:?}"))
-					})?;
-
-					if packet.timed_out(&sink_timestamp, sink_height) {
-						timeout_packets_count.fetch_add(1, Ordering::SeqCst);
-						// so we know this packet has timed out on the sink, we need to find the maximum
-						// consensus state height at which we can generate a non-membership proof of the
-						// packet for the sink's client on the source.
-						let proof_height =
-							if let Some(proof_height) = get_timeout_proof_height(
-								&**source,
-								&**sink,
-								source_height,
-								sink_height,
-								sink_timestamp,
-								latest_sink_height_on_source,
-								&packet,
-								packet_height,
-							)
-							.await
-						{
-							proof_height
-						} else {
-							log::trace!(target: "hyperspace", "Skipping packet as no timeout proof height could be found: {:?}", packet);
-							return Ok(None)
-						};
-
-						// given this maximum height, has the connection delay been satisfied?
-						if !verify_delay_passed(
+		for send_packet in send_packets {
+			let source_connection_end = source_connection_end.clone();
+			let sink_channel_end = sink_channel_end.clone();
+			let source_connection_end = source_connection_end.clone();
+			let source = source.clone();
+			let sink = sink.clone();
+			let duration = Duration::from_millis(
+				rand::thread_rng().gen_range(1..source.rpc_call_delay().as_millis() as u64),
+			);
+			let timeout_packets_count = timeout_packets_count.clone();
+			let recv_packets_count = send_packets_count.clone();
+			let required_client_height = required_client_height.clone();
+			let middlewares = middlewares.clone();
+			let proof_queries = proof_queries.clone();
+			let channel = (channel_id, port_id.clone());
+			recv_packets_join_set.spawn(async move {
+				sleep(duration).await;
+				let _permit = proof_queries.acquire().await?;
+				let source = &source;
+				let sink = &sink;
+				let packet_height = send_packet.height;
+				let packet = packet_info_to_packet(send_packet);
+				// Check if packet has timed out
+				let packet_height = packet_height.ok_or_else(|| {
+					Error::Custom(format!("Packet height not found for {}", PacketSummary(&packet)))
+				})?;
+
+				if packet.timed_out(&sink_timestamp, sink_height) {
+					timeout_packets_count.fetch_add(1, Ordering::SeqCst);
+					// so we know this packet has timed out on the sink, we need to find the maximum
+					// consensus state height at which we can generate a non-membership proof of the
+					// packet for the sink's client on the source.
+					let proof_height =
+						if let Some(proof_height) = get_timeout_proof_height(
 							&**source,
 							&**sink,
-							source_timestamp,
 							source_height,
-							sink_timestamp,
 							sink_height,
-							source_connection_end.delay_period(),
-							proof_height,
-							VerifyDelayOn::Source,
-						)
-							.await?
-						{
-							log::trace!(target: "hyperspace", "Skipping packet as connection delay has not passed {:?}", packet);
-							return Ok(None)
-						}
-
-						// lets construct the timeout message to be sent to the source
-						let msg = construct_timeout_message(
-							&**source,
-							&**sink,
-							&sink_channel_end,
-							packet,
-							next_sequence_recv.next_sequence_receive,
-							proof_height,
+							sink_timestamp,
+							latest_sink_height_on_source,
+							&packet,
+							packet_height,
 						)
-							.await?;
-						return Ok(Some(Left(msg)))
-					} else {
-						log::trace!(target: "hyperspace", "The packet has not timed out yet: {:?}", packet);
-					}
-
-					// If packet has not timed out but channel is closed on sink we skip
-					// Since we have no reference point for when this channel was closed so we can't
-					// calculate connection delays yet
-					if sink_channel_end.state == State::Closed {
-						log::debug!(target: "hyperspace", "Skipping packet as channel is closed on sink: {:?}", packet);
-						return Ok(None)
-					}
-
-					#[cfg(feature = "testing")]
-					// If packet relay status is paused skip
-					if !packet_relay_status() {
-						return Ok(None)
-					}
-
-					// Check if packet is ready to be sent to sink
-					// If sink does not have a client height that is equal to or greater than the packet
-					// creation height, we can't send it yet, packet_info.height should represent the packet
-					// creation height on source chain
-					if packet_height > latest_source_height_on_sink.revision_height {
-						// Sink does not have client update required to prove recv packet message
-						log::debug!(target: "hyperspace", "Skipping packet {:?} as sink does not have client update required to prove recv packet message", packet);
-						recv_packets_count.fetch_add(1, Ordering::SeqCst);
-						return Ok(None)
-					}
-
-					let proof_height = if let Some(proof_height) = find_suitable_proof_height_for_client(
-						&**source,
-						&**sink,
-						sink_height,
-						source.client_id(),
-						Height::new(latest_source_height_on_sink.revision_number, packet_height),
-						None,
-						latest_source_height_on_sink,
-					)
 						.await
 					{
 						proof_height
 					} else {
-						log::trace!(target: "hyperspace", "Skipping packet {:?} as no proof height could be found", packet);
+						log::trace!(target: "hyperspace", "Skipping packet as no timeout proof height could be found: {}", PacketSummary(&packet));
 						return Ok(None)
 					};
 
+					// given this maximum height, has the connection delay been satisfied?
 					if !verify_delay_passed(
 						&**source,
 						&**sink,
@@ -336,49 +339,276 @@ pub async fn query_ready_and_timed_out_packets(
 						sink_height,
 						source_connection_end.delay_period(),
 						proof_height,
-						VerifyDelayOn::Sink,
+						VerifyDelayOn::Source,
 					)
 						.await?
 					{
-						log::trace!(target: "hyperspace", "Skipping packet as connection delay has not passed {:?}", packet);
+						log::trace!(target: "hyperspace", "Skipping packet as connection delay has not passed {}", PacketSummary(&packet));
 						return Ok(None)
 					}
 
-					if packet.timeout_height.is_zero() && packet.timeout_timestamp.nanoseconds() == 0 {
-						log::warn!(target: "hyperspace", "Skipping packet as packet timeout is zero: {}", packet.sequence);
+					#[cfg(feature = "testing")]
+					// If timeout relay is paused skip
+					if !timeout_relay_status() {
 						return Ok(None)
 					}
 
-					let list = &source.common_state().skip_tokens_list;
-
-					let decoded_dara: PacketData = serde_json::from_str(&String::from_utf8_lossy(packet.data.as_ref())).map_err(|e| {
-						Error::Custom(format!(
-						"Failed to decode packet data for packet {:?}: {:?}",
-						packet, e
-						))
-					})?;
+					if !middlewares
+						.forwards(PacketKind::Timeout, source.name(), sink.name(), &packet)
+						.await
+					{
+						return Ok(None)
+					}
 
-					if list.iter().any(|skiped_denom| decoded_dara.token.denom.base_denom.as_str() == skiped_denom) {
-						log::info!(target: "hyperspace", "Skipping packet with ignored token: {:?}", packet);
+					// lets construct the timeout message to be sent to the source
+					let sequence = packet.sequence;
+					let msg = construct_timeout_message(
+						&**source,
+						&**sink,
+						&sink_channel_end,
+						packet,
+						next_sequence_recv.next_sequence_receive,
+						proof_height,
+					)
+						.await?;
+					return Ok(Some((channel, u64::from(sequence), Left(msg))))
+				} else {
+					log::trace!(target: "hyperspace", "The packet has not timed out yet: {}", PacketSummary(&packet));
+				}
+
+				if timeouts_only {
+					return Ok(None)
+				}
+
+				// Packets of a disabled direction stay pending, only their timeouts are relayed
+				if !relays_packets {
+					log::trace!(target: "hyperspace", "Skipping packet as {} doesn't relay this direction: {}", source.name(), PacketSummary(&packet));
+					return Ok(None)
+				}
+
+				// If packet has not timed out but channel is closed on sink we skip
+				// Since we have no reference point for when this channel was closed so we can't
+				// calculate connection delays yet
+				if sink_channel_end.state == State::Closed {
+					log::debug!(target: "hyperspace", "Skipping packet as channel is closed on sink: {}", PacketSummary(&packet));
+					return Ok(None)
+				}
+
+				#[cfg(feature = "testing")]
+				// If packet relay status is paused skip
+				if !packet_relay_status() {
+					return Ok(None)
+				}
+
+				// Check if packet is ready to be sent to sink
+				// If sink does not have a client height that is equal to or greater than the packet
+				// creation height, we can't send it yet, packet_info.height should represent the packet
+				// creation height on source chain
+				if packet_height > latest_source_height_on_sink.revision_height {
+					// Sink does not have client update required to prove recv packet message
+					log::debug!(target: "hyperspace", "Skipping {} as sink does not have client update required to prove recv packet message", PacketSummary(&packet));
+					recv_packets_count.fetch_add(1, Ordering::SeqCst);
+					required_client_height.fetch_max(packet_height, Ordering::SeqCst);
+					return Ok(None)
+				}
+
+				let proof_height = if let Some(proof_height) = find_suitable_proof_height_for_client(
+					&**source,
+					&**sink,
+					sink_height,
+					source.try_client_id()?,
+					Height::new(latest_source_height_on_sink.revision_number, packet_height),
+					None,
+					latest_source_height_on_sink,
+				)
+					.await
+				{
+					proof_height
+				} else {
+					log::trace!(target: "hyperspace", "Skipping {} as no proof height could be found", PacketSummary(&packet));
+					return Ok(None)
+				};
+
+				if !verify_delay_passed(
+					&**source,
+					&**sink,
+					source_timestamp,
+					source_height,
+					sink_timestamp,
+					sink_height,
+					source_connection_end.delay_period(),
+					proof_height,
+					VerifyDelayOn::Sink,
+				)
+					.await?
+				{
+					log::trace!(target: "hyperspace", "Skipping packet as connection delay has not passed {}", PacketSummary(&packet));
+					return Ok(None)
+				}
+
+				if packet.timeout_height.is_zero() && packet.timeout_timestamp.nanoseconds() == 0 {
+					log::warn!(target: "hyperspace", "Skipping packet as packet timeout is zero: {}", packet.sequence);
+					return Ok(None)
+				}
+
+				let list = &source.common_state().skip_tokens_list;
+
+				// Only transfer packets carry a token, packets of other applications are not
+				// filtered
+				let decoded_data = serde_json::from_str::<PacketData>(&String::from_utf8_lossy(packet.data.as_ref()));
+				if let Ok(decoded_data) = decoded_data {
+					if list.iter().any(|skipped_denom| decoded_data.token.denom.base_denom.as_str() == skipped_denom) {
+						log::info!(target: "hyperspace", "Skipping packet with ignored token: {}", PacketSummary(&packet));
 						return Ok(None)
 					}
+				}
 
-					let msg = construct_recv_message(&**source, &**sink, packet, proof_height).await?;
-					Ok(Some(Right(msg)))
-				});
-			}
+				if !middlewares.forwards(PacketKind::Recv, source.name(), sink.name(), &packet).await {
+					return Ok(None)
+				}
+
+				let sequence = u64::from(packet.sequence);
+				let msg = construct_recv_message(&**source, &**sink, packet, proof_height).await?;
+				Ok(Some((channel, sequence, Right(msg))))
+			});
 		}
 
-		while let Some(result) = recv_packets_join_set.join_next().await {
-			let Some(either) = result?? else { continue };
-			match either {
-				Left(msg) => timeout_messages.push(msg),
-				Right(msg) => messages.push(msg),
-			}
+		// Get acknowledgement messages
+		if timeouts_only {
+			continue
+		}
+		if source_channel_end.state == State::Closed {
+			log::trace!(target: "hyperspace", "Skipping acknowledgements for channel {:?} as channel is closed on source", channel_id);
+			continue
+		}
+		if !source.common_state().relays_acks(channel_id, &port_id) {
+			log::trace!(target: "hyperspace", "Skipping acknowledgements for channel {:?} as {} doesn't relay them", channel_id, source.name());
+			continue
+		}
+
+		let mut acknowledgements =
+			source.query_received_packets(channel_id, port_id.clone(), acks).await?;
+		acknowledgements.sort();
+		acknowledgements.dedup();
+		log::trace!(target: "hyperspace", "Got acknowledgements for channel {:?}: {:?}", channel_id, acknowledgements);
+		has_acknowledgements |= !acknowledgements.is_empty();
+		for mut acknowledgement in acknowledgements {
+			let source_connection_end = source_connection_end.clone();
+			let source = source.clone();
+			let sink = sink.clone();
+			let required_client_height = required_client_height.clone();
+			let middlewares = middlewares.clone();
+			let proof_queries = proof_queries.clone();
+			let channel = (channel_id, port_id.clone());
+			let duration1 = Duration::from_millis(
+				rand::thread_rng().gen_range(1..source.rpc_call_delay().as_millis() as u64),
+			);
+			acknowledgements_join_set.spawn(async move {
+				sleep(duration1).await;
+				let _permit = proof_queries.acquire().await?;
+				let source = &source;
+				let sink = &sink;
+				let ack = acknowledgement.ack.take();
+				let ack_height = acknowledgement.height;
+				let packet = packet_info_to_packet(acknowledgement);
+				let ack = if let Some(ack) = ack {
+					ack
+				} else {
+					// Packet has no valid acknowledgement, skip
+					log::trace!(target: "hyperspace", "Skipping acknowledgement for {} as packet has no valid acknowledgement", PacketSummary(&packet));
+					return Ok(None)
+				};
+
+				// Check if ack is ready to be sent to sink
+				// If sink does not have a client height that is equal to or greater than the packet
+				// creation height, we can't send it yet packet_info.height should represent the
+				// acknowledgement creation height on source chain
+				let ack_height = ack_height.ok_or_else(|| {
+					Error::Custom(format!("Packet height not found for {}", PacketSummary(&packet)))
+				})?;
+				if ack_height > latest_source_height_on_sink.revision_height {
+					// Sink does not have client update required to prove acknowledgement packet message
+					log::trace!(target: "hyperspace", "Skipping acknowledgement for {} as sink does not have client update required to prove acknowledgement packet message", PacketSummary(&packet));
+					required_client_height.fetch_max(ack_height, Ordering::SeqCst);
+					return Ok(None)
+				}
+
+				log::trace!(target: "hyperspace", "sink_height: {:?}, latest_source_height_on_sink: {:?}, acknowledgement.height: {}", sink_height, latest_source_height_on_sink, ack_height);
+
+				let proof_height = if let Some(proof_height) = find_suitable_proof_height_for_client(
+					&**source,
+					&**sink,
+					sink_height,
+					source.try_client_id()?,
+					Height::new(latest_source_height_on_sink.revision_number, ack_height),
+					None,
+					latest_source_height_on_sink,
+				)
+					.await
+				{
+					log::trace!(target: "hyperspace", "Using proof height: {}", proof_height);
+					proof_height
+				} else {
+					log::trace!(target: "hyperspace", "Skipping acknowledgement for {} as no proof height could be found", PacketSummary(&packet));
+					return Ok(None)
+				};
+
+				if !verify_delay_passed(
+					&**source,
+					&**sink,
+					source_timestamp,
+					source_height,
+					sink_timestamp,
+					sink_height,
+					source_connection_end.delay_period(),
+					proof_height,
+					VerifyDelayOn::Sink,
+				)
+					.await?
+				{
+					log::trace!(target: "hyperspace", "Skipping acknowledgement for packet as connection delay has not passed {}", PacketSummary(&packet));
+					return Ok(None)
+				}
+
+				if !middlewares.forwards(PacketKind::Ack, source.name(), sink.name(), &packet).await {
+					return Ok(None)
+				}
+
+				let sequence = u64::from(packet.sequence);
+				let msg = construct_ack_message(&**source, &**sink, packet, ack.into(), proof_height).await?;
+				Ok(Some((channel, sequence, msg)))
+			});
+		}
+	}
+
+	let mut recv_messages = BTreeMap::<_, Vec<(u64, Any)>>::new();
+	while let Some(result) = recv_packets_join_set.join_next().await {
+		let Some((channel, sequence, either)) = result?? else { continue };
+		match either {
+			Left(msg) => timeout_messages.entry(channel).or_default().push((sequence, msg)),
+			Right(msg) => recv_messages.entry(channel).or_default().push((sequence, msg)),
+		}
+	}
+	for ((channel_id, port_id), recv_messages) in recv_messages {
+		let recv_messages = match ordered_channels.get(&(channel_id, port_id.clone())) {
+			Some(next_sequence_recv) => {
+				let ready = recv_messages.len();
+				let recv_messages = in_sequence(*next_sequence_recv, recv_messages);
+				if recv_messages.len() < ready {
+					log::debug!(target: "hyperspace", "Withholding {} packets on ordered channel {}/{} until the packets before them are ready", ready - recv_messages.len(), channel_id, port_id);
+				}
+				recv_messages
+			},
+			None => by_sequence(recv_messages),
+		};
+		if !recv_messages.is_empty() {
+			messages.entry((channel_id, port_id)).or_default().extend(recv_messages);
 		}
+	}
 
-		let timeouts_count = timeout_packets_count.load(Ordering::SeqCst);
-		log::debug!(target: "hyperspace", "Found {timeouts_count} packets that have timed out");
+	let timeouts_count = timeout_packets_count.load(Ordering::SeqCst);
+	log::debug!(target: "hyperspace", "Found {timeouts_count} packets that have timed out");
+	if !timeouts_only {
 		source
 			.on_undelivered_sequences(timeouts_count != 0, UndeliveredType::Timeouts)
 			.await;
@@ -386,115 +616,206 @@ pub async fn query_ready_and_timed_out_packets(
 		let sends_count = send_packets_count.load(Ordering::SeqCst);
 		log::debug!(target: "hyperspace", "Found {sends_count} sent packets");
 		sink.on_undelivered_sequences(sends_count != 0, UndeliveredType::Recvs).await;
+		sink.on_undelivered_sequences(has_acknowledgements, UndeliveredType::Acks).await;
+	}
 
-		// Get acknowledgement messages
-		if source_channel_end.state == State::Closed {
-			log::trace!(target: "hyperspace", "Skipping acknowledgements for channel {:?} as channel is closed on source", channel_id);
-			continue
-		}
-
-		// query acknowledgements that are waiting for connection delay.
-		let acks = query_undelivered_acks(
-			source_height,
-			sink_height,
-			channel_id,
-			port_id.clone(),
-			&*source,
-			&*sink,
-		)
-		.await?
-		.into_iter()
-		.take(max_packets_to_process)
-		.collect::<Vec<_>>();
+	let mut ack_messages = BTreeMap::<_, Vec<(u64, Any)>>::new();
+	while let Some(result) = acknowledgements_join_set.join_next().await {
+		let Some((channel, sequence, msg)) = result?? else { continue };
+		ack_messages.entry(channel).or_default().push((sequence, msg));
+	}
+	for (channel, ack_messages) in ack_messages {
+		messages.entry(channel).or_default().extend(by_sequence(ack_messages));
+	}
 
-		let acknowledgements =
-			source.query_received_packets(channel_id, port_id.clone(), acks).await?;
-		log::trace!(target: "hyperspace", "Got acknowledgements for channel {:?}: {:?}", channel_id, acknowledgements);
-		let mut acknowledgements_join_set: JoinSet<Result<_, anyhow::Error>> = JoinSet::new();
-		sink.on_undelivered_sequences(!acknowledgements.is_empty(), UndeliveredType::Acks)
-			.await;
-		for acknowledgements in acknowledgements.chunks(PROCESS_PACKETS_BATCH_SIZE) {
-			for acknowledgement in acknowledgements.iter().cloned() {
-				let source_connection_end = source_connection_end.clone();
-				let source = source.clone();
-				let sink = sink.clone();
-				let duration1 = Duration::from_millis(
-					rand::thread_rng().gen_range(1..source.rpc_call_delay().as_millis() as u64),
-				);
-				acknowledgements_join_set.spawn(async move {
-					sleep(duration1).await;
-					let source = &source;
-					let sink = &sink;
-					let packet = packet_info_to_packet(&acknowledgement);
-					let ack = if let Some(ack) = acknowledgement.ack {
-						ack
-					} else {
-						// Packet has no valid acknowledgement, skip
-						log::trace!(target: "hyperspace", "Skipping acknowledgement for packet {:?} as packet has no valid acknowledgement", packet);
-						return Ok(None)
-					};
+	let required_client_height = match required_client_height.load(Ordering::SeqCst) {
+		0 => None,
+		height => Some(Height::new(source_height.revision_number, height)),
+	};
+	Ok((
+		interleave(messages.into_values()),
+		interleave(timeout_messages.into_values().map(by_sequence)),
+		required_client_height,
+	))
+}
 
-					// Check if ack is ready to be sent to sink
-					// If sink does not have a client height that is equal to or greater than the packet
-					// creation height, we can't send it yet packet_info.height should represent the
-					// acknowledgement creation height on source chain
-					let ack_height = acknowledgement.height.ok_or_else(|| {
-						Error::Custom(format!("Packet height not found for packet {packet:?}"))
-					})?;
-					if ack_height > latest_source_height_on_sink.revision_height {
-						// Sink does not have client update required to prove acknowledgement packet message
-						log::trace!(target: "hyperspace", "Skipping acknowledgement for packet {:?} as sink does not have client update required to prove acknowledgement packet message", packet);
-						return Ok(None)
-					}
+/// Advances the [`ChannelLifecycle`] of a whitelisted channel. Packets sent on a closed channel
+/// are pending as long as they can be timed out, i.e. their commitments exist on the source and
+/// they weren't received on the sink. Packets that were received keep their commitments until
+/// they're acknowledged, which a closed channel doesn't allow, so they aren't counted.
+#[allow(clippy::too_many_arguments)]
+async fn update_channel_lifecycle(
+	source: &impl Chain,
+	sink: &impl Chain,
+	channel_id: ChannelId,
+	port_id: &PortId,
+	sink_channel_id: ChannelId,
+	sink_port_id: &PortId,
+	closed: bool,
+	source_height: Height,
+	sink_height: Height,
+) -> Result<ChannelLifecycle, anyhow::Error> {
+	let common_state = source.common_state();
+	let current = common_state.channel_lifecycle(channel_id, port_id);
+	let has_pending_packets = if closed {
+		let commitments = source
+			.query_packet_commitments(source_height, channel_id, port_id.clone())
+			.await?;
+		!commitments.is_empty() &&
+			!sink
+				.query_unreceived_packets(
+					sink_height,
+					sink_channel_id,
+					sink_port_id.clone(),
+					commitments,
+				)
+				.await?
+				.is_empty()
+	} else {
+		false
+	};
+	let next = current.next(closed, has_pending_packets);
+	if next != current {
+		match next {
+			ChannelLifecycle::Draining =>
+				log::info!(target: "hyperspace", "Channel {channel_id}/{port_id} on {} was closed, timing out its pending packets", source.name()),
+			ChannelLifecycle::Closed =>
+				log::info!(target: "hyperspace", "Channel {channel_id}/{port_id} on {} is closed and drained, it's not relayed anymore", source.name()),
+			ChannelLifecycle::Open => {},
+		}
+		common_state.set_channel_lifecycle(channel_id, port_id.clone(), next);
+	}
+	Ok(next)
+}
 
-					log::trace!(target: "hyperspace", "sink_height: {:?}, latest_source_height_on_sink: {:?}, acknowledgement.height: {}", sink_height, latest_source_height_on_sink, ack_height);
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ibc::core::{
+		ics03_connection::{
+			connection::{Counterparty as ConnectionCounterparty, State as ConnectionState},
+			version::Version as ConnectionVersion,
+		},
+		ics04_channel::{channel::Counterparty, Version},
+		ics24_host::identifier::{ClientId, ConnectionId},
+	};
+	use ibc_rpc::PacketInfo;
+	use primitives::{mock::MockChain, IbcProvider};
+	use std::str::FromStr;
+
+	const CHANNELS: u64 = 3;
+	/// Packets sent on every channel
+	const PACKETS: u64 = 4;
+	/// Acknowledgements written on every channel
+	const ACKS: u64 = 2;
+	/// Latest height the clients of both chains are updated to
+	const CLIENT_HEIGHT: u64 = 15;
+
+	fn transfer() -> PortId {
+		PortId::transfer()
+	}
 
-					let proof_height = if let Some(proof_height) = find_suitable_proof_height_for_client(
-						&**source,
-						&**sink,
-						sink_height,
-						source.client_id(),
-						Height::new(latest_source_height_on_sink.revision_number, ack_height),
-						None,
-						latest_source_height_on_sink,
-					)
-						.await
-					{
-						log::trace!(target: "hyperspace", "Using proof height: {}", proof_height);
-						proof_height
-					} else {
-						log::trace!(target: "hyperspace", "Skipping acknowledgement for packet {:?} as no proof height could be found", packet);
-						return Ok(None)
-					};
+	fn packet_info(sequence: u64, channel_id: ChannelId, ack: Option<&str>) -> PacketInfo {
+		PacketInfo {
+			height: Some(10 + sequence),
+			sequence,
+			source_port: transfer().to_string(),
+			source_channel: channel_id.to_string(),
+			destination_port: transfer().to_string(),
+			destination_channel: channel_id.to_string(),
+			channel_order: Order::Unordered.as_str().to_string(),
+			data: format!("packet {sequence}").into_bytes().into(),
+			timeout_height: Default::default(),
+			// far in the future of the mock chains
+			timeout_timestamp: 4_000_000_000_000_000_000,
+			ack: ack.map(|ack| ack.as_bytes().to_vec().into()),
+		}
+	}
 
-					if !verify_delay_passed(
-						&**source,
-						&**sink,
-						source_timestamp,
-						source_height,
-						sink_timestamp,
-						sink_height,
-						source_connection_end.delay_period(),
-						proof_height,
-						VerifyDelayOn::Sink,
-					)
-						.await?
-					{
-						log::trace!(target: "hyperspace", "Skipping acknowledgement for packet as connection delay has not passed {:?}", packet);
-						return Ok(None)
-					}
+	/// Returns a source and a sink that are connected by `CHANNELS` open channels, with pending
+	/// packets and acknowledgements on every channel.
+	fn chains() -> (MockChain, MockChain) {
+		let mut source = MockChain::named("source", 2000);
+		let mut sink = MockChain::named("sink", 2001);
+		source.set_client_id(ClientId::from_str("10-grandpa-0").unwrap());
+		sink.set_client_id(ClientId::from_str("10-grandpa-1").unwrap());
+		for channel in 0..CHANNELS {
+			source.add_channel_to_whitelist((ChannelId::new(channel), transfer()));
+		}
 
-					let msg = construct_ack_message(&**source, &**sink, packet, ack, proof_height).await?;
-					Ok(Some(msg))
-				});
+		for (chain, counterparty) in [(&source, &sink), (&sink, &source)] {
+			let client_id = counterparty.client_id();
+			let mut state = chain.state();
+			state.height = 20;
+			state
+				.clients
+				.insert(client_id.clone(), counterparty.host_client_state(CLIENT_HEIGHT));
+			state.consensus_states.insert(
+				(client_id.clone(), counterparty.height(CLIENT_HEIGHT)),
+				counterparty.host_consensus_state(CLIENT_HEIGHT),
+			);
+			let connection_id = ConnectionId::new(0);
+			state.connections.insert(
+				connection_id.clone(),
+				ConnectionEnd::new(
+					ConnectionState::Open,
+					client_id,
+					ConnectionCounterparty::new(
+						chain.client_id(),
+						Some(connection_id.clone()),
+						counterparty.connection_prefix(),
+					),
+					vec![ConnectionVersion::default()],
+					Duration::ZERO,
+				),
+			);
+			for channel in 0..CHANNELS {
+				let channel_id = ChannelId::new(channel);
+				state.channels.insert(
+					(channel_id, transfer()),
+					ChannelEnd::new(
+						State::Open,
+						Order::Unordered,
+						Counterparty::new(transfer(), Some(channel_id)),
+						vec![connection_id.clone()],
+						Version::ics20(),
+					),
+				);
 			}
 		}
 
-		while let Some(result) = acknowledgements_join_set.join_next().await {
-			let Some(msg) = result?? else { continue };
-			messages.push(msg)
+		for channel in 0..CHANNELS {
+			let channel_id = ChannelId::new(channel);
+			for sequence in 1..=PACKETS {
+				source.state().sent_packets.push(packet_info(sequence, channel_id, None));
+			}
+			// packets of the sink that were received and acknowledged on the source
+			for sequence in 1..=ACKS {
+				sink.state().sent_packets.push(packet_info(sequence, channel_id, None));
+				source.state().received_packets.push(packet_info(
+					sequence,
+					channel_id,
+					Some("{\"result\":\"AQ==\"}"),
+				));
+			}
 		}
+		(source, sink)
 	}
 
-	Ok((messages, timeout_messages))
+	#[tokio::test]
+	async fn messages_do_not_depend_on_proof_query_concurrency() {
+		let (mut source, sink) = chains();
+		let middlewares = MiddlewareStack::default();
+		assert!(source.common_state().max_concurrent_proof_queries > 1);
+		let (messages, timeout_messages, required_client_height) =
+			query_ready_and_timed_out_packets(&source, &sink, &middlewares).await.unwrap();
+		assert_eq!(messages.len(), (CHANNELS * (PACKETS + ACKS)) as usize);
+		assert!(timeout_messages.is_empty());
+		assert_eq!(required_client_height, None);
+
+		source.common_state_mut().max_concurrent_proof_queries = 1;
+		let (sequential_messages, ..) =
+			query_ready_and_timed_out_packets(&source, &sink, &middlewares).await.unwrap();
+		assert_eq!(sequential_messages, messages);
+	}
 }
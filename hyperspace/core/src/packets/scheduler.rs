@@ -0,0 +1,234 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use ibc::{
+	core::ics24_host::identifier::{ChannelId, PortId},
+	Height,
+};
+use primitives::{query_undelivered_acks, query_undelivered_sequences, Chain};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Undelivered sequences of a whitelisted channel that fit into the channel's share of the
+/// per-cycle packet budget.
+#[derive(Debug, Default, Clone)]
+pub struct ChannelPlan {
+	/// Sequences of packets that haven't been received on the sink yet
+	pub seqs: Vec<u64>,
+	/// Sequences of acknowledgements that haven't been delivered to the sink yet
+	pub acks: Vec<u64>,
+}
+
+/// Splits `budget` between channels using weighted round-robin, so that every channel with
+/// pending work makes progress in a cycle. `channels` is a list of `(key, weight, pending)`.
+///
+/// Budget that a channel can't use (because it has less pending work than its share) is
+/// carried over to the other channels.
+pub fn allocate_budget<K: Ord + Clone>(
+	budget: usize,
+	channels: &[(K, u32, usize)],
+) -> BTreeMap<K, usize> {
+	let mut quotas = channels
+		.iter()
+		.map(|(key, ..)| (key.clone(), 0usize))
+		.collect::<BTreeMap<_, _>>();
+	let mut remaining = budget;
+	while remaining > 0 {
+		let mut progressed = false;
+		for (key, weight, pending) in channels {
+			let quota = quotas.get_mut(key).expect("quota is initialized for every channel");
+			let share = (*weight).max(1) as usize;
+			let give = share.min(pending.saturating_sub(*quota)).min(remaining);
+			if give == 0 {
+				continue
+			}
+			*quota += give;
+			remaining -= give;
+			progressed = true;
+		}
+		if !progressed {
+			break
+		}
+	}
+	quotas
+}
+
+/// Merges per-channel message queues by taking one message from every queue in turn, so a busy
+/// channel can't push the messages of other channels to the end of the batch.
+pub fn interleave<T>(queues: impl IntoIterator<Item = Vec<T>>) -> Vec<T> {
+	let mut queues = queues.into_iter().map(|queue| queue.into_iter()).collect::<Vec<_>>();
+	let mut result = Vec::new();
+	loop {
+		let len = result.len();
+		result.extend(queues.iter_mut().filter_map(|queue| queue.next()));
+		if result.len() == len {
+			break
+		}
+	}
+	result
+}
+
+/// Orders the messages of an ordered channel by sequence and keeps only those that continue the
+/// sink's receive sequence without a gap, starting at `next_sequence_recv`. The sink would reject
+/// a packet that arrives before its predecessors, so the packets after a held-back one have to
+/// wait for it.
+pub fn in_sequence<T>(next_sequence_recv: u64, mut msgs: Vec<(u64, T)>) -> Vec<T> {
+	msgs.sort_by_key(|(sequence, _)| *sequence);
+	msgs.into_iter()
+		.zip(next_sequence_recv..)
+		.take_while(|((sequence, _), expected)| sequence == expected)
+		.map(|((_, msg), _)| msg)
+		.collect()
+}
+
+/// Orders messages by sequence, so that the messages of a channel don't depend on the order in
+/// which their proofs were queried.
+pub fn by_sequence<T>(mut msgs: Vec<(u64, T)>) -> Vec<T> {
+	msgs.sort_by_key(|(sequence, _)| *sequence);
+	msgs.into_iter().map(|(_, msg)| msg).collect()
+}
+
+/// Queries undelivered packets and acknowledgements of every whitelisted channel and splits the
+/// `max_packets_to_process` budget of the `source` between them according to the channel weights.
+/// Acknowledgements are only queried if `with_acks` is set.
+///
+/// Fails if the undelivered sequences of a channel can't be queried, so that an RPC failure isn't
+/// mistaken for a channel without pending packets.
+pub async fn plan_channels(
+	source: &impl Chain,
+	sink: &impl Chain,
+	source_height: Height,
+	sink_height: Height,
+	channel_whitelist: &BTreeSet<(ChannelId, PortId)>,
+	with_acks: bool,
+) -> Result<BTreeMap<(ChannelId, PortId), ChannelPlan>, anyhow::Error> {
+	let common_state = source.common_state();
+	let budget = common_state.max_packets_to_process;
+	let mut plans = BTreeMap::new();
+	for (channel_id, port_id) in channel_whitelist {
+		let seqs = query_undelivered_sequences(
+			source_height,
+			sink_height,
+			*channel_id,
+			port_id.clone(),
+			source,
+			sink,
+		)
+		.await
+		.map_err(|e| {
+			anyhow!(
+				"Failed to query undelivered sequences for {channel_id}/{port_id} on {}: {e:?}",
+				source.name()
+			)
+		})?;
+		// acknowledgements of a disabled direction are never relayed, so they aren't queried
+		let acks = if with_acks && common_state.relays_acks(*channel_id, port_id) {
+			query_undelivered_acks(
+				source_height,
+				sink_height,
+				*channel_id,
+				port_id.clone(),
+				source,
+				sink,
+			)
+			.await
+			.map_err(|e| {
+				anyhow!(
+					"Failed to query undelivered acks for {channel_id}/{port_id} on {}: {e:?}",
+					source.name()
+				)
+			})?
+		} else {
+			vec![]
+		};
+		plans.insert((*channel_id, port_id.clone()), ChannelPlan { seqs, acks });
+	}
+
+	let weight = |(channel_id, port_id): &(ChannelId, PortId)| {
+		common_state.channel_weight(*channel_id, port_id)
+	};
+	let seqs_pending = plans
+		.iter()
+		.map(|(key, plan)| (key.clone(), weight(key), plan.seqs.len()))
+		.collect::<Vec<_>>();
+	let acks_pending = plans
+		.iter()
+		.map(|(key, plan)| (key.clone(), weight(key), plan.acks.len()))
+		.collect::<Vec<_>>();
+	let seqs_quotas = allocate_budget(budget, &seqs_pending);
+	let acks_quotas = allocate_budget(budget, &acks_pending);
+
+	for (key, plan) in plans.iter_mut() {
+		let seqs_quota = seqs_quotas.get(key).copied().unwrap_or_default();
+		let acks_quota = acks_quotas.get(key).copied().unwrap_or_default();
+		if plan.seqs.len() > seqs_quota || plan.acks.len() > acks_quota {
+			log::debug!(
+				target: "hyperspace",
+				"Channel {}/{} on {} is limited to {} packets and {} acks this cycle ({} and {} pending)",
+				key.0, key.1, source.name(), seqs_quota, acks_quota, plan.seqs.len(), plan.acks.len()
+			);
+		}
+		plan.seqs.truncate(seqs_quota);
+		plan.acks.truncate(acks_quota);
+	}
+	Ok(plans)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn small_channel_is_not_starved_by_busy_channel() {
+		let quotas = allocate_budget(50, &[("busy", 1, 500), ("quiet", 1, 3)]);
+		assert_eq!(quotas["quiet"], 3);
+		assert_eq!(quotas["busy"], 47);
+	}
+
+	#[test]
+	fn budget_is_split_by_weight() {
+		let quotas = allocate_budget(40, &[("a", 3, 500), ("b", 1, 500)]);
+		assert_eq!(quotas["a"], 30);
+		assert_eq!(quotas["b"], 10);
+	}
+
+	#[test]
+	fn unused_budget_is_left_when_there_is_no_work() {
+		let quotas = allocate_budget(50, &[("a", 1, 2), ("b", 5, 0)]);
+		assert_eq!(quotas["a"], 2);
+		assert_eq!(quotas["b"], 0);
+	}
+
+	#[test]
+	fn ordered_packets_after_a_gap_are_withheld() {
+		assert_eq!(in_sequence(4, vec![(5, "b"), (4, "a"), (7, "d")]), vec!["a", "b"]);
+		assert_eq!(in_sequence(4, vec![(5, "b"), (6, "c")]), Vec::<&str>::new());
+		assert_eq!(in_sequence(1, vec![(3, "c"), (1, "a"), (2, "b")]), vec!["a", "b", "c"]);
+	}
+
+	#[test]
+	fn messages_are_ordered_regardless_of_completion_order() {
+		let completed = vec![(7, "g"), (2, "b"), (5, "e"), (1, "a")];
+		let mut reversed = completed.clone();
+		reversed.reverse();
+		assert_eq!(by_sequence(completed), vec!["a", "b", "e", "g"]);
+		assert_eq!(by_sequence(reversed), vec!["a", "b", "e", "g"]);
+	}
+
+	#[test]
+	fn interleave_takes_from_every_queue_in_turn() {
+		let merged = interleave(vec![vec![1, 2, 3, 4], vec![10], vec![20, 21]]);
+		assert_eq!(merged, vec![1, 10, 20, 2, 21, 3, 4]);
+	}
+}
@@ -37,7 +37,7 @@ use ibc::{
 };
 use ibc_proto::google::protobuf::Any;
 use pallet_ibc::light_clients::AnyClientState;
-use primitives::{find_suitable_proof_height_for_client, Chain};
+use primitives::{find_suitable_proof_height_for_client, summary::PacketSummary, Chain};
 use std::time::Duration;
 use tendermint_proto::Protobuf;
 
@@ -53,16 +53,17 @@ pub async fn get_timeout_proof_height(
 	packet_creation_height: u64,
 ) -> Option<Height> {
 	let timeout_variant = Packet::timeout_variant(packet, &sink_timestamp, sink_height).unwrap();
-	log::trace!(target: "hyperspace", "get_timeout_proof_height: {}->{}, timeout_variant={:?}, source_height={}, sink_height={}, sink_timestamp={}, latest_client_height_on_source={}, packet_creation_height={}, packet={:?}",
-		source.name(), sink.name(), timeout_variant, source_height, sink_height, sink_timestamp, latest_client_height_on_source, packet_creation_height, packet);
+	log::trace!(target: "hyperspace", "get_timeout_proof_height: {}->{}, timeout_variant={:?}, source_height={}, sink_height={}, sink_timestamp={}, latest_client_height_on_source={}, packet_creation_height={}, {}",
+		source.name(), sink.name(), timeout_variant, source_height, sink_height, sink_timestamp, latest_client_height_on_source, packet_creation_height, PacketSummary(packet));
 
+	let sink_client_id = sink.try_client_id().ok()?;
 	match timeout_variant {
 		TimeoutVariant::Height =>
 			find_suitable_proof_height_for_client(
 				sink,
 				source,
 				source_height,
-				sink.client_id(),
+				sink_client_id.clone(),
 				packet.timeout_height,
 				None,
 				latest_client_height_on_source,
@@ -79,7 +80,7 @@ pub async fn get_timeout_proof_height(
 				"Querying client state at {height}"
 			);
 			let sink_client_state =
-				source.query_client_state(height, sink.client_id()).await.ok()?;
+				source.query_client_state(height, sink_client_id.clone()).await.ok()?;
 			let sink_client_state =
 				AnyClientState::try_from(sink_client_state.client_state?).ok()?;
 			let height = sink_client_state.latest_height();
@@ -97,7 +98,7 @@ pub async fn get_timeout_proof_height(
 				sink,
 				source,
 				source_height,
-				sink.client_id(),
+				sink_client_id.clone(),
 				start_height,
 				Some(packet.timeout_timestamp),
 				latest_client_height_on_source,
@@ -110,7 +111,7 @@ pub async fn get_timeout_proof_height(
 			let sink_client_state = source
 				.query_client_state(
 					Height::new(source_height.revision_number, packet_creation_height),
-					sink.client_id(),
+					sink_client_id.clone(),
 				)
 				.await
 				.ok()?;
@@ -135,7 +136,7 @@ pub async fn get_timeout_proof_height(
 				sink,
 				source,
 				source_height,
-				sink.client_id(),
+				sink_client_id.clone(),
 				start_height,
 				Some(packet.timeout_timestamp),
 				latest_client_height_on_source,
@@ -168,7 +169,7 @@ pub async fn verify_delay_passed(
 		VerifyDelayOn::Source => {
 			let actual_proof_height = sink.get_proof_height(proof_height).await;
 			if let Ok((source_client_update_height, source_client_update_time)) = source
-				.query_client_update_time_and_height(sink.client_id(), actual_proof_height)
+				.query_client_update_time_and_height(sink.try_client_id()?, actual_proof_height)
 				.await
 			{
 				let block_delay =
@@ -188,6 +189,7 @@ pub async fn verify_delay_passed(
 		},
 		VerifyDelayOn::Sink => {
 			let actual_proof_height = source.get_proof_height(proof_height).await;
+			let source_client_id = source.try_client_id()?;
 			log::info!(
 				"Checking proof height on {} as {}:{}",
 				sink.name(),
@@ -195,19 +197,18 @@ pub async fn verify_delay_passed(
 				actual_proof_height
 			);
 			let _cs = sink
-				.query_client_consensus(sink_height, source.client_id(), actual_proof_height)
+				.query_client_consensus(sink_height, source_client_id.clone(), actual_proof_height)
 				.await
 				.unwrap()
 				.consensus_state
 				.unwrap_or_else(|| {
 					panic!(
 						"query_client_consensus for {} at height {} is not found",
-						source.client_id(),
-						actual_proof_height
+						source_client_id, actual_proof_height
 					)
 				});
 			if let Ok((sink_client_update_height, sink_client_update_time)) = sink
-				.query_client_update_time_and_height(source.client_id(), actual_proof_height)
+				.query_client_update_time_and_height(source_client_id.clone(), actual_proof_height)
 				.await
 			{
 				let block_delay =
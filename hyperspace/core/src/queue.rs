@@ -12,16 +12,200 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ibc_proto::google::protobuf::Any;
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics02_client::msgs::update_client,
+		ics04_channel::{
+			msgs::{
+				acknowledgement::{self, MsgAcknowledgement},
+				recv_packet::{self, MsgRecvPacket},
+				timeout::{self, MsgTimeout},
+				timeout_on_close::{self, MsgTimeoutOnClose},
+			},
+			packet::Packet,
+		},
+		ics24_host::identifier::{ChannelId, PortId},
+	},
+	Height,
+};
+use ibc_proto::{google::protobuf::Any, ibc::core::channel::v1::Order};
 use metrics::handler::MetricsHandler;
-use primitives::Chain;
+use primitives::{summary::AnySummary, Chain};
+use std::collections::{BTreeMap, BTreeSet};
+use tendermint_proto::Protobuf;
+
+/// Errors returned by the chains when every packet message of a transaction was already
+/// delivered by someone else.
+const REDUNDANT_PACKET_ERRORS: &[&str] =
+	&["packet messages are redundant", "message is redundant, no-op will be performed"];
+
+/// What has to be checked on the sink to know if a packet message is still needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PacketCheck {
+	/// `MsgRecvPacket` is needed while the sink has no receipt for the packet
+	Receipt,
+	/// `MsgAcknowledgement` and `MsgTimeout` are needed while the sink still has the packet
+	/// commitment
+	Commitment,
+}
+
+type PacketKey = (PacketCheck, ChannelId, PortId);
+
+/// Returns the channel and sequence on the sink that the packet message refers to.
+fn packet_key(msg: &Any) -> Option<(PacketKey, u64)> {
+	let (check, packet) = match msg.type_url.as_str() {
+		recv_packet::TYPE_URL =>
+			(PacketCheck::Receipt, MsgRecvPacket::decode_vec(&msg.value).ok()?.packet),
+		acknowledgement::TYPE_URL =>
+			(PacketCheck::Commitment, MsgAcknowledgement::decode_vec(&msg.value).ok()?.packet),
+		timeout::TYPE_URL =>
+			(PacketCheck::Commitment, MsgTimeout::decode_vec(&msg.value).ok()?.packet),
+		timeout_on_close::TYPE_URL =>
+			(PacketCheck::Commitment, MsgTimeoutOnClose::decode_vec(&msg.value).ok()?.packet),
+		_ => return None,
+	};
+	let Packet {
+		sequence, source_port, source_channel, destination_port, destination_channel, ..
+	} = packet;
+	let key = match check {
+		PacketCheck::Receipt => (check, destination_channel, destination_port),
+		PacketCheck::Commitment => (check, source_channel, source_port),
+	};
+	Some((key, sequence.into()))
+}
+
+/// Returns the `seqs` the sink hasn't received on the channel. Receipts only exist on unordered
+/// channels, so on ordered channels the sequences are compared with the sink's next receive
+/// sequence instead.
+async fn query_unreceived<C: Chain>(
+	sink: &C,
+	at: Height,
+	channel_id: ChannelId,
+	port_id: PortId,
+	seqs: Vec<u64>,
+) -> Result<Vec<u64>, C::Error> {
+	let channel = sink.query_channel_end(at, channel_id, port_id.clone()).await?.channel;
+	if !channel.map_or(false, |channel| channel.ordering == Order::Ordered as i32) {
+		return sink.query_unreceived_packets(at, channel_id, port_id, seqs).await
+	}
+	let next_sequence_recv = sink
+		.query_next_sequence_recv(at, &port_id, &channel_id)
+		.await?
+		.next_sequence_receive;
+	Ok(seqs.into_iter().filter(|seq| *seq >= next_sequence_recv).collect())
+}
+
+/// Drops packet messages that were delivered to the sink after they had been built, e.g. by
+/// another relayer instance. The sink is queried once per channel at its latest height. If a
+/// query fails, the messages of that channel are kept.
+pub async fn drop_delivered_packets(msgs: Vec<Any>, sink: &impl Chain) -> Vec<Any> {
+	let keys = msgs.iter().map(packet_key).collect::<Vec<_>>();
+	let mut sequences = BTreeMap::<PacketKey, Vec<u64>>::new();
+	for (key, seq) in keys.iter().flatten() {
+		sequences.entry(key.clone()).or_default().push(*seq);
+	}
+	if sequences.is_empty() {
+		return msgs
+	}
+
+	let sink_height = match sink.latest_height_and_timestamp().await {
+		Ok((height, _)) => height,
+		Err(e) => {
+			log::debug!(target: "hyperspace", "Failed to query latest height of {}: {:?}", sink.name(), e);
+			return msgs
+		},
+	};
+
+	let mut pending = BTreeSet::new();
+	for ((check, channel_id, port_id), seqs) in sequences {
+		let result = match check {
+			PacketCheck::Receipt =>
+				query_unreceived(sink, sink_height, channel_id, port_id.clone(), seqs.clone()).await,
+			PacketCheck::Commitment =>
+				sink.query_unreceived_acknowledgements(
+					sink_height,
+					channel_id,
+					port_id.clone(),
+					seqs.clone(),
+				)
+				.await,
+		};
+		let undelivered = result.unwrap_or_else(|e| {
+			log::debug!(target: "hyperspace", "Failed to query undelivered packets of {}/{} on {}: {:?}", channel_id, port_id, sink.name(), e);
+			seqs
+		});
+		pending
+			.extend(undelivered.into_iter().map(|seq| ((check, channel_id, port_id.clone()), seq)));
+	}
+
+	msgs.into_iter()
+		.zip(keys)
+		.filter_map(|(msg, key)| match key {
+			Some(key) if !pending.contains(&key) => {
+				let ((_, channel_id, port_id), seq) = key;
+				log::info!(
+					target: "hyperspace",
+					"Dropping {} for {}/{} sequence {} as it was already delivered to {}",
+					msg.type_url, channel_id, port_id, seq, sink.name()
+				);
+				None
+			},
+			_ => Some(msg),
+		})
+		.collect()
+}
+
+/// Submits the messages, treating rejections of transactions whose packets were all delivered
+/// by someone else in the meantime as success. The client updates of such a transaction are
+/// submitted again on their own, since later messages may need their consensus states.
+async fn submit_messages(sink: &impl Chain, msgs: Vec<Any>) -> Result<(), anyhow::Error> {
+	let client_updates = msgs
+		.iter()
+		.filter(|msg| msg.type_url == update_client::TYPE_URL)
+		.cloned()
+		.collect::<Vec<_>>();
+	match sink.submit(msgs).await {
+		Ok(_) => Ok(()),
+		Err(e) => {
+			let error = e.to_string();
+			if !REDUNDANT_PACKET_ERRORS.iter().any(|redundant| error.contains(redundant)) {
+				return Err(e.into())
+			}
+			log::warn!(target: "hyperspace", "Packets were already delivered to {}: {}", sink.name(), error);
+			if client_updates.is_empty() {
+				return Ok(())
+			}
+			let summaries =
+				client_updates.iter().map(|msg| AnySummary(msg).to_string()).collect::<Vec<_>>();
+			log::info!(target: "hyperspace", "Resubmitting client updates of the rejected transaction to {}: {summaries:?}", sink.name());
+			sink.submit(client_updates).await.map_err(|e| {
+				anyhow!(
+					"Failed to resubmit client updates {summaries:?} to {}: {:?}",
+					sink.name(),
+					e
+				)
+			})?;
+			Ok(())
+		},
+	}
+}
 
 /// This sends messages to the sink chain in a gas-aware manner.
+///
+/// Packet messages that are no longer needed are dropped right before submission (see
+/// [`drop_delivered_packets`]).
 pub async fn flush_message_batch(
 	msgs: Vec<Any>,
 	metrics: Option<&MetricsHandler>,
 	sink: &impl Chain,
 ) -> Result<(), anyhow::Error> {
+	let msgs = drop_delivered_packets(msgs, sink).await;
+	if msgs.is_empty() {
+		log::debug!(target: "hyperspace", "All outgoing messages were already delivered to {}", sink.name());
+		return Ok(())
+	}
+
 	let block_max_weight = sink.block_max_weight();
 	let batch_weight = sink.estimate_weight(msgs.clone()).await?;
 
@@ -32,7 +216,7 @@ pub async fn flush_message_batch(
 	log::debug!(target: "hyperspace", "Outgoing messages weight: {} block max weight: {}", batch_weight, block_max_weight);
 	let ratio = (batch_weight / block_max_weight) as usize;
 	if ratio == 0 {
-		sink.submit(msgs).await?;
+		submit_messages(sink, msgs).await?;
 		return Ok(())
 	}
 
@@ -53,8 +237,23 @@ pub async fn flush_message_batch(
 	// TODO: return number of failed messages and record it to metrics
 	for batch in msgs.chunks(chunk_size) {
 		// send out batches.
-		sink.submit(batch.to_vec()).await?;
+		submit_messages(sink, batch.to_vec()).await?;
 	}
 
 	Ok(())
 }
+
+/// Records the messages the sink left out of its transactions because they failed the
+/// simulation.
+pub fn record_excluded_messages(metrics: Option<&MetricsHandler>, sink: &impl Chain) {
+	let excluded = sink.common_state().take_excluded_messages();
+	if excluded.is_empty() {
+		return
+	}
+	for message in &excluded {
+		log::debug!(target: "hyperspace", "{} was excluded from a transaction to {}: {}", message.type_url, sink.name(), message.error);
+	}
+	if let Some(metrics) = metrics {
+		metrics.record_excluded_messages(excluded.len() as u64);
+	}
+}
@@ -0,0 +1,375 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use ibc::{
+	core::{
+		ics02_client::client_state::{ClientState as ClientStateT, ClientType},
+		ics24_host::identifier::{ChainId, ClientId},
+	},
+	Height,
+};
+use pallet_ibc::light_clients::AnyClientState;
+use primitives::Chain;
+
+/// A light client on the counterparty that tracks the host chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCandidate {
+	pub client_id: ClientId,
+	/// Latest height of the host chain known to the client
+	pub latest_height: Height,
+}
+
+/// Outcome of matching the configured client id against the clients found on the counterparty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientSelection {
+	/// The configured client exists and tracks the host chain.
+	Configured(ClientId),
+	/// The configured client is missing or invalid, the newest candidate was picked instead.
+	AutoSelected(ClientId),
+	/// No client could be selected.
+	Unresolved { configured: Option<ClientId>, candidates: Vec<ClientId> },
+}
+
+/// Picks the client id the relayer should use. The configured client is kept if it's among the
+/// `candidates`, otherwise the candidate with the highest latest height is picked when
+/// `auto_select` is enabled.
+pub fn select_client_id(
+	configured: Option<&ClientId>,
+	candidates: &[ClientCandidate],
+	auto_select: bool,
+) -> ClientSelection {
+	if let Some(client_id) = configured {
+		if candidates.iter().any(|candidate| &candidate.client_id == client_id) {
+			return ClientSelection::Configured(client_id.clone())
+		}
+	}
+
+	if auto_select {
+		if let Some(newest) = candidates.iter().max_by_key(|candidate| candidate.latest_height) {
+			return ClientSelection::AutoSelected(newest.client_id.clone())
+		}
+	}
+
+	ClientSelection::Unresolved {
+		configured: configured.cloned(),
+		candidates: candidates.iter().map(|candidate| candidate.client_id.clone()).collect(),
+	}
+}
+
+/// Returns the client if it's of `client_type` and tracks the chain `chain_id` at `chain_height`.
+async fn query_candidate(
+	counterparty: &impl Chain,
+	at: Height,
+	client_id: ClientId,
+	client_type: &ClientType,
+	chain_id: &ChainId,
+	chain_height: Height,
+) -> Option<ClientCandidate> {
+	let response = counterparty
+		.query_client_state(at, client_id.clone())
+		.await
+		.map_err(|e| {
+			log::debug!(target: "hyperspace", "Failed to query client state {} on {}: {:?}", client_id, counterparty.name(), e);
+		})
+		.ok()?;
+	let client_state =
+		AnyClientState::try_decode_recursive(response.client_state?, |client_state| {
+			&client_state.client_type() == client_type
+		})
+		.map_err(|e| {
+			log::debug!(target: "hyperspace", "Skipping client {} on {}: {}", client_id, counterparty.name(), e);
+		})
+		.ok()?;
+	if &client_state.chain_id() != chain_id {
+		log::debug!(target: "hyperspace", "Skipping client {} on {} as it tracks {} instead of {}", client_id, counterparty.name(), client_state.chain_id(), chain_id);
+		return None
+	}
+	let latest_height = client_state.latest_height();
+	if latest_height.revision_number != chain_height.revision_number || latest_height > chain_height
+	{
+		return None
+	}
+	Some(ClientCandidate { client_id, latest_height })
+}
+
+/// Makes sure the client id of `chain` refers to a client on `counterparty` that tracks `chain`.
+///
+/// If the configured client id is unset or stale, the clients on the counterparty are listed and
+/// the newest one is picked when `auto_select` is enabled. Otherwise an error naming the
+/// candidates is returned.
+pub async fn reconcile_client_id(
+	chain: &mut impl Chain,
+	counterparty: &impl Chain,
+	auto_select: bool,
+) -> anyhow::Result<ClientId> {
+	let client_type = chain.client_type();
+	// the client state the chain would create for itself carries its chain id
+	let (host_client_state, _) = chain.initialize_client_state().await?;
+	let chain_id = host_client_state.chain_id();
+	let (chain_height, _) = chain.latest_height_and_timestamp().await?;
+	let (counterparty_height, _) = counterparty.latest_height_and_timestamp().await?;
+	let configured = chain.try_client_id().ok();
+
+	let mut candidates = vec![];
+	if let Some(client_id) = configured.clone() {
+		candidates.extend(
+			query_candidate(
+				counterparty,
+				counterparty_height,
+				client_id,
+				&client_type,
+				&chain_id,
+				chain_height,
+			)
+			.await,
+		);
+	}
+	if candidates.is_empty() {
+		for client_id in counterparty.query_clients().await? {
+			candidates.extend(
+				query_candidate(
+					counterparty,
+					counterparty_height,
+					client_id,
+					&client_type,
+					&chain_id,
+					chain_height,
+				)
+				.await,
+			);
+		}
+	}
+
+	match select_client_id(configured.as_ref(), &candidates, auto_select) {
+		ClientSelection::Configured(client_id) => Ok(client_id),
+		ClientSelection::AutoSelected(client_id) => {
+			log::warn!(
+				target: "hyperspace",
+				"Client {:?} of {} is not valid on {}, using {} instead. Update `client_id` in the config of {}",
+				configured, chain.name(), counterparty.name(), client_id, chain.name()
+			);
+			chain.set_client_id(client_id.clone());
+			Ok(client_id)
+		},
+		ClientSelection::Unresolved { configured, candidates } => {
+			let problem = match configured {
+				Some(client_id) => format!(
+					"Client {client_id} of {} doesn't exist on {} or is not a {client_type} client tracking {}",
+					chain.name(),
+					counterparty.name(),
+					chain.name()
+				),
+				None => format!("Client id of {} is not set", chain.name()),
+			};
+			if candidates.is_empty() {
+				Err(anyhow!(
+					"{problem}. No {client_type} clients tracking {} were found on {}, run `create-clients` first",
+					chain.name(),
+					counterparty.name()
+				))
+			} else {
+				let candidates =
+					candidates.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+				Err(anyhow!(
+					"{problem}. Candidates on {}: {candidates}. Set `client_id` in the config of {} or pass `--auto-select-client`",
+					counterparty.name(),
+					chain.name()
+				))
+			}
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use primitives::{mock::MockChain, IbcProvider};
+	use std::str::FromStr;
+
+	fn candidate(client_id: &str, height: u64) -> ClientCandidate {
+		ClientCandidate {
+			client_id: ClientId::from_str(client_id).unwrap(),
+			latest_height: Height::new(1, height),
+		}
+	}
+
+	#[test]
+	fn configured_client_is_kept() {
+		let candidates = [candidate("10-grandpa-0", 10), candidate("10-grandpa-1", 20)];
+		let configured = ClientId::from_str("10-grandpa-0").unwrap();
+		assert_eq!(
+			select_client_id(Some(&configured), &candidates, true),
+			ClientSelection::Configured(configured)
+		);
+	}
+
+	#[test]
+	fn stale_client_is_replaced_by_newest_candidate() {
+		let candidates = [candidate("10-grandpa-1", 30), candidate("10-grandpa-2", 20)];
+		let configured = ClientId::from_str("10-grandpa-0").unwrap();
+		assert_eq!(
+			select_client_id(Some(&configured), &candidates, true),
+			ClientSelection::AutoSelected(ClientId::from_str("10-grandpa-1").unwrap())
+		);
+		assert_eq!(
+			select_client_id(Some(&configured), &candidates, false),
+			ClientSelection::Unresolved {
+				configured: Some(configured),
+				candidates: vec![
+					ClientId::from_str("10-grandpa-1").unwrap(),
+					ClientId::from_str("10-grandpa-2").unwrap()
+				],
+			}
+		);
+	}
+
+	#[test]
+	fn unset_client_is_resolved_only_when_auto_selecting() {
+		let candidates = [candidate("07-tendermint-3", 5)];
+		assert_eq!(
+			select_client_id(None, &candidates, true),
+			ClientSelection::AutoSelected(ClientId::from_str("07-tendermint-3").unwrap())
+		);
+		assert_eq!(
+			select_client_id(None, &[], true),
+			ClientSelection::Unresolved { configured: None, candidates: vec![] }
+		);
+	}
+
+	/// Returns a chain at height 10 and a counterparty that hosts two clients of it and one of
+	/// another chain.
+	fn chains() -> (MockChain, MockChain) {
+		let chain = MockChain::named("chain", 2000);
+		let counterparty = MockChain::named("counterparty", 2001);
+		chain.state().height = 10;
+		let mut state = counterparty.state();
+		state.height = 20;
+		state
+			.clients
+			.insert(ClientId::from_str("10-grandpa-0").unwrap(), chain.host_client_state(5));
+		state
+			.clients
+			.insert(ClientId::from_str("10-grandpa-1").unwrap(), chain.host_client_state(8));
+		let other_chain = MockChain::named("other", 2002);
+		state
+			.clients
+			.insert(ClientId::from_str("10-grandpa-2").unwrap(), other_chain.host_client_state(9));
+		drop(state);
+		(chain, counterparty)
+	}
+
+	async fn query(
+		chain: &MockChain,
+		counterparty: &MockChain,
+		client_id: &str,
+		client_type: &str,
+		chain_height: Height,
+	) -> Option<ClientCandidate> {
+		query_candidate(
+			counterparty,
+			counterparty.height(20),
+			ClientId::from_str(client_id).unwrap(),
+			&client_type.to_string(),
+			&chain.host_client_state(10).chain_id(),
+			chain_height,
+		)
+		.await
+	}
+
+	#[tokio::test]
+	async fn candidate_must_track_the_chain_below_its_height() {
+		let (chain, counterparty) = chains();
+		let height = chain.height(10);
+		assert_eq!(
+			query(&chain, &counterparty, "10-grandpa-1", "10-grandpa", height).await,
+			Some(ClientCandidate {
+				client_id: ClientId::from_str("10-grandpa-1").unwrap(),
+				latest_height: chain.height(8),
+			})
+		);
+		// the client was created for another chain
+		assert_eq!(query(&chain, &counterparty, "10-grandpa-2", "10-grandpa", height).await, None);
+		// the client is of another type
+		assert_eq!(
+			query(&chain, &counterparty, "10-grandpa-1", "07-tendermint", height).await,
+			None
+		);
+		// the client is ahead of the chain, e.g. the chain was reset
+		let behind = chain.height(7);
+		assert_eq!(query(&chain, &counterparty, "10-grandpa-1", "10-grandpa", behind).await, None);
+		let other_revision = Height::new(1, 10);
+		assert_eq!(
+			query(&chain, &counterparty, "10-grandpa-1", "10-grandpa", other_revision).await,
+			None
+		);
+		// the client doesn't exist
+		assert_eq!(query(&chain, &counterparty, "10-grandpa-7", "10-grandpa", height).await, None);
+	}
+
+	#[tokio::test]
+	async fn valid_configured_client_is_kept() {
+		let (mut chain, counterparty) = chains();
+		let client_id = ClientId::from_str("10-grandpa-0").unwrap();
+		chain.set_client_id(client_id.clone());
+		assert_eq!(reconcile_client_id(&mut chain, &counterparty, false).await.unwrap(), client_id);
+		assert_eq!(chain.client_id(), client_id);
+	}
+
+	#[tokio::test]
+	async fn stale_client_is_replaced_only_when_auto_selecting() {
+		let (mut chain, counterparty) = chains();
+		chain.set_client_id(ClientId::from_str("10-grandpa-2").unwrap());
+		let error = reconcile_client_id(&mut chain, &counterparty, false).await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"Client 10-grandpa-2 of chain doesn't exist on counterparty or is not a 10-grandpa \
+			 client tracking chain. Candidates on counterparty: 10-grandpa-0, 10-grandpa-1. Set \
+			 `client_id` in the config of chain or pass `--auto-select-client`"
+		);
+		assert_eq!(chain.client_id(), ClientId::from_str("10-grandpa-2").unwrap());
+
+		let newest = ClientId::from_str("10-grandpa-1").unwrap();
+		assert_eq!(reconcile_client_id(&mut chain, &counterparty, true).await.unwrap(), newest);
+		assert_eq!(chain.client_id(), newest);
+	}
+
+	#[tokio::test]
+	async fn unset_client_is_selected_from_the_counterparty() {
+		let (mut chain, counterparty) = chains();
+		let error = reconcile_client_id(&mut chain, &counterparty, false).await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"Client id of chain is not set. Candidates on counterparty: 10-grandpa-0, \
+			 10-grandpa-1. Set `client_id` in the config of chain or pass `--auto-select-client`"
+		);
+		assert!(chain.try_client_id().is_err());
+
+		let newest = ClientId::from_str("10-grandpa-1").unwrap();
+		assert_eq!(reconcile_client_id(&mut chain, &counterparty, true).await.unwrap(), newest);
+		assert_eq!(chain.client_id(), newest);
+	}
+
+	#[tokio::test]
+	async fn missing_clients_ask_to_create_them() {
+		let (mut chain, counterparty) = chains();
+		counterparty.state().clients.clear();
+		let error = reconcile_client_id(&mut chain, &counterparty, true).await.unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"Client id of chain is not set. No 10-grandpa clients tracking chain were found on \
+			 counterparty, run `create-clients` first"
+		);
+	}
+}
@@ -0,0 +1,80 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Embeds the relayer in an application the way library users do, with chains that don't
+//! connect to any node.
+
+use hyperspace_core::{
+	middleware::{PacketContext, PacketDecision, PacketMiddleware},
+	RelayerBuilder,
+};
+use primitives::{mock::MockChain, Chain};
+use std::time::Duration;
+
+struct ForwardAll;
+
+#[async_trait::async_trait]
+impl PacketMiddleware for ForwardAll {
+	async fn on_packet(&self, _ctx: &PacketContext<'_>) -> PacketDecision {
+		PacketDecision::Forward
+	}
+}
+
+#[tokio::test]
+async fn embedded_relayer_runs_until_shut_down() {
+	let (chain_a, chain_b) = (MockChain::named("mock_a", 2000), MockChain::named("mock_b", 2001));
+	let (subscribed_a, subscribed_b) = (chain_a.subscribed.clone(), chain_b.subscribed.clone());
+	let registry = prometheus::Registry::new();
+	let relayer = RelayerBuilder::new()
+		.with_chains(chain_a, chain_b)
+		.with_metrics(registry.clone())
+		.with_middleware(ForwardAll)
+		.build()
+		.await
+		.unwrap();
+	assert_eq!(relayer.chain_a().name(), "mock_a");
+	assert_eq!(relayer.chain_b().name(), "mock_b");
+	let metrics = registry.gather();
+	for prefix in ["hyperspace_mock_a_", "hyperspace_mock_b_"] {
+		assert!(metrics.iter().any(|family| family.get_name().starts_with(prefix)));
+	}
+
+	let (result, ()) = tokio::join!(relayer.run(), async {
+		// the loop has started once it subscribed to the finality notifications of both chains
+		subscribed_a.notified().await;
+		subscribed_b.notified().await;
+		relayer.shutdown();
+	});
+	result.unwrap();
+
+	let error = relayer.run().await.err().unwrap();
+	assert_eq!(error.to_string(), "Relayer was already started");
+}
+
+#[tokio::test]
+async fn relayer_shut_down_before_running_does_not_start() {
+	let chain_a = MockChain::named("mock_a", 2000);
+	let subscribed = chain_a.subscribed.clone();
+	let relayer = RelayerBuilder::new()
+		.with_chains(chain_a, MockChain::named("mock_b", 2001))
+		.build()
+		.await
+		.unwrap();
+	relayer.shutdown();
+	relayer.run().await.unwrap();
+
+	// a stored permit would make `notified` complete immediately
+	let notified = tokio::time::timeout(Duration::from_millis(100), subscribed.notified()).await;
+	assert!(notified.is_err());
+}
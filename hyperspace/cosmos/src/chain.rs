@@ -145,9 +145,7 @@ where
 		// .and_eq("update_client.header", hex::encode(&update.header.unwrap_or_default()))
 		use tendermint::abci::Event as AbciEvent;
 
-		let mut client = ServiceClient::connect(self.grpc_url().to_string())
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
+		let mut client = ServiceClient::new(self.grpc_client().clone());
 		let mut resp = client
 			.get_txs_event(GetTxsEventRequest {
 				events: query_str
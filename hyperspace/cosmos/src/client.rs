@@ -1,8 +1,9 @@
 #![allow(clippy::all)]
 use super::{
 	key_provider::KeyEntry,
+	key_source::{read_env, EncryptedKeyFile, Secret},
 	light_client::LightClient,
-	tx::{broadcast_tx, confirm_tx, sign_tx, simulate_tx},
+	tx::{broadcast_tx, confirm_tx, isolate_failing_messages, sign_tx, simulate_tx},
 };
 use crate::error::Error;
 use bech32::ToBase32;
@@ -18,24 +19,34 @@ use ibc::core::{
 	},
 };
 use ibc_proto::{
-	cosmos::auth::v1beta1::{query_client::QueryClient, BaseAccount, QueryAccountRequest},
+	cosmos::{
+		auth::v1beta1::{query_client::QueryClient, BaseAccount, QueryAccountRequest},
+		bank::v1beta1::QueryBalanceRequest,
+	},
 	google::protobuf::Any,
 };
 use ics07_tendermint::{
-	client_message::Header, client_state::ClientState, consensus_state::ConsensusState,
+	client_message::{ClientMessage, Header},
+	client_state::ClientState,
+	consensus_state::ConsensusState,
 	merkle::convert_tm_to_ics_merkle_proof,
 };
-use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
+use pallet_ibc::light_clients::{
+	AnyClientMessage, AnyClientState, AnyConsensusState, HostFunctionsManager,
+};
 use primitives::{
-	Chain, CommonClientConfig, CommonClientState, IbcProvider, KeyProvider, UpdateType,
+	Chain, CommonClientConfig, CommonClientState, ExcludedMessage, IbcProvider, KeyProvider,
+	UpdateBuilder, UpdateType,
 };
 use prost::Message;
 use quick_cache::sync::Cache;
 use rand::Rng;
 use ripemd::Ripemd160;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
-	collections::HashSet,
+	collections::BTreeSet,
+	path::PathBuf,
 	str::FromStr,
 	sync::{Arc, Mutex},
 	time::Duration,
@@ -92,9 +103,11 @@ impl TryFrom<MnemonicEntry> for KeyEntry {
 
 	fn try_from(mnemonic_entry: MnemonicEntry) -> Result<Self, Self::Error> {
 		// From mnemonic to pubkey
-		let mnemonic =
-			bip39::Mnemonic::from_phrase(&mnemonic_entry.mnemonic, bip39::Language::English)
-				.unwrap();
+		let mnemonic = bip39::Mnemonic::from_phrase(
+			mnemonic_entry.mnemonic.expose(),
+			bip39::Language::English,
+		)
+		.unwrap();
 		let seed = bip39::Seed::new(&mnemonic, "");
 		let key_m = XPrv::derive_from_path(seed, &DerivationPath::from_str("m/44'/118'/0'/0/0")?)?;
 
@@ -117,7 +130,7 @@ impl TryFrom<MnemonicEntry> for KeyEntry {
 }
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MnemonicEntry {
-	pub mnemonic: String,
+	pub mnemonic: Secret,
 	pub prefix: String,
 }
 // Implements the [`crate::Chain`] trait for cosmos.
@@ -148,7 +161,7 @@ pub struct CosmosClient<H> {
 	/// Connection Id
 	pub connection_id: Arc<Mutex<Option<ConnectionId>>>,
 	/// Channels cleared for packet relay
-	pub channel_whitelist: Arc<Mutex<HashSet<(ChannelId, PortId)>>>,
+	pub channel_whitelist: Arc<Mutex<BTreeSet<(ChannelId, PortId)>>>,
 	/// Light Client instance
 	pub light_client: LightClient,
 	/// The key that signs transactions
@@ -178,22 +191,30 @@ pub struct CosmosClient<H> {
 	pub join_handles: Arc<TokioMutex<Vec<JoinHandle<Result<(), tendermint_rpc::Error>>>>>,
 }
 
+/// Timeout of a single gRPC request to the node
+const GRPC_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// config options for [`ParachainClient`]
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct CosmosClientConfig {
 	/// Chain name
 	pub name: String,
 	/// rpc url for cosmos
+	#[schemars(with = "String")]
 	pub rpc_url: Url,
 	/// grpc url for cosmos
+	#[schemars(with = "Option<String>")]
 	pub grpc_url: Option<Url>,
 	/// websocket url for cosmos
+	#[schemars(with = "Option<String>")]
 	pub websocket_url: Option<Url>,
 	/// Cosmos chain Id
 	pub chain_id: String,
 	/// Light client id on counterparty chain
+	#[schemars(with = "Option<String>")]
 	pub client_id: Option<ClientId>,
 	/// Connection Id
+	#[schemars(with = "Option<String>")]
 	pub connection_id: Option<ConnectionId>,
 	/// Account prefix
 	pub account_prefix: String,
@@ -234,9 +255,21 @@ pub struct CosmosClientConfig {
 	pub extension_options: Vec<ExtensionOption>,// TODO: Could be set to None
 	*/
 	/// Whitelisted channels
+	#[schemars(with = "Vec<(String, String)>")]
 	pub channel_whitelist: Vec<(ChannelId, PortId)>,
-	/// The key that signs transactions
-	pub mnemonic: String,
+	/// The key that signs transactions. Exactly one of `mnemonic`, `mnemonic_env` and `key_file`
+	/// must be set.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub mnemonic: Option<Secret>,
+	/// Name of the environment variable that holds the mnemonic
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub mnemonic_env: Option<String>,
+	/// Path to an [`EncryptedKeyFile`] that holds the mnemonic
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub key_file: Option<PathBuf>,
+	/// Name of the environment variable that holds the passphrase of `key_file`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub key_file_passphrase_env: Option<String>,
 	/// Common client config
 	#[serde(flatten)]
 	pub common: CommonClientConfig,
@@ -244,6 +277,29 @@ pub struct CosmosClientConfig {
 	pub skip_tokens_list: Option<Vec<String>>,
 }
 
+impl CosmosClientConfig {
+	/// Returns the mnemonic from the configured key source.
+	pub fn mnemonic(&self) -> Result<Secret, Error> {
+		match (&self.mnemonic, &self.mnemonic_env, &self.key_file) {
+			(Some(mnemonic), None, None) => Ok(mnemonic.clone()),
+			(None, Some(name), None) => read_env(name),
+			(None, None, Some(path)) => {
+				let passphrase_env = self.key_file_passphrase_env.as_ref().ok_or_else(|| {
+					Error::from(format!(
+						"`key_file_passphrase_env` must be set to use `key_file` for {}",
+						self.name
+					))
+				})?;
+				EncryptedKeyFile::read(path, read_env(passphrase_env)?.expose())
+			},
+			_ => Err(Error::from(format!(
+				"Exactly one of `mnemonic`, `mnemonic_env` and `key_file` must be set for {}",
+				self.name
+			))),
+		}
+	}
+}
+
 impl<H> CosmosClient<H>
 where
 	Self: KeyProvider,
@@ -268,12 +324,7 @@ where
 			.map_err(|e| Error::RpcError(format!("failed to connect to RPC {:?}", e)))?;
 		let mut grpc_client = None;
 		if let Some(grpc_url) = &config.grpc_url {
-			grpc_client = tonic::transport::Endpoint::new(grpc_url.to_string())
-				.map_err(|e| Error::RpcError(format!("failed to connect to RPC {:?}", e)))?
-				.connect()
-				.await
-				.map(Some)
-				.map_err(|e| Error::RpcError(format!("failed to connect to RPC {:?}", e)))?;
+			grpc_client = Some(grpc_channel(grpc_url)?);
 		} else {
 			log::warn!(target: "hyperspace_cosmos", "No grpc url provided for cosmos chain");
 		}
@@ -285,7 +336,7 @@ where
 			.map_err(|e| Error::from(format!("Invalid store prefix {:?}", e)))?;
 
 		let keybase: KeyEntry = KeyEntry::try_from(MnemonicEntry {
-			mnemonic: config.mnemonic,
+			mnemonic: config.mnemonic()?,
 			prefix: config.account_prefix.clone(),
 		})
 		.map_err(|e| e.to_string())?;
@@ -322,6 +373,24 @@ where
 				misbehaviour_client_msg_queue: Arc::new(AsyncMutex::new(vec![])),
 				max_packets_to_process: config.common.max_packets_to_process as usize,
 				skip_tokens_list: config.skip_tokens_list.unwrap_or_default(),
+				channel_weights: config
+					.common
+					.channel_weights
+					.into_iter()
+					.map(|w| ((w.channel_id, w.port_id), w.weight))
+					.collect(),
+				channel_directions: config
+					.common
+					.channel_directions
+					.into_iter()
+					.map(|d| ((d.channel_id, d.port_id), (d.relay_packets, d.relay_acks)))
+					.collect(),
+				max_proof_age: config.common.max_proof_age,
+				circuit_breaker: config.common.circuit_breaker,
+				max_concurrent_proof_queries: config.common.max_concurrent_proof_queries,
+				timeout_sweep_interval: config.common.timeout_sweep_interval,
+				excluded_messages: Default::default(),
+				channel_lifecycles: Default::default(),
 			},
 			join_handles: Arc::new(TokioMutex::new(join_handles)),
 		})
@@ -387,8 +456,37 @@ where
 		let _lock = self.tx_mutex.lock().await;
 		let account_info = self.query_account().await?;
 
+		// Simulate transaction, leaving out the messages that would make it fail
+		let account = &account_info;
+		let simulate = move |messages: Vec<Any>| async move {
+			let (tx, _, tx_bytes) = sign_tx(
+				self.keybase.clone(),
+				self.chain_id.clone(),
+				account,
+				messages,
+				self.get_fee(),
+			)?;
+			let res = simulate_tx(self.grpc_client().clone(), tx, tx_bytes).await?;
+			res.result
+					.map(|r| log::debug!(target: "hyperspace_cosmos", "Simulated transaction: events: {:?}\nlogs: {}", r.events, r.log));
+			Ok::<_, Error>(())
+		};
+		let (messages, excluded) = isolate_failing_messages(messages, simulate).await?;
+		let mut last_error = None;
+		for (message, error) in excluded {
+			log::warn!(target: "hyperspace_cosmos", "Excluding {} from the transaction to {}: {}", message.type_url, self.name, error);
+			self.common_state.on_message_excluded(ExcludedMessage {
+				type_url: message.type_url,
+				error: error.clone(),
+			});
+			last_error = Some(error);
+		}
+		if messages.is_empty() {
+			return Err(Error::from(last_error.unwrap_or_default()))
+		}
+
 		// Sign transaction
-		let (tx, _, tx_bytes) = sign_tx(
+		let (_, _, tx_bytes) = sign_tx(
 			self.keybase.clone(),
 			self.chain_id.clone(),
 			&account_info,
@@ -396,11 +494,6 @@ where
 			self.get_fee(),
 		)?;
 
-		// Simulate transaction
-		let res = simulate_tx(self.grpc_url(), tx, tx_bytes.clone()).await?;
-		res.result
-			.map(|r| log::debug!(target: "hyperspace_cosmos", "Simulated transaction: events: {:?}\nlogs: {}", r.events, r.log));
-
 		// Broadcast transaction
 		let client = &self.rpc_ws_client();
 		let hash = broadcast_tx(client, tx_bytes).await?;
@@ -427,6 +520,14 @@ where
 		self.light_block_cache.get_or_insert_async(&height, fut).await
 	}
 
+	/// Returns the lowest block height the node still serves, blocks below it were pruned.
+	pub async fn query_earliest_height(&self) -> Result<u64, Error> {
+		let status = self.rpc_http_client.status().await.map_err(|e| {
+			Error::RpcError(format!("Failed to query status of {}: {e:?}", self.name))
+		})?;
+		Ok(status.sync_info.earliest_block_height.value())
+	}
+
 	pub async fn msg_update_client_header(
 		&self,
 		from: TmHeight,
@@ -492,9 +593,7 @@ where
 
 	/// Uses the GRPC client to retrieve the account sequence
 	pub async fn query_account(&self) -> Result<BaseAccount, Error> {
-		let mut client = QueryClient::connect(self.grpc_url().to_string())
-			.await
-			.map_err(|e| Error::from(format!("GRPC client error: {:?}", e)))?;
+		let mut client = QueryClient::new(self.grpc_client().clone());
 
 		let request =
 			tonic::Request::new(QueryAccountRequest { address: self.keybase.account.to_string() });
@@ -565,16 +664,108 @@ where
 	}
 }
 
+impl<H> UpdateBuilder for CosmosClient<H> {
+	type Header = Header;
+
+	fn client_message(header: Header) -> AnyClientMessage {
+		AnyClientMessage::Tendermint(ClientMessage::Header(header))
+	}
+}
+
 /// Checks that the two validator sets are equal. The default implementation
 /// of `Eq` cannot be used, because the `proposer` should be ignored.
 fn is_validators_equal(set_a: &ValidatorSet, set_b: &ValidatorSet) -> bool {
 	set_a.hash() == set_b.hash()
 }
 
+/// Checks that the node still has the block following `trusted_height`, whose validator set is
+/// needed to update the client. Once it's pruned, no update can be built from this node.
+pub fn ensure_trusted_height_available(
+	chain: &str,
+	client_id: &ClientId,
+	trusted_height: u64,
+	earliest_height: u64,
+) -> Result<(), Error> {
+	if trusted_height.saturating_add(1) < earliest_height {
+		return Err(Error::TrustedHeightPruned {
+			chain: chain.to_string(),
+			client_id: client_id.to_string(),
+			trusted_height,
+			earliest_height,
+		})
+	}
+	Ok(())
+}
+
+/// Opens the gRPC channel of a [`CosmosClient`]. The channel connects on first use and reconnects
+/// after failures. All queries of the client and its clones share it, instead of opening a
+/// connection per query.
+pub fn grpc_channel(grpc_url: &Url) -> Result<tonic::transport::Channel, Error> {
+	Ok(tonic::transport::Endpoint::new(grpc_url.to_string())
+		.map_err(|e| Error::RpcError(format!("invalid grpc url {:?}", e)))?
+		.timeout(GRPC_REQUEST_TIMEOUT)
+		.connect_lazy())
+}
+
+/// gRPC metadata key that makes a Cosmos SDK node answer a query at a past height
+pub const BLOCK_HEIGHT_HEADER: &str = "x-cosmos-block-height";
+
+/// Builds the `x/bank` balance query of `denom` held by `address`, pinned to `at` if it's given.
+pub fn balance_request(
+	address: String,
+	denom: String,
+	at: Option<Height>,
+) -> Result<tonic::Request<QueryBalanceRequest>, Error> {
+	let mut request = tonic::Request::new(QueryBalanceRequest { address, denom });
+	if let Some(at) = at {
+		let height = at
+			.revision_height
+			.to_string()
+			.parse()
+			.map_err(|e| Error::from(format!("Invalid block height header {at}: {e:?}")))?;
+		request.metadata_mut().insert(BLOCK_HEIGHT_HEADER, height);
+	}
+	Ok(request)
+}
+
 #[cfg(test)]
 pub mod tests {
-	use super::MnemonicEntry;
-	use crate::key_provider::KeyEntry;
+	use super::{
+		balance_request, ensure_trusted_height_available, grpc_channel, CosmosClient,
+		CosmosClientConfig, Header, MnemonicEntry, BLOCK_HEIGHT_HEADER,
+	};
+	use crate::{
+		error::{is_pruned_height_error, Error},
+		key_provider::KeyEntry,
+		key_source::EncryptedKeyFile,
+	};
+	use ibc::{
+		core::{
+			ics02_client::msgs::update_client::{MsgUpdateAnyClient, TYPE_URL},
+			ics24_host::identifier::ClientId,
+		},
+		protobuf::Protobuf,
+		signer::Signer,
+		Height,
+	};
+	use ibc_proto::ibc::applications::interchain_accounts::host::v1::{
+		query_client::QueryClient,
+		query_server::{Query, QueryServer},
+		QueryParamsRequest, QueryParamsResponse,
+	};
+	use ics07_tendermint::client_message::ClientMessage;
+	use pallet_ibc::light_clients::AnyClientMessage;
+	use primitives::{mock::LocalClientTypes, UpdateBuilder};
+	use std::{
+		str::FromStr,
+		sync::{
+			atomic::{AtomicUsize, Ordering},
+			Arc,
+		},
+	};
+	use tendermint::{block::signed_header::SignedHeader, validator::Set as ValidatorSet};
+	use tendermint_rpc::Url;
+	use tokio::net::TcpListener;
 
 	struct TestVector {
 		mnemonic: &'static str,
@@ -613,7 +804,7 @@ pub mod tests {
 	fn test_from_mnemonic() {
 		for vector in TEST_VECTORS {
 			match KeyEntry::try_from(MnemonicEntry {
-				mnemonic: vector.mnemonic.to_string(),
+				mnemonic: vector.mnemonic.to_string().into(),
 				prefix: "cosmos".to_string(),
 			}) {
 				Ok(key_entry) => {
@@ -625,4 +816,183 @@ pub mod tests {
 			}
 		}
 	}
+
+	const CONFIG: &str = r#"
+name = "cosmos"
+rpc_url = "http://127.0.0.1:26657"
+chain_id = "ibcgo-1"
+account_prefix = "cosmos"
+store_prefix = "ibc"
+max_tx_size = 200000
+channel_whitelist = []
+"#;
+
+	fn parse_config(key_source: &str) -> CosmosClientConfig {
+		toml::from_str(&format!("{CONFIG}{key_source}")).unwrap()
+	}
+
+	#[test]
+	fn mnemonic_from_config() {
+		let mnemonic = TEST_VECTORS[1].mnemonic;
+		let config = parse_config(&format!("mnemonic = \"{mnemonic}\""));
+		assert_eq!(config.mnemonic().unwrap().expose(), mnemonic);
+	}
+
+	#[test]
+	fn mnemonic_from_env() {
+		let mnemonic = TEST_VECTORS[1].mnemonic;
+		std::env::set_var("HYPERSPACE_TEST_MNEMONIC", mnemonic);
+		let config = parse_config("mnemonic_env = \"HYPERSPACE_TEST_MNEMONIC\"");
+		assert_eq!(config.mnemonic().unwrap().expose(), mnemonic);
+	}
+
+	#[test]
+	fn mnemonic_from_key_file() {
+		let mnemonic = TEST_VECTORS[1].mnemonic;
+		let key_file = EncryptedKeyFile::encrypt_with_rounds(mnemonic, "passphrase", 1000).unwrap();
+		let path = std::env::temp_dir().join("hyperspace-test-key-file.json");
+		std::fs::write(&path, serde_json::to_string(&key_file).unwrap()).unwrap();
+		std::env::set_var("HYPERSPACE_TEST_KEY_PASSPHRASE", "passphrase");
+		let config = parse_config(&format!(
+			"key_file = \"{}\"\nkey_file_passphrase_env = \"HYPERSPACE_TEST_KEY_PASSPHRASE\"",
+			path.display()
+		));
+		assert_eq!(config.mnemonic().unwrap().expose(), mnemonic);
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn exactly_one_key_source_is_required() {
+		let mnemonic = TEST_VECTORS[1].mnemonic;
+		assert!(parse_config("").mnemonic().is_err());
+		let config = parse_config(&format!(
+			"mnemonic = \"{mnemonic}\"\nmnemonic_env = \"HYPERSPACE_TEST_MNEMONIC\""
+		));
+		assert!(config.mnemonic().is_err());
+	}
+
+	#[test]
+	fn debug_output_has_no_secrets() {
+		let mnemonic = TEST_VECTORS[1].mnemonic;
+		let config = parse_config(&format!("mnemonic = \"{mnemonic}\""));
+		let entry = MnemonicEntry { mnemonic: config.mnemonic().unwrap(), prefix: "cosmos".into() };
+		assert!(!format!("{config:?}").contains(mnemonic));
+		assert!(!format!("{entry:?}").contains(mnemonic));
+	}
+
+	#[test]
+	fn update_is_a_decodable_tendermint_header() {
+		let signed_header = serde_json::from_str::<SignedHeader>(include_str!(
+			"../../../light-clients/ics07-tendermint/src/mock/signed_header.json"
+		))
+		.unwrap();
+		let header = Header {
+			signed_header,
+			validator_set: ValidatorSet::without_proposer(vec![]),
+			trusted_height: Height::new(0, 1),
+			trusted_validator_set: ValidatorSet::without_proposer(vec![]),
+		};
+		let client_id = ClientId::from_str("07-tendermint-0").unwrap();
+		let signer = Signer::from_str("relayer").unwrap();
+
+		let update =
+			CosmosClient::<()>::build_update(header.clone(), client_id.clone(), signer.clone())
+				.unwrap();
+		assert_eq!(update.type_url, TYPE_URL);
+		let msg = MsgUpdateAnyClient::<LocalClientTypes>::decode_vec(&update.value).unwrap();
+		assert_eq!((msg.client_id, msg.signer), (client_id, signer));
+		match msg.client_message {
+			AnyClientMessage::Tendermint(ClientMessage::Header(decoded)) =>
+				assert_eq!(decoded.signed_header, header.signed_header),
+			other => panic!("Expected a tendermint header, found {other:?}"),
+		}
+	}
+
+	#[test]
+	fn pruned_height_errors_are_recognized() {
+		for error in [
+			"Internal error: height 5 is not available, lowest height is 1200 (code: -32603)",
+			"could not find results for height #5",
+			"failed to load state at height 5; version does not exist (latest height: 1300)",
+		] {
+			assert!(is_pruned_height_error(error), "{error}");
+		}
+		assert!(!is_pruned_height_error(
+			"height 1400 must be less than or equal to the current blockchain height 1300"
+		));
+	}
+
+	#[test]
+	fn pruned_trusted_height_requires_operator_action() {
+		let client_id = ClientId::from_str("07-tendermint-0").unwrap();
+		assert!(ensure_trusted_height_available("cosmos", &client_id, 1199, 1200).is_ok());
+		assert!(ensure_trusted_height_available("cosmos", &client_id, 1500, 1200).is_ok());
+		let error = ensure_trusted_height_available("cosmos", &client_id, 100, 1200).unwrap_err();
+		assert!(matches!(
+			error,
+			Error::TrustedHeightPruned { trusted_height: 100, earliest_height: 1200, .. }
+		));
+		assert!(error.to_string().contains("archive node"));
+	}
+
+	#[test]
+	fn balance_request_is_pinned_to_height() {
+		let address = "cosmos1dxsre7u4zkg28k4fqtgy2slcrrq46hqafe6547".to_string();
+		let latest = balance_request(address.clone(), "stake".to_string(), None).unwrap();
+		assert!(latest.metadata().get(BLOCK_HEIGHT_HEADER).is_none());
+
+		let pinned =
+			balance_request(address.clone(), "stake".to_string(), Some(Height::new(1, 4242)))
+				.unwrap();
+		assert_eq!(pinned.metadata().get(BLOCK_HEIGHT_HEADER).unwrap(), "4242");
+		assert_eq!(pinned.get_ref().address, address);
+		assert_eq!(pinned.get_ref().denom, "stake");
+	}
+
+	struct MockQuery;
+
+	#[tonic::async_trait]
+	impl Query for MockQuery {
+		async fn params(
+			&self,
+			_request: tonic::Request<QueryParamsRequest>,
+		) -> Result<tonic::Response<QueryParamsResponse>, tonic::Status> {
+			Ok(tonic::Response::new(QueryParamsResponse::default()))
+		}
+	}
+
+	#[tokio::test]
+	async fn clones_share_one_grpc_connection() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = Url::from_str(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+		let connections = Arc::new(AtomicUsize::new(0));
+		let incoming = futures::stream::unfold(
+			(listener, connections.clone()),
+			|(listener, connections)| async move {
+				let accepted = listener.accept().await.map(|(stream, _)| stream);
+				connections.fetch_add(1, Ordering::SeqCst);
+				Some((accepted, (listener, connections)))
+			},
+		);
+		tokio::spawn(
+			tonic::transport::Server::builder()
+				.add_service(QueryServer::new(MockQuery))
+				.serve_with_incoming(incoming),
+		);
+
+		// the channel is lazy, nothing is connected before the first query
+		let channel = grpc_channel(&url).unwrap();
+		assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+		// every clone of a `CosmosClient` clones the channel, and every query wraps a clone of it
+		let queries = (0..8).map(|_| {
+			let mut client = QueryClient::new(channel.clone());
+			async move { client.params(QueryParamsRequest {}).await }
+		});
+		for response in futures::future::join_all(queries).await {
+			response.unwrap();
+		}
+		QueryClient::new(channel.clone()).params(QueryParamsRequest {}).await.unwrap();
+		assert_eq!(connections.load(Ordering::SeqCst), 1);
+	}
 }
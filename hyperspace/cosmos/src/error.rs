@@ -25,6 +25,29 @@ pub enum Error {
 	/// Tendermint error
 	#[error("Tendermint error: {0}")]
 	TendermintError(#[from] tendermint::Error),
+	/// The node pruned the block the light client on the counterparty trusts
+	#[error("Client {client_id} trusts height {trusted_height} of {chain}, but the node only keeps blocks from height {earliest_height}. The client can't be updated from this node anymore: point the relayer to an archive node or recover the client with a substitute")]
+	TrustedHeightPruned {
+		chain: String,
+		client_id: String,
+		trusted_height: u64,
+		earliest_height: u64,
+	},
+}
+
+/// Messages returned by Tendermint and the Cosmos SDK for queries at heights the node has pruned
+const PRUNED_HEIGHT_ERRORS: &[&str] = &[
+	"is not available, lowest height is",
+	"could not find results for height",
+	"version does not exist",
+	"version mismatch on immutable iavl tree",
+	"failed to load state at height",
+];
+
+/// Returns whether `error` reports that the queried height was pruned by the node.
+pub fn is_pruned_height_error(error: &str) -> bool {
+	let error = error.to_lowercase();
+	PRUNED_HEIGHT_ERRORS.iter().any(|pattern| error.contains(pattern))
 }
 
 impl From<String> for Error {
@@ -0,0 +1,114 @@
+//! Alternative sources of the signing key, so that it doesn't have to be stored in plaintext in
+//! the config file.
+
+use crate::error::Error;
+use aes_gcm::{
+	aead::{Aead, KeyInit},
+	Aes256Gcm, Nonce,
+};
+use hmac::Hmac;
+pub use primitives::secret::Secret;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::Path;
+
+/// Number of PBKDF2 rounds used for new key files
+const KEY_FILE_ROUNDS: u32 = 600_000;
+
+/// A mnemonic encrypted with AES-256-GCM, using a key derived from a passphrase with
+/// PBKDF2-HMAC-SHA256. Stored as JSON with hex-encoded fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKeyFile {
+	pub rounds: u32,
+	pub salt: String,
+	pub nonce: String,
+	pub ciphertext: String,
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], rounds: u32) -> Aes256Gcm {
+	let mut key = [0u8; 32];
+	pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, rounds, &mut key);
+	Aes256Gcm::new(&key.into())
+}
+
+impl EncryptedKeyFile {
+	/// Encrypts `mnemonic` with `passphrase`.
+	pub fn encrypt(mnemonic: &str, passphrase: &str) -> Result<Self, Error> {
+		Self::encrypt_with_rounds(mnemonic, passphrase, KEY_FILE_ROUNDS)
+	}
+
+	pub(crate) fn encrypt_with_rounds(
+		mnemonic: &str,
+		passphrase: &str,
+		rounds: u32,
+	) -> Result<Self, Error> {
+		let mut salt = [0u8; 16];
+		let mut nonce = [0u8; 12];
+		rand::thread_rng().fill_bytes(&mut salt);
+		rand::thread_rng().fill_bytes(&mut nonce);
+		let ciphertext = derive_cipher(passphrase, &salt, rounds)
+			.encrypt(Nonce::from_slice(&nonce), mnemonic.as_bytes())
+			.map_err(|_| Error::from("Failed to encrypt the key".to_string()))?;
+		Ok(Self {
+			rounds,
+			salt: hex::encode(salt),
+			nonce: hex::encode(nonce),
+			ciphertext: hex::encode(ciphertext),
+		})
+	}
+
+	/// Decrypts the mnemonic with `passphrase`.
+	pub fn decrypt(&self, passphrase: &str) -> Result<Secret, Error> {
+		let decode = |field: &str, value: &str| {
+			hex::decode(value).map_err(|e| Error::from(format!("Invalid key file {field}: {e}")))
+		};
+		let salt = decode("salt", &self.salt)?;
+		let nonce = decode("nonce", &self.nonce)?;
+		let ciphertext = decode("ciphertext", &self.ciphertext)?;
+		if nonce.len() != 12 {
+			return Err(Error::from("Invalid key file nonce length".to_string()))
+		}
+		let plaintext = derive_cipher(passphrase, &salt, self.rounds)
+			.decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+			.map_err(|_| {
+				Error::from("Failed to decrypt the key file, wrong passphrase?".to_string())
+			})?;
+		String::from_utf8(plaintext)
+			.map(Secret::from)
+			.map_err(|_| Error::from("Key file doesn't contain a valid mnemonic".to_string()))
+	}
+
+	/// Reads the key file at `path` and decrypts it with `passphrase`.
+	pub fn read(path: &Path, passphrase: &str) -> Result<Secret, Error> {
+		let content = std::fs::read_to_string(path)
+			.map_err(|e| Error::from(format!("Failed to read key file {}: {e}", path.display())))?;
+		let key_file: Self = serde_json::from_str(&content).map_err(|e| {
+			Error::from(format!("Failed to parse key file {}: {e}", path.display()))
+		})?;
+		key_file.decrypt(passphrase)
+	}
+}
+
+/// Reads a secret from the environment variable `name`.
+pub fn read_env(name: &str) -> Result<Secret, Error> {
+	std::env::var(name)
+		.map(Secret::from)
+		.map_err(|e| Error::from(format!("Failed to read environment variable {name}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MNEMONIC: &str = "elite program lift later ask fox change process dirt talk type coconut";
+
+	#[test]
+	fn key_file_roundtrip() {
+		let key_file = EncryptedKeyFile::encrypt_with_rounds(MNEMONIC, "passphrase", 1000).unwrap();
+		let key_file: EncryptedKeyFile =
+			serde_json::from_str(&serde_json::to_string(&key_file).unwrap()).unwrap();
+		assert_eq!(key_file.decrypt("passphrase").unwrap().expose(), MNEMONIC);
+		assert!(key_file.decrypt("wrong passphrase").is_err());
+	}
+}
@@ -20,6 +20,7 @@ pub mod encode;
 pub mod error;
 pub mod events;
 pub mod key_provider;
+pub mod key_source;
 pub mod light_client;
 pub mod provider;
 #[cfg(any(test, feature = "testing"))]
@@ -1,11 +1,11 @@
 use super::{
-	client::CosmosClient,
+	client::{balance_request, ensure_trusted_height_available, CosmosClient},
 	events::{
 		event_is_type_channel, event_is_type_client, event_is_type_connection,
 		ibc_event_try_from_abci_event, IbcEventWithHeight,
 	},
 };
-use crate::error::Error;
+use crate::error::{is_pruned_height_error, Error};
 use futures::{
 	stream::{self, select_all},
 	Stream, StreamExt,
@@ -14,8 +14,7 @@ use ibc::{
 	applications::transfer::{Amount, BaseDenom, PrefixedCoin, PrefixedDenom, TracePath},
 	core::{
 		ics02_client::{
-			client_state::ClientType, events as ClientEvents,
-			msgs::update_client::MsgUpdateAnyClient, trust_threshold::TrustThreshold,
+			client_state::ClientType, events as ClientEvents, trust_threshold::TrustThreshold,
 		},
 		ics04_channel::packet::Sequence,
 		ics23_commitment::{commitment::CommitmentPrefix, specs::ProofSpecs},
@@ -31,12 +30,11 @@ use ibc::{
 	protobuf::Protobuf,
 	signer::Signer,
 	timestamp::Timestamp,
-	tx_msg::Msg,
 	Height,
 };
 use ibc_primitives::PacketInfo as IbcPacketInfo;
 use ibc_proto::{
-	cosmos::{bank::v1beta1::QueryBalanceRequest, base::query::v1beta1::PageRequest},
+	cosmos::base::query::v1beta1::PageRequest,
 	google::protobuf::Any,
 	ibc::core::{
 		channel::v1::{
@@ -55,20 +53,17 @@ use ibc_proto::{
 	},
 };
 use ibc_rpc::PacketInfo;
-use ics07_tendermint::{
-	client_message::ClientMessage, client_state::ClientState, consensus_state::ConsensusState,
-};
+use ics07_tendermint::{client_state::ClientState, consensus_state::ConsensusState};
 use ics08_wasm::msg::MsgPushNewWasmCode;
-use pallet_ibc::light_clients::{
-	AnyClientMessage, AnyClientState, AnyConsensusState, HostFunctionsManager,
-};
+use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
 use primitives::{
-	filter_events_by_ids, mock::LocalClientTypes, Chain, IbcProvider, KeyProvider, UpdateType,
+	filter_events_by_ids, Chain, IbcProvider, KeyProvider, UpdateBuilder, UpdateType,
 };
 use prost::Message;
 use rand::Rng;
 use std::{
-	collections::{hash_map::Entry, HashMap, HashSet},
+	collections::{hash_map::Entry, BTreeSet, HashMap},
+	future::Future,
 	pin::Pin,
 	str::FromStr,
 	time::Duration,
@@ -119,19 +114,19 @@ where
 			FinalityEvent::Tendermint { from: _, to } => to,
 		};
 		let client_id = self.client_id();
-		let latest_cp_height = counterparty.latest_height_and_timestamp().await?.0;
-		let latest_cp_client_state =
-			counterparty.query_client_state(latest_cp_height, client_id.clone()).await?;
-		let client_state_response = latest_cp_client_state
-			.client_state
-			.ok_or_else(|| Error::Custom("counterparty returned empty client state".to_string()))?;
-		let client_state =
-			ClientState::<HostFunctionsManager>::decode_vec(&client_state_response.value)
-				.map_err(|_| Error::Custom("failed to decode client state response".to_string()))?;
+		let client_state = self.query_client_state_on(counterparty).await?;
 		let latest_cp_client_height = client_state.latest_height().revision_height;
 		let latest_height = self.latest_height_and_timestamp().await?.0;
 		let latest_revision = latest_height.revision_number;
 
+		let earliest_height = self.query_earliest_height().await?;
+		ensure_trusted_height_available(
+			&self.name,
+			&client_id,
+			latest_cp_client_height,
+			earliest_height,
+		)?;
+
 		let from = TmHeight::try_from(latest_cp_client_height).unwrap();
 		let to = finality_event_height.min(
 			TmHeight::try_from(latest_cp_client_height + NUMBER_OF_BLOCKS_TO_PROCESS_PER_ITER)
@@ -155,11 +150,16 @@ where
 				let counterparty = counterparty.clone();
 				join_set.spawn(async move {
 					sleep(duration).await;
-					let xs = tokio::time::timeout(
-						Duration::from_secs(30),
-						client.parse_ibc_events_at(&counterparty, latest_revision, height),
-					)
-					.await??;
+					let xs = unless_pruned(
+						tokio::time::timeout(
+							Duration::from_secs(30),
+							client.parse_ibc_events_at(&counterparty, latest_revision, height),
+						)
+						.await?,
+					)?;
+					if xs.is_none() {
+						log::warn!(target: "hyperspace_cosmos", "Results of block {height} were pruned on {}, skipping its events", client.name);
+					}
 					Ok((height, xs))
 				});
 			}
@@ -177,36 +177,83 @@ where
 			))
 		}
 		block_events.sort_by_key(|(height, _)| *height);
+		let state_path = Path::ClientState(ClientStatePath(counterparty.client_id()))
+			.to_string()
+			.into_bytes();
+		let has_pruned_blocks = move_pruned_events(&mut block_events, latest_revision, |height| {
+			let state_path = state_path.clone();
+			async move {
+				self.query_path(state_path, Height::new(latest_revision, height), false)
+					.await
+					.map(|_| ())
+			}
+		})
+		.await?;
+		let last_block = block_events.len().saturating_sub(1);
 
 		let mut updates = Vec::new();
 		for (i, (events, (update_header, mut update_type))) in block_events
 			.into_iter()
-			.map(|(_, events)| events)
+			.map(|(_, events)| events.unwrap_or_default())
 			.zip(update_headers)
 			.enumerate()
 		{
-			if i == NUMBER_OF_BLOCKS_TO_PROCESS_PER_ITER as usize - 1 {
+			// the client has to reach a height whose state the node still has, to prove the
+			// events of pruned blocks and the packets sent in them
+			if i == NUMBER_OF_BLOCKS_TO_PROCESS_PER_ITER as usize - 1 ||
+				(has_pruned_blocks && i == last_block)
+			{
 				update_type = UpdateType::Mandatory;
 			}
 			let height = update_header.height();
-			let update_client_header = {
-				let msg = MsgUpdateAnyClient::<LocalClientTypes> {
-					client_id: client_id.clone(),
-					client_message: AnyClientMessage::Tendermint(ClientMessage::Header(
-						update_header,
-					)),
-					signer: counterparty.account_id(),
-				};
-				let value = msg.encode_vec().map_err(|e| {
-					Error::from(format!("Failed to encode MsgUpdateClient {msg:?}: {e:?}"))
-				})?;
-				Any { value, type_url: msg.type_url() }
-			};
+			let update_client_header =
+				Self::build_update(update_header, client_id.clone(), counterparty.account_id())
+					.map_err(|e| Error::from(e.to_string()))?;
 			updates.push((update_client_header, height, events, update_type));
 		}
 		Ok(updates)
 	}
 
+	async fn query_client_updates_at<C>(
+		&self,
+		counterparty: &C,
+		height: Height,
+	) -> Result<Vec<Any>, anyhow::Error>
+	where
+		C: Chain,
+	{
+		let client_id = self.client_id();
+		let trusted_height = self.query_client_state_on(counterparty).await?.latest_height();
+		// headers have to be newer than the trusted consensus state
+		if trusted_height >= height {
+			return Err(anyhow::anyhow!(
+				"Client {client_id} of {} is at {trusted_height}, can't update it to {height}",
+				self.name
+			))
+		}
+		let earliest_height = self.query_earliest_height().await?;
+		ensure_trusted_height_available(
+			&self.name,
+			&client_id,
+			trusted_height.revision_height,
+			earliest_height,
+		)?;
+
+		// proofs queried at `height` are verified against the header at the proof height, both
+		// consensus states have to be on the counterparty
+		let proof_height = self.get_proof_height(height).await;
+		let from = TmHeight::try_from(height.revision_height - 1)?;
+		let to = TmHeight::try_from(proof_height.revision_height)?;
+		log::info!(target: "hyperspace_cosmos", "Building updates of {client_id} for blocks {}..={}", height, proof_height);
+		self.msg_update_client_header(from, to, trusted_height)
+			.await?
+			.into_iter()
+			.map(|(header, _)| {
+				Self::build_update(header, client_id.clone(), counterparty.account_id())
+			})
+			.collect()
+	}
+
 	// TODO: Changed result: `Item =` from `IbcEvent` to `IbcEventWithHeight` to include the
 	// necessary height field, as `height` is removed from `Attribute` from ibc-rs v0.22.0
 	async fn ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>> {
@@ -503,12 +550,9 @@ where
 		channel_id: ChannelId,
 		port_id: PortId,
 	) -> Result<Vec<u64>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
+		let mut grpc_client = ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 
 		let request = QueryPacketCommitmentsRequest {
 			port_id: port_id.to_string(),
@@ -539,12 +583,9 @@ where
 			channel_id,
 			port_id
 		);
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
+		let mut grpc_client = ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 
 		let request = QueryPacketAcknowledgementsRequest {
 			port_id: port_id.to_string(),
@@ -572,12 +613,9 @@ where
 		port_id: PortId,
 		seqs: Vec<u64>,
 	) -> Result<Vec<u64>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
+		let mut grpc_client = ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 
 		let request = QueryUnreceivedPacketsRequest {
 			port_id: port_id.to_string(),
@@ -603,12 +641,9 @@ where
 		port_id: PortId,
 		seqs: Vec<u64>,
 	) -> Result<Vec<u64>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(e.to_string()))?;
+		let mut grpc_client = ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 
 		let request = QueryUnreceivedAcksRequest {
 			port_id: port_id.to_string(),
@@ -627,7 +662,7 @@ where
 		Ok(commitment_sequences)
 	}
 
-	fn channel_whitelist(&self) -> HashSet<(ChannelId, PortId)> {
+	fn channel_whitelist(&self) -> BTreeSet<(ChannelId, PortId)> {
 		self.channel_whitelist.lock().unwrap().clone()
 	}
 
@@ -636,12 +671,9 @@ where
 		_at: Height,
 		connection_id: &ConnectionId,
 	) -> Result<QueryChannelsResponse, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?;
+		let mut grpc_client = ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 		let request = tonic::Request::new(QueryConnectionChannelsRequest {
 			connection: connection_id.to_string(),
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
@@ -793,7 +825,7 @@ where
 											.to_string(),
 									)
 								})?;
-							info.ack = Some(p.ack);
+							info.ack = Some(p.ack.into());
 							info.height = Some(p.height.revision_height);
 							let entry = block_events.entry(seq);
 							match entry {
@@ -887,36 +919,15 @@ where
 		&self,
 		asset_id: Self::AssetId,
 	) -> Result<Vec<PrefixedCoin>, Self::Error> {
-		let denom = &asset_id;
-		let mut grpc_client = ibc_proto::cosmos::bank::v1beta1::query_client::QueryClient::connect(
-			self.grpc_url().to_string(),
-		)
-		.await
-		.map_err(|e| Error::from(format!("{e:?}")))?;
-
-		let request = tonic::Request::new(QueryBalanceRequest {
-			address: self.keybase.clone().account,
-			denom: denom.to_string(),
-		});
-
-		let response = grpc_client
-			.balance(request)
-			.await
-			.map(|r| r.into_inner())
-			.map_err(|e| Error::from(format!("{e:?}")))?;
-
-		// Querying for a balance might fail, i.e. if the account doesn't actually exist
-		let balance = response
-			.balance
-			.ok_or_else(|| Error::from(format!("No balance for denom {denom}")))?;
+		self.query_balance(&asset_id, None).await
+	}
 
-		Ok(vec![PrefixedCoin {
-			denom: PrefixedDenom {
-				trace_path: TracePath::default(),
-				base_denom: BaseDenom::from_str(denom)?,
-			},
-			amount: Amount::from_str(balance.amount.as_str())?,
-		}])
+	async fn query_ibc_balance_at_height(
+		&self,
+		asset_id: Self::AssetId,
+		at: Height,
+	) -> Result<Vec<PrefixedCoin>, Self::Error> {
+		self.query_balance(&asset_id, Some(at)).await
 	}
 
 	fn connection_prefix(&self) -> CommitmentPrefix {
@@ -927,6 +938,14 @@ where
 		self.client_id()
 	}
 
+	fn try_client_id(&self) -> Result<ClientId, Self::Error> {
+		self.client_id
+			.lock()
+			.unwrap()
+			.clone()
+			.ok_or_else(|| Error::from(format!("Client id is not set for {}", self.name)))
+	}
+
 	fn set_client_id(&mut self, client_id: ClientId) {
 		*self.client_id.lock().unwrap() = Some(client_id);
 	}
@@ -936,7 +955,7 @@ where
 	}
 
 	/// Set the channel whitelist for the relayer task.
-	fn set_channel_whitelist(&mut self, channel_whitelist: HashSet<(ChannelId, PortId)>) {
+	fn set_channel_whitelist(&mut self, channel_whitelist: BTreeSet<(ChannelId, PortId)>) {
 		*self.channel_whitelist.lock().unwrap() = channel_whitelist;
 	}
 
@@ -996,12 +1015,9 @@ where
 		let request = tonic::Request::new(QueryChannelsRequest {
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
 		});
-		let mut grpc_client =
-			ibc_proto::ibc::core::channel::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?;
+		let mut grpc_client = ibc_proto::ibc::core::channel::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 		let response = grpc_client
 			.channels(request)
 			.await
@@ -1023,12 +1039,9 @@ where
 		_height: u32,
 		client_id: String,
 	) -> Result<Vec<IdentifiedConnection>, Self::Error> {
-		let mut grpc_client =
-			ibc_proto::ibc::core::connection::v1::query_client::QueryClient::connect(
-				self.grpc_url().to_string(),
-			)
-			.await
-			.map_err(|e| Error::from(format!("{e:?}")))?;
+		let mut grpc_client = ibc_proto::ibc::core::connection::v1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
 
 		let request = tonic::Request::new(QueryConnectionsRequest {
 			pagination: Some(PageRequest { limit: u32::MAX as _, ..Default::default() }),
@@ -1412,6 +1425,52 @@ where
 		}
 		Ok(ibc_events)
 	}
+
+	/// Queries the client state of this chain on `counterparty` at the latest height.
+	async fn query_client_state_on<C: Chain>(
+		&self,
+		counterparty: &C,
+	) -> Result<ClientState<HostFunctionsManager>, anyhow::Error> {
+		let latest_cp_height = counterparty.latest_height_and_timestamp().await?.0;
+		let latest_cp_client_state =
+			counterparty.query_client_state(latest_cp_height, self.client_id()).await?;
+		let client_state_response = latest_cp_client_state
+			.client_state
+			.ok_or_else(|| Error::Custom("counterparty returned empty client state".to_string()))?;
+		Ok(ClientState::<HostFunctionsManager>::decode_vec(&client_state_response.value)
+			.map_err(|_| Error::Custom("failed to decode client state response".to_string()))?)
+	}
+
+	/// Queries the signer's balance of `denom` at `at`, or at the latest height if it's `None`.
+	async fn query_balance(
+		&self,
+		denom: &str,
+		at: Option<Height>,
+	) -> Result<Vec<PrefixedCoin>, <Self as IbcProvider>::Error> {
+		let mut grpc_client = ibc_proto::cosmos::bank::v1beta1::query_client::QueryClient::new(
+			self.grpc_client().clone(),
+		);
+
+		let request = balance_request(self.keybase.clone().account, denom.to_string(), at)?;
+		let response = grpc_client
+			.balance(request)
+			.await
+			.map(|r| r.into_inner())
+			.map_err(|e| Error::from(format!("{e:?}")))?;
+
+		// Querying for a balance might fail, i.e. if the account doesn't actually exist
+		let balance = response
+			.balance
+			.ok_or_else(|| Error::from(format!("No balance for denom {denom}")))?;
+
+		Ok(vec![PrefixedCoin {
+			denom: PrefixedDenom {
+				trace_path: TracePath::default(),
+				base_denom: BaseDenom::from_str(denom)?,
+			},
+			amount: Amount::from_str(balance.amount.as_str())?,
+		}])
+	}
 }
 
 impl<H: Clone + Send + Sync + 'static> CosmosClient<H> {
@@ -1464,6 +1523,57 @@ impl<H: Clone + Send + Sync + 'static> CosmosClient<H> {
 	}
 }
 
+/// Events of a block, or `None` if the node pruned the block's results.
+fn unless_pruned(events: Result<Vec<IbcEvent>, Error>) -> Result<Option<Vec<IbcEvent>>, Error> {
+	match events {
+		Err(e) if is_pruned_height_error(&e.to_string()) => Ok(None),
+		events => events.map(Some),
+	}
+}
+
+/// Moves the events of blocks whose state the node pruned to the last block, so that they're
+/// proven at its height instead of retrying the pruned one. `query_state` fails with a pruning
+/// error if the state at a height is gone. Returns whether any block was pruned, either its
+/// results or its state.
+async fn move_pruned_events<F, Fut>(
+	blocks: &mut [(u64, Option<Vec<IbcEvent>>)],
+	revision: u64,
+	query_state: F,
+) -> Result<bool, Error>
+where
+	F: Fn(u64) -> Fut,
+	Fut: Future<Output = Result<(), Error>>,
+{
+	let Some(((last_height, last_events), blocks)) = blocks.split_last_mut() else {
+		return Ok(false)
+	};
+	let mut has_pruned_blocks = false;
+	let mut moved = Vec::new();
+	for (height, events) in blocks {
+		let Some(events) = events else {
+			has_pruned_blocks = true;
+			continue
+		};
+		if events.is_empty() {
+			continue
+		}
+		match query_state(*height).await {
+			Ok(()) => (),
+			Err(e) if is_pruned_height_error(&e.to_string()) => {
+				log::warn!(target: "hyperspace_cosmos", "State at {height} was pruned, proving its events at {last_height}");
+				has_pruned_blocks = true;
+				moved.append(events);
+			},
+			Err(e) => return Err(e),
+		}
+	}
+	for event in &mut moved {
+		event.set_height(Height::new(revision, *last_height));
+	}
+	last_events.get_or_insert_with(Vec::new).append(&mut moved);
+	Ok(has_pruned_blocks)
+}
+
 fn increment_proof_height(
 	height: Option<ibc_proto::ibc::core::client::v1::Height>,
 ) -> Option<ibc_proto::ibc::core::client::v1::Height> {
@@ -1472,3 +1582,106 @@ fn increment_proof_height(
 		..height
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{move_pruned_events, unless_pruned, ClientEvents};
+	use crate::error::Error;
+	use ibc::{events::IbcEvent, Height};
+
+	/// Node that pruned the results of the blocks below `earliest_results` and the state below
+	/// `earliest_state`, with a `NewBlock` event in every block it still has.
+	struct PrunedNode {
+		earliest_results: u64,
+		earliest_state: u64,
+	}
+
+	impl PrunedNode {
+		fn block_events(&self, height: u64) -> Result<Vec<IbcEvent>, Error> {
+			if height < self.earliest_results {
+				return Err(Error::from(format!("could not find results for height #{height}")))
+			}
+			Ok(vec![IbcEvent::NewBlock(ClientEvents::NewBlock::new(Height::new(1, height)))])
+		}
+
+		async fn query_state(&self, height: u64) -> Result<(), Error> {
+			if height < self.earliest_state {
+				return Err(Error::from(format!(
+					"failed to load state at height {height}; version does not exist (latest height: 1300)"
+				)))
+			}
+			Ok(())
+		}
+
+		fn blocks(&self, heights: std::ops::Range<u64>) -> Vec<(u64, Option<Vec<IbcEvent>>)> {
+			heights
+				.map(|height| (height, unless_pruned(self.block_events(height)).unwrap()))
+				.collect()
+		}
+	}
+
+	fn event_heights(events: &Option<Vec<IbcEvent>>) -> Option<Vec<u64>> {
+		events
+			.as_ref()
+			.map(|events| events.iter().map(|ev| ev.height().revision_height).collect())
+	}
+
+	#[tokio::test]
+	async fn events_at_pruned_state_are_proven_at_the_last_block() {
+		let node = PrunedNode { earliest_results: 0, earliest_state: 105 };
+		let mut blocks = node.blocks(100..110);
+		let has_pruned_blocks =
+			move_pruned_events(&mut blocks, 1, |height| node.query_state(height))
+				.await
+				.unwrap();
+		assert!(has_pruned_blocks);
+		for (height, events) in &blocks[..5] {
+			assert_eq!(event_heights(events), Some(vec![]), "{height}");
+		}
+		for (height, events) in &blocks[5..9] {
+			assert_eq!(event_heights(events), Some(vec![*height]));
+		}
+		assert_eq!(event_heights(&blocks[9].1), Some(vec![109; 6]));
+	}
+
+	#[tokio::test]
+	async fn pruned_block_results_are_skipped() {
+		let node = PrunedNode { earliest_results: 105, earliest_state: 0 };
+		let mut blocks = node.blocks(100..110);
+		assert!(blocks[..5].iter().all(|(_, events)| events.is_none()));
+		let has_pruned_blocks =
+			move_pruned_events(&mut blocks, 1, |height| node.query_state(height))
+				.await
+				.unwrap();
+		// the last update is made mandatory, packets of the skipped blocks are proven at it
+		assert!(has_pruned_blocks);
+		for (height, events) in &blocks[5..] {
+			assert_eq!(event_heights(events), Some(vec![*height]));
+		}
+	}
+
+	#[tokio::test]
+	async fn blocks_above_the_pruning_threshold_are_kept() {
+		let node = PrunedNode { earliest_results: 100, earliest_state: 100 };
+		let mut blocks = node.blocks(100..110);
+		let has_pruned_blocks =
+			move_pruned_events(&mut blocks, 1, |height| node.query_state(height))
+				.await
+				.unwrap();
+		assert!(!has_pruned_blocks);
+		for (height, events) in &blocks {
+			assert_eq!(event_heights(events), Some(vec![*height]));
+		}
+	}
+
+	#[tokio::test]
+	async fn other_state_errors_fail_the_cycle() {
+		let node = PrunedNode { earliest_results: 0, earliest_state: 0 };
+		let mut blocks = node.blocks(100..110);
+		let result = move_pruned_events(&mut blocks, 1, |_| async {
+			Err(Error::from("connection refused".to_string()))
+		})
+		.await;
+		assert!(result.is_err());
+	}
+}
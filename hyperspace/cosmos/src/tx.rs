@@ -6,9 +6,12 @@ use super::{
 	key_provider::KeyEntry,
 };
 use crate::error::Error;
-use core::time::Duration;
+use core::{future::Future, time::Duration};
 use futures::TryFutureExt;
-use ibc::core::ics24_host::identifier::ChainId;
+use ibc::core::{
+	ics02_client::msgs::update_client::TYPE_URL as UPDATE_CLIENT_TYPE_URL,
+	ics24_host::identifier::ChainId,
+};
 use ibc_proto::{
 	cosmos::{
 		auth::v1beta1::BaseAccount,
@@ -21,8 +24,9 @@ use ibc_proto::{
 use prost::Message;
 use tendermint::Hash;
 use tendermint_rpc::{
-	endpoint::tx::Response as TxResponse, query::Query, Client, Order, Url, WebSocketClient,
+	endpoint::tx::Response as TxResponse, query::Query, Client, Order, WebSocketClient,
 };
+use tonic::transport::Channel;
 
 pub fn sign_tx(
 	key: KeyEntry,
@@ -58,7 +62,7 @@ pub fn sign_tx(
 }
 
 pub async fn simulate_tx(
-	grpc_url: Url,
+	grpc_client: Channel,
 	tx: Tx,
 	tx_bytes: Vec<u8>,
 ) -> Result<SimulateResponse, Error> {
@@ -67,9 +71,7 @@ pub async fn simulate_tx(
 		tx: Some(tx), // needed for simulation to go through with Cosmos SDK <  0.43
 		tx_bytes,     // needed for simulation to go through with Cosmos SDk >= 0.43
 	};
-	let mut client = ServiceClient::connect(grpc_url.clone().to_string())
-		.await
-		.map_err(|e| Error::from(e.to_string()))?;
+	let mut client = ServiceClient::new(grpc_client);
 	let request = tonic::Request::new(req);
 
 	let response = tokio::time::timeout(
@@ -82,6 +84,57 @@ pub async fn simulate_tx(
 	Ok(response)
 }
 
+/// Returns the index of the message that failed a transaction, as reported by the Cosmos SDK in
+/// `failed to execute message; message index: 2: ...`.
+pub fn failing_message_index(error: &str) -> Option<usize> {
+	let (_, rest) = error.split_once("message index: ")?;
+	rest.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+/// Maximum number of messages [`isolate_failing_messages`] leaves out of a batch, i.e. the number
+/// of re-simulations after the first one.
+pub const MAX_EXCLUDED_MESSAGES: usize = 8;
+
+/// Simulates `messages` with `simulate` and leaves out the message the simulation reports as
+/// failing, until the rest of the batch passes. Returns the remaining messages and the excluded
+/// ones with their errors, so that one stale message doesn't cause the whole batch to be rejected.
+///
+/// An error that doesn't name the failing message (e.g. from the ante handler or the connection
+/// to the node) applies to the whole transaction and is returned as is. So is a failing
+/// `MsgUpdateClient`, since the proofs of the other messages depend on it, and the error of a
+/// batch that still fails after [`MAX_EXCLUDED_MESSAGES`] messages were left out.
+pub async fn isolate_failing_messages<F, Fut>(
+	mut messages: Vec<Any>,
+	simulate: F,
+) -> Result<(Vec<Any>, Vec<(Any, String)>), Error>
+where
+	F: Fn(Vec<Any>) -> Fut,
+	Fut: Future<Output = Result<(), Error>>,
+{
+	let mut excluded = vec![];
+	while !messages.is_empty() {
+		let error = match simulate(messages.clone()).await {
+			Ok(()) => break,
+			Err(e) => e.to_string(),
+		};
+		match failing_message_index(&error) {
+			Some(index) if index < messages.len() => {
+				if messages[index].type_url == UPDATE_CLIENT_TYPE_URL {
+					return Err(Error::from(format!("Client update failed: {error}")))
+				}
+				if excluded.len() == MAX_EXCLUDED_MESSAGES {
+					return Err(Error::from(format!(
+						"Batch still fails after leaving out {MAX_EXCLUDED_MESSAGES} messages: {error}"
+					)))
+				}
+				excluded.push((messages.remove(index), error))
+			},
+			_ => return Err(Error::from(error)),
+		}
+	}
+	Ok((messages, excluded))
+}
+
 pub async fn broadcast_tx(rpc_client: &WebSocketClient, tx_bytes: Vec<u8>) -> Result<Hash, Error> {
 	let response = rpc_client
 		.broadcast_tx_sync(tx_bytes)
@@ -147,3 +200,74 @@ pub fn encoded_tx_metrics(
 
 	Ok((total_len, envelope_len))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+
+	const RECV_PACKET: &str = "/ibc.core.channel.v1.MsgRecvPacket";
+	const ACKNOWLEDGEMENT: &str = "/ibc.core.channel.v1.MsgAcknowledgement";
+
+	fn msg(type_url: &str, sequence: u8) -> Any {
+		Any { type_url: type_url.to_string(), value: vec![sequence] }
+	}
+
+	/// Fails like the chain does for an acknowledgement whose packet commitment is already gone.
+	async fn simulate(messages: Vec<Any>) -> Result<(), Error> {
+		match messages.iter().position(|msg| msg.type_url == ACKNOWLEDGEMENT) {
+			Some(index) => Err(Error::from(format!(
+				"status: Unknown, message: \"failed to execute message; message index: {index}: packet commitment not found: packet acknowledgement verification failed\""
+			))),
+			None => Ok(()),
+		}
+	}
+
+	#[test]
+	fn stale_ack_is_excluded_from_batch() {
+		let batch = vec![
+			msg(RECV_PACKET, 1),
+			msg(RECV_PACKET, 2),
+			msg(ACKNOWLEDGEMENT, 3),
+			msg(RECV_PACKET, 4),
+		];
+		let (messages, excluded) = block_on(isolate_failing_messages(batch, simulate)).unwrap();
+		assert_eq!(messages, vec![msg(RECV_PACKET, 1), msg(RECV_PACKET, 2), msg(RECV_PACKET, 4)]);
+		assert_eq!(excluded.len(), 1);
+		assert_eq!(excluded[0].0, msg(ACKNOWLEDGEMENT, 3));
+		assert!(excluded[0].1.contains("packet commitment not found"));
+	}
+
+	#[test]
+	fn batch_error_without_message_index_is_returned() {
+		let error = block_on(isolate_failing_messages(vec![msg(RECV_PACKET, 1)], |_| async {
+			Err(Error::from("account sequence mismatch, expected 5, got 4".to_string()))
+		}))
+		.unwrap_err();
+		assert!(error.to_string().contains("account sequence mismatch"));
+		assert_eq!(failing_message_index("message index: 12: out of gas"), Some(12));
+		assert_eq!(failing_message_index("out of gas"), None);
+	}
+
+	#[test]
+	fn failing_client_update_fails_the_batch() {
+		let batch = vec![msg(UPDATE_CLIENT_TYPE_URL, 0), msg(RECV_PACKET, 1)];
+		let error = block_on(isolate_failing_messages(batch, |_| async {
+			Err(Error::from(
+				"failed to execute message; message index: 0: header expired".to_string(),
+			))
+		}))
+		.unwrap_err();
+		assert!(error.to_string().contains("Client update failed"));
+	}
+
+	#[test]
+	fn resimulations_are_bounded() {
+		let batch = (0..20).map(|sequence| msg(RECV_PACKET, sequence)).collect();
+		let error = block_on(isolate_failing_messages(batch, |_| async {
+			Err(Error::from("failed to execute message; message index: 0: no proof".to_string()))
+		}))
+		.unwrap_err();
+		assert!(error.to_string().contains("after leaving out 8 messages"));
+	}
+}
@@ -143,6 +143,16 @@ pub struct Metrics {
 
 	/// Latest processed height - helpful to prevent pushing the same event twice
 	pub latest_processed_height: Gauge<U64>,
+	/// State of the relay loop circuit breaker: 0 closed, 1 half-open, 2 open.
+	pub circuit_breaker_state: Gauge<U64>,
+	/// Total number of packets held by the packet middlewares.
+	pub number_of_held_packets: Counter<U64>,
+	/// Total number of packets dropped by the packet middlewares.
+	pub number_of_dropped_packets: Counter<U64>,
+	/// Total number of messages left out of transactions because they failed the simulation.
+	pub number_of_excluded_messages: Counter<U64>,
+	/// Whether the client of the chain on its counterparty is frozen: 0 active, 1 frozen.
+	pub counterparty_client_frozen: Gauge<U64>,
 
 	/// Metrics prefix.
 	pub prefix: String,
@@ -353,6 +363,56 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			circuit_breaker_state: register(
+				Gauge::with_opts(
+					Opts::new(
+						"hyperspace_circuit_breaker_state".to_string(),
+						"Relay loop circuit breaker state: 0 closed, 1 half-open, 2 open",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			number_of_held_packets: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_{prefix}_number_of_held_packets"),
+						"Total number of packets held by the packet middlewares.",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			number_of_dropped_packets: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_{prefix}_number_of_dropped_packets"),
+						"Total number of packets dropped by the packet middlewares.",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			number_of_excluded_messages: register(
+				Counter::with_opts(
+					Opts::new(
+						format!("hyperspace_{prefix}_number_of_excluded_messages"),
+						"Total number of messages left out of transactions because they failed the simulation.",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
+			counterparty_client_frozen: register(
+				Gauge::with_opts(
+					Opts::new(
+						"hyperspace_counterparty_client_frozen".to_string(),
+						"Whether the client on the counterparty is frozen: 0 active, 1 frozen",
+					)
+					.const_label("name", prefix.to_string()),
+				)?,
+				registry,
+			)?,
 			prefix: prefix.to_string(),
 		})
 	}
@@ -227,6 +227,23 @@ impl MetricsHandler {
 		self.metrics.transaction_length_for_sent_tx_bundle.observe(batch_size as f64);
 	}
 
+	pub fn set_circuit_state(&self, state: u64) {
+		self.metrics.circuit_breaker_state.set(state);
+	}
+
+	pub fn record_packet_decisions(&self, held: u64, dropped: u64) {
+		self.metrics.number_of_held_packets.inc_by(held);
+		self.metrics.number_of_dropped_packets.inc_by(dropped);
+	}
+
+	pub fn record_excluded_messages(&self, excluded: u64) {
+		self.metrics.number_of_excluded_messages.inc_by(excluded);
+	}
+
+	pub fn set_counterparty_client_frozen(&self, frozen: bool) {
+		self.metrics.counterparty_client_frozen.set(frozen as u64);
+	}
+
 	pub fn observe_last_packet_time(
 		&self,
 		packet: &Packet,
@@ -14,7 +14,7 @@
 
 //! Light client protocols for parachains.
 
-use crate::{error::Error, ParachainClient};
+use crate::{error::Error, utils::decode_grandpa_client_state, ParachainClient};
 use anyhow::anyhow;
 use beefy_light_client_primitives::{ClientState as BeefyPrimitivesClientState, NodesUtils};
 use codec::{Decode, Encode};
@@ -24,12 +24,7 @@ use grandpa_light_client_primitives::{
 	justification::find_scheduled_change, FinalityProof, ParachainHeaderProofs,
 	ParachainHeadersWithFinalityProof,
 };
-use ibc::{
-	core::ics02_client::{client_state::ClientState as _, msgs::update_client::MsgUpdateAnyClient},
-	events::IbcEvent,
-	tx_msg::Msg,
-	Height,
-};
+use ibc::{core::ics02_client::client_state::ClientState as _, events::IbcEvent, Height};
 use ibc_proto::google::protobuf::Any;
 use ibc_rpc::{BlockNumberOrHash, IbcApiClient};
 use ics10_grandpa::client_message::{ClientMessage, Header as GrandpaHeader};
@@ -38,10 +33,11 @@ use ics11_beefy::client_message::{
 };
 use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState};
 use primitives::{
-	filter_events_by_ids, mock::LocalClientTypes, query_maximum_height_for_timeout_proofs, Chain,
-	IbcProvider, KeyProvider, UpdateType,
+	filter_events_by_ids, query_maximum_height_for_timeout_proofs, summary::ClientStateSummary,
+	Chain, IbcProvider, KeyProvider, UpdateBuilder, UpdateType,
 };
 use rand::Rng;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sp_consensus_grandpa::GRANDPA_ENGINE_ID;
 use sp_core::H256;
@@ -61,10 +57,9 @@ use grandpa_prover::{
 use subxt::config::{
 	extrinsic_params::BaseExtrinsicParamsBuilder, ExtrinsicParams, Header as HeaderT, Header,
 };
-use tendermint_proto::Protobuf;
 use tokio::task::JoinSet;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub enum FinalityProtocol {
 	Grandpa,
 	Beefy,
@@ -81,6 +76,28 @@ pub enum FinalityEvent {
 	Beefy(beefy_primitives::SignedCommitment<u32, beefy_primitives::crypto::Signature>),
 }
 
+/// Builds updates of the GRANDPA light client of a parachain
+pub struct GrandpaUpdates;
+
+impl UpdateBuilder for GrandpaUpdates {
+	type Header = GrandpaHeader;
+
+	fn client_message(header: GrandpaHeader) -> AnyClientMessage {
+		AnyClientMessage::Grandpa(ClientMessage::Header(header))
+	}
+}
+
+/// Builds updates of the BEEFY light client of a parachain
+pub struct BeefyUpdates;
+
+impl UpdateBuilder for BeefyUpdates {
+	type Header = BeefyHeader;
+
+	fn client_message(header: BeefyHeader) -> AnyClientMessage {
+		AnyClientMessage::Beefy(BeefyClientMessage::Header(header))
+	}
+}
+
 impl FinalityProtocol {
 	pub async fn query_latest_ibc_events<T, C>(
 		&self,
@@ -165,9 +182,10 @@ where
 	let client_state = response.client_state.ok_or_else(|| {
 		Error::Custom("Received an empty client state from counterparty".to_string())
 	})?;
-	let client_state =
-		AnyClientState::decode_recursive(client_state, |c| matches!(c, AnyClientState::Beefy(_)))
-			.ok_or_else(|| Error::Custom(format!("Failed to decode client state")))?;
+	let client_state = AnyClientState::try_decode_recursive(client_state, |c| {
+		matches!(c, AnyClientState::Beefy(_))
+	})
+	.map_err(|e| Error::ClientStateRehydration(format!("Expected a BEEFY client state: {e}")))?;
 	let beefy_client_state = match &client_state {
 		AnyClientState::Beefy(client_state) => BeefyPrimitivesClientState {
 			latest_beefy_height: client_state.latest_beefy_height,
@@ -176,17 +194,12 @@ where
 			next_authorities: client_state.next_authority_set.clone(),
 		},
 		c => Err(Error::ClientStateRehydration(format!(
-			"Expected AnyClientState::Beefy found: {:?}",
-			c
+			"Expected AnyClientState::Beefy found: {}",
+			ClientStateSummary(c)
 		)))?,
 	};
 
 	if signed_commitment.commitment.validator_set_id < beefy_client_state.current_authorities.id {
-		log::info!(
-			"Commitment: {:#?}\nClientState: {:#?}",
-			signed_commitment.commitment,
-			beefy_client_state
-		);
 		// If validator set id of signed commitment is less than current validator set
 		// id we have Then commitment is outdated and we skip it.
 		log::warn!(
@@ -341,18 +354,11 @@ where
 
 	let mmr_update = source.query_beefy_mmr_update_proof(signed_commitment).await?;
 
-	let update_header = {
-		let msg = MsgUpdateAnyClient::<LocalClientTypes> {
-			client_id: source.client_id(),
-			client_message: AnyClientMessage::Beefy(BeefyClientMessage::Header(BeefyHeader {
-				headers_with_proof,
-				mmr_update_proof: Some(mmr_update),
-			})),
-			signer: counterparty.account_id(),
-		};
-		let value = msg.encode_vec()?;
-		Any { value, type_url: msg.type_url() }
-	};
+	let update_header = BeefyUpdates::build_update(
+		BeefyHeader { headers_with_proof, mmr_update_proof: Some(mmr_update) },
+		source.client_id(),
+		counterparty.account_id(),
+	)?;
 
 	// FIXME: use height from the beefy header
 	Ok(vec![(update_header, Height::new(0, 0), events, update_type)])
@@ -466,14 +472,7 @@ where
 		Error::Custom("Received an empty client state from counterparty".to_string())
 	})?;
 
-	let AnyClientState::Grandpa(client_state) =
-		AnyClientState::decode_recursive(any_client_state, |c| {
-			matches!(c, AnyClientState::Grandpa(_))
-		})
-		.ok_or_else(|| Error::Custom(format!("Could not decode client state")))?
-	else {
-		unreachable!()
-	};
+	let client_state = decode_grandpa_client_state(any_client_state)?;
 
 	let prover = source.grandpa_prover();
 	// prove_finality will always give us the highest block finalized by the authority set for the
@@ -681,15 +680,72 @@ where
 		height: Height::new(source.para_id as u64, finalized_para_height as u64),
 	};
 	let height = grandpa_header.height();
-	let update_header = {
-		let msg = MsgUpdateAnyClient::<LocalClientTypes> {
-			client_id: source.client_id(),
-			client_message: AnyClientMessage::Grandpa(ClientMessage::Header(grandpa_header)),
-			signer: counterparty.account_id(),
-		};
-		let value = msg.encode_vec()?;
-		Any { value, type_url: msg.type_url() }
-	};
+	let update_header = GrandpaUpdates::build_update(
+		grandpa_header,
+		source.client_id(),
+		counterparty.account_id(),
+	)?;
 
 	Ok(vec![(update_header, height, events, update_type)])
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ibc::{
+		core::{
+			ics02_client::msgs::update_client::{MsgUpdateAnyClient, TYPE_URL},
+			ics24_host::identifier::ClientId,
+		},
+		protobuf::Protobuf,
+		signer::Signer,
+	};
+	use ics10_grandpa::client_message::RelayChainHeader;
+	use primitives::mock::LocalClientTypes;
+	use std::str::FromStr;
+
+	fn decode(update: Any) -> AnyClientMessage {
+		assert_eq!(update.type_url, TYPE_URL);
+		MsgUpdateAnyClient::<LocalClientTypes>::decode_vec(&update.value)
+			.unwrap()
+			.client_message
+	}
+
+	fn client_id_and_signer() -> (ClientId, Signer) {
+		(ClientId::from_str("10-grandpa-0").unwrap(), Signer::from_str("relayer").unwrap())
+	}
+
+	#[test]
+	fn grandpa_update_is_a_decodable_grandpa_header() {
+		let header = GrandpaHeader {
+			finality_proof: FinalityProof::<RelayChainHeader> {
+				block: H256::repeat_byte(1),
+				justification: vec![1, 2, 3],
+				unknown_headers: vec![],
+			},
+			parachain_headers: BTreeMap::new(),
+			height: Height::new(2000, 10),
+		};
+		let (client_id, signer) = client_id_and_signer();
+		let update = GrandpaUpdates::build_update(header.clone(), client_id, signer).unwrap();
+		match decode(update) {
+			AnyClientMessage::Grandpa(ClientMessage::Header(decoded)) => {
+				assert_eq!(decoded.finality_proof.block, header.finality_proof.block);
+				assert_eq!(decoded.finality_proof.justification, vec![1, 2, 3]);
+			},
+			other => panic!("Expected a GRANDPA header, found {other:?}"),
+		}
+	}
+
+	#[test]
+	fn beefy_update_is_a_decodable_beefy_header() {
+		let header = BeefyHeader { headers_with_proof: None, mmr_update_proof: None };
+		let (client_id, signer) = client_id_and_signer();
+		let update = BeefyUpdates::build_update(header.clone(), client_id, signer).unwrap();
+		match decode(update) {
+			AnyClientMessage::Beefy(BeefyClientMessage::Header(decoded)) =>
+				assert_eq!(decoded, header),
+			other => panic!("Expected a BEEFY header, found {other:?}"),
+		}
+	}
+}
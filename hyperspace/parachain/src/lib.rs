@@ -15,7 +15,7 @@
 #![allow(clippy::all)]
 
 use std::{
-	collections::{BTreeMap, HashSet},
+	collections::{BTreeMap, BTreeSet},
 	path::PathBuf,
 	str::FromStr,
 	sync::{Arc, Mutex},
@@ -37,6 +37,7 @@ pub mod test_provider;
 
 use error::Error;
 use frame_support::Serialize;
+use schemars::JsonSchema;
 use serde::Deserialize;
 
 use crate::{
@@ -63,7 +64,7 @@ use jsonrpsee_ws_client::WsClientBuilder;
 use light_client_common::config::{AsInner, RuntimeStorage};
 use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState, HostFunctionsManager};
 use pallet_mmr_primitives::Proof;
-use primitives::{CommonClientState, KeyProvider};
+use primitives::{secret::Secret, CommonClientConfig, CommonClientState, KeyProvider};
 use sc_keystore::LocalKeystore;
 use sp_core::{ecdsa, ed25519, sr25519, Bytes, Pair, H256};
 use sp_keystore::KeystorePtr;
@@ -106,7 +107,7 @@ pub struct ParachainClient<T: light_client_common::config::Config> {
 	/// Connection Id
 	pub connection_id: Arc<Mutex<Option<ConnectionId>>>,
 	/// Channels cleared for packet relay
-	pub channel_whitelist: Arc<Mutex<HashSet<(ChannelId, PortId)>>>,
+	pub channel_whitelist: Arc<Mutex<BTreeSet<(ChannelId, PortId)>>>,
 	/// ICS-23 provable store commitment prefix
 	pub commitment_prefix: Vec<u8>,
 	/// Public key for relayer on chain
@@ -158,7 +159,7 @@ impl FromStr for KeyType {
 }
 
 /// config options for [`ParachainClient`]
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ParachainClientConfig {
 	/// Chain name
 	pub name: String,
@@ -169,16 +170,20 @@ pub struct ParachainClientConfig {
 	/// rpc url for relay chain
 	pub relay_chain_rpc_url: String,
 	/// Light client id on counterparty chain
+	#[schemars(with = "Option<String>")]
 	pub client_id: Option<ClientId>,
 	/// Connection Id
+	#[schemars(with = "Option<String>")]
 	pub connection_id: Option<ConnectionId>,
 	/// Commitment prefix
+	#[schemars(with = "String")]
 	pub commitment_prefix: Bytes,
 	/// Raw private key for signing transactions
-	pub private_key: String,
+	pub private_key: Secret,
 	/// used for encoding relayer address.
 	pub ss58_version: u8,
 	/// Channels cleared for packet relay
+	#[schemars(with = "Vec<(String, String)>")]
 	pub channel_whitelist: Vec<(ChannelId, PortId)>,
 	/// Finality protocol
 	pub finality_protocol: FinalityProtocol,
@@ -187,6 +192,9 @@ pub struct ParachainClientConfig {
 	/// All the client states and headers will be wrapped in WASM ones using the WASM code ID.
 	#[serde(default)]
 	pub wasm_code_id: Option<String>,
+	/// Common client config
+	#[serde(flatten)]
+	pub common: CommonClientConfig,
 }
 
 impl<T> ParachainClient<T>
@@ -219,27 +227,26 @@ where
 		let key_type = KeyType::from_str(&config.key_type)?;
 		let key_type_id = key_type.to_key_type_id();
 
+		let private_key = config.private_key.expose();
 		let public_key: MultiSigner = match key_type {
-			KeyType::Sr25519 => sr25519::Pair::from_string_with_seed(&config.private_key, None)
+			KeyType::Sr25519 => sr25519::Pair::from_string_with_seed(private_key, None)
 				.map_err(|_| Error::Custom("invalid key".to_owned()))?
 				.0
 				.public()
 				.into(),
-			KeyType::Ed25519 => ed25519::Pair::from_string_with_seed(&config.private_key, None)
+			KeyType::Ed25519 => ed25519::Pair::from_string_with_seed(private_key, None)
 				.map_err(|_| Error::Custom("invalid key".to_owned()))?
 				.0
 				.public()
 				.into(),
-			KeyType::Ecdsa => ecdsa::Pair::from_string_with_seed(&config.private_key, None)
+			KeyType::Ecdsa => ecdsa::Pair::from_string_with_seed(private_key, None)
 				.map_err(|_| Error::Custom("invalid key".to_owned()))?
 				.0
 				.public()
 				.into(),
 		};
 
-		key_store
-			.insert(key_type_id, &*config.private_key, public_key.as_ref())
-			.unwrap();
+		key_store.insert(key_type_id, private_key, public_key.as_ref()).unwrap();
 
 		assert!(key_store.has_keys(&[(public_key.as_ref().to_vec(), key_type_id)]));
 		Ok(Self {
@@ -262,11 +269,28 @@ where
 			channel_whitelist: Arc::new(Mutex::new(config.channel_whitelist.into_iter().collect())),
 			finality_protocol: config.finality_protocol,
 			common_state: CommonClientState {
-				skip_optional_client_updates: true,
+				skip_optional_client_updates: config.common.skip_optional_client_updates,
 				maybe_has_undelivered_packets: Arc::new(Mutex::new(Default::default())),
 				rpc_call_delay: DEFAULT_RPC_CALL_DELAY,
 				initial_rpc_call_delay: DEFAULT_RPC_CALL_DELAY,
 				misbehaviour_client_msg_queue: Arc::new(AsyncMutex::new(vec![])),
+				max_packets_to_process: config.common.max_packets_to_process as usize,
+				channel_weights: config
+					.common
+					.channel_weights
+					.into_iter()
+					.map(|w| ((w.channel_id, w.port_id), w.weight))
+					.collect(),
+				channel_directions: config
+					.common
+					.channel_directions
+					.into_iter()
+					.map(|d| ((d.channel_id, d.port_id), (d.relay_packets, d.relay_acks)))
+					.collect(),
+				max_proof_age: config.common.max_proof_age,
+				circuit_breaker: config.common.circuit_breaker,
+				max_concurrent_proof_queries: config.common.max_concurrent_proof_queries,
+				timeout_sweep_interval: config.common.timeout_sweep_interval,
 				..Default::default()
 			},
 		})
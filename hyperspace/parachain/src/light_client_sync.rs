@@ -18,22 +18,18 @@ use subxt::config::{
 };
 
 use grandpa_prover::GrandpaProver;
-use ibc::core::ics02_client::msgs::update_client::MsgUpdateAnyClient;
-use tendermint_proto::Protobuf;
 
-use ibc::{
-	core::ics24_host::identifier::ClientId, events::IbcEvent, signer::Signer, tx_msg::Msg, Height,
-};
+use ibc::{core::ics24_host::identifier::ClientId, events::IbcEvent, signer::Signer, Height};
 use ibc_rpc::{BlockNumberOrHash, IbcApiClient};
-use ics10_grandpa::client_message::{ClientMessage, Header as GrandpaHeader};
-use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState};
+use ics10_grandpa::client_message::Header as GrandpaHeader;
 
-use primitives::{
-	filter_events_by_ids, mock::LocalClientTypes, Chain, KeyProvider, LightClientSync,
-};
+use primitives::{filter_events_by_ids, Chain, KeyProvider, LightClientSync, UpdateBuilder};
 
 use super::{error::Error, ParachainClient};
-use crate::finality_protocol::FinalityProtocol;
+use crate::{
+	finality_protocol::{FinalityProtocol, GrandpaUpdates},
+	utils::decode_grandpa_client_state,
+};
 
 const MAX_HEADERS_PER_ITERATION: usize = 100;
 
@@ -72,14 +68,7 @@ where
 		match self.finality_protocol {
 			FinalityProtocol::Grandpa => {
 				let prover = self.grandpa_prover();
-				let AnyClientState::Grandpa(client_state) =
-					AnyClientState::decode_recursive(any_client_state, |c| {
-						matches!(c, AnyClientState::Grandpa(_))
-					})
-					.ok_or_else(|| Error::Custom(format!("Could not decode client state")))?
-				else {
-					unreachable!()
-				};
+				let client_state = decode_grandpa_client_state(any_client_state)?;
 
 				let latest_hash = self.relay_client.rpc().finalized_head().await?;
 				let finalized_head =
@@ -113,14 +102,7 @@ where
 
 		let (messages, events) = match self.finality_protocol {
 			FinalityProtocol::Grandpa => {
-				let AnyClientState::Grandpa(client_state) =
-					AnyClientState::decode_recursive(any_client_state, |c| {
-						matches!(c, AnyClientState::Grandpa(_))
-					})
-					.ok_or_else(|| Error::Custom(format!("Could not decode client state")))?
-				else {
-					unreachable!()
-				};
+				let client_state = decode_grandpa_client_state(any_client_state)?;
 				let latest_hash = self.relay_client.rpc().finalized_head().await?;
 				let finalized_head =
 					self.relay_client.rpc().header(Some(latest_hash)).await?.ok_or_else(|| {
@@ -346,14 +328,8 @@ where
 		height: Height::new(para_id as u64, finalized_para_height as u64),
 	};
 
-	let msg = MsgUpdateAnyClient::<LocalClientTypes> {
-		client_id,
-		client_message: AnyClientMessage::Grandpa(ClientMessage::Header(grandpa_header)),
-		signer,
-	};
-	let value = msg.encode_vec()?;
 	Result::<_, anyhow::Error>::Ok((
-		Any { value, type_url: msg.type_url() },
+		GrandpaUpdates::build_update(grandpa_header, client_id, signer)?,
 		events,
 		latest_finalized_para_height,
 		latest_finalized_height,
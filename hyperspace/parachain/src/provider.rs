@@ -58,7 +58,7 @@ use sp_runtime::{
 	MultiSignature, MultiSigner,
 };
 use std::{
-	collections::{BTreeMap, HashSet},
+	collections::{BTreeMap, BTreeSet},
 	fmt::Display,
 	pin::Pin,
 	str::FromStr,
@@ -454,7 +454,7 @@ where
 		Ok(res)
 	}
 
-	fn channel_whitelist(&self) -> HashSet<(ChannelId, PortId)> {
+	fn channel_whitelist(&self) -> BTreeSet<(ChannelId, PortId)> {
 		self.channel_whitelist.lock().unwrap().iter().cloned().collect()
 	}
 
@@ -608,6 +608,14 @@ where
 		self.client_id()
 	}
 
+	fn try_client_id(&self) -> Result<ClientId, Self::Error> {
+		self.client_id
+			.lock()
+			.unwrap()
+			.clone()
+			.ok_or_else(|| Error::Custom(format!("Client id is not set for {}", self.name)))
+	}
+
 	fn set_client_id(&mut self, client_id: ClientId) {
 		*self.client_id.lock().unwrap() = Some(client_id);
 	}
@@ -799,7 +807,7 @@ where
 	}
 
 	/// Set the channel whitelist for the relayer task.
-	fn set_channel_whitelist(&mut self, channel_whitelist: HashSet<(ChannelId, PortId)>) {
+	fn set_channel_whitelist(&mut self, channel_whitelist: BTreeSet<(ChannelId, PortId)>) {
 		*self.channel_whitelist.lock().unwrap() = channel_whitelist;
 	}
 
@@ -18,6 +18,10 @@ use beefy_primitives::known_payloads::MMR_ROOT_ID;
 use codec::Decode;
 use frame_support::pallet_prelude::{DispatchClass, Weight};
 use frame_system::limits::BlockWeights;
+use ibc::core::ics02_client::client_state::ClientState as _;
+use ibc_proto::google::protobuf::Any;
+use ics10_grandpa::client_state::ClientState as GrandpaClientState;
+use pallet_ibc::light_clients::{AnyClientState, HostFunctionsManager};
 use sp_core::H256;
 
 pub fn get_updated_client_state(
@@ -59,3 +63,21 @@ pub async fn fetch_max_extrinsic_weight<T: light_client_common::config::Config>(
 		.unwrap_or(Weight::from_parts(u64::MAX, 0));
 	Ok(max_extrinsic_weight.ref_time())
 }
+
+/// Decodes the GRANDPA client state of a parachain on its counterparty, which may be wrapped in a
+/// wasm client state.
+pub fn decode_grandpa_client_state(
+	client_state: Any,
+) -> Result<GrandpaClientState<HostFunctionsManager>, Error> {
+	match AnyClientState::try_decode_recursive(client_state, |c| {
+		matches!(c, AnyClientState::Grandpa(_))
+	}) {
+		Ok(AnyClientState::Grandpa(client_state)) => Ok(client_state),
+		Ok(c) => Err(Error::ClientStateRehydration(format!(
+			"Expected a GRANDPA client state, found {}",
+			c.client_type()
+		))),
+		Err(e) =>
+			Err(Error::ClientStateRehydration(format!("Expected a GRANDPA client state: {e}"))),
+	}
+}
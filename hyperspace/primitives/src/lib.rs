@@ -28,10 +28,12 @@ use ibc_proto::{
 	},
 };
 use rand::Rng;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeSet, HashMap},
 	fmt::Debug,
+	num::NonZeroU64,
 	pin::Pin,
 	str::FromStr,
 	sync::{Arc, Mutex},
@@ -39,7 +41,7 @@ use std::{
 };
 use tokio::{sync::Mutex as AsyncMutex, task::JoinSet, time::sleep};
 
-use crate::error::Error;
+use crate::{error::Error, mock::LocalClientTypes};
 #[cfg(any(feature = "testing", test))]
 use ibc::applications::transfer::msgs::transfer::MsgTransfer;
 use ibc::{
@@ -49,6 +51,7 @@ use ibc::{
 			client_consensus::ConsensusState as ConsensusStateT,
 			client_state::{ClientState as ClientStateT, ClientType},
 			events::UpdateClient,
+			msgs::update_client::MsgUpdateAnyClient,
 		},
 		ics04_channel::{
 			channel::{ChannelEnd, Order},
@@ -59,8 +62,10 @@ use ibc::{
 		ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
 	},
 	events::IbcEvent,
+	protobuf::Protobuf,
 	signer::Signer,
 	timestamp::Timestamp,
+	tx_msg::Msg,
 	Height,
 };
 use ibc_proto::ibc::core::{
@@ -71,6 +76,8 @@ use pallet_ibc::light_clients::{AnyClientMessage, AnyClientState, AnyConsensusSt
 
 pub mod error;
 pub mod mock;
+pub mod secret;
+pub mod summary;
 pub mod utils;
 
 pub enum UpdateMessage {
@@ -95,6 +102,34 @@ impl UpdateType {
 	}
 }
 
+/// Builds the `MsgUpdateClient` messages that update the light client of a chain on its
+/// counterparty, so that the chain clients share the signer handling and encoding.
+pub trait UpdateBuilder {
+	/// Header the light client is updated with
+	type Header;
+
+	/// Wraps `header` into a client message of the light client
+	fn client_message(header: Self::Header) -> AnyClientMessage;
+
+	/// Builds the update of the client `client_id`. The message is submitted on the
+	/// counterparty, so `signer` has to be the counterparty's account.
+	fn build_update(
+		header: Self::Header,
+		client_id: ClientId,
+		signer: Signer,
+	) -> Result<Any, anyhow::Error> {
+		let msg = MsgUpdateAnyClient::<LocalClientTypes> {
+			client_id,
+			client_message: Self::client_message(header),
+			signer,
+		};
+		let value = msg.encode_vec().map_err(|e| {
+			anyhow::anyhow!("Failed to encode MsgUpdateClient for {}: {e:?}", msg.client_id)
+		})?;
+		Ok(Any { value, type_url: msg.type_url() })
+	}
+}
+
 fn default_skip_optional_client_updates() -> bool {
 	true
 }
@@ -103,15 +138,112 @@ fn max_packets_to_process() -> u32 {
 	50
 }
 
+/// Default of [`CommonClientConfig::max_concurrent_proof_queries`]
+fn default_max_concurrent_proof_queries() -> usize {
+	16
+}
+
+fn default_channel_weight() -> u32 {
+	1
+}
+
+fn default_relay_direction() -> bool {
+	true
+}
+
+/// Relative share of the per-cycle packet budget given to a whitelisted channel
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ChannelWeight {
+	#[schemars(with = "String")]
+	pub channel_id: ChannelId,
+	#[schemars(with = "String")]
+	pub port_id: PortId,
+	#[serde(default = "default_channel_weight")]
+	pub weight: u32,
+}
+
+/// Directions a whitelisted channel is relayed in, from the perspective of the chain whose
+/// config lists the channel. Both chains of a one-way channel have to be configured, each of them
+/// only controls the messages built from its own state.
+///
+/// Timeouts of packets sent from this chain are relayed regardless of the settings, otherwise
+/// the funds locked by them could never be refunded.
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ChannelDirection {
+	#[schemars(with = "String")]
+	pub channel_id: ChannelId,
+	#[schemars(with = "String")]
+	pub port_id: PortId,
+	/// Deliver the packets sent from this chain to the counterparty
+	#[serde(default = "default_relay_direction")]
+	pub relay_packets: bool,
+	/// Deliver the acknowledgements of packets received from the counterparty back to it
+	#[serde(default = "default_relay_direction")]
+	pub relay_acks: bool,
+}
+
+/// Parameters of the circuit breaker that pauses relaying from a chain after repeated failures
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+	/// Number of consecutive failed relay cycles after which the circuit opens. The breaker is
+	/// disabled when set to 0.
+	pub failure_threshold: u32,
+	/// Seconds relaying is paused for after the circuit opens
+	pub reset_timeout: u64,
+	/// Seconds the trial cycle after a pause may take before it counts as failed
+	pub half_open_timeout: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+	fn default() -> Self {
+		Self { failure_threshold: 5, reset_timeout: 60, half_open_timeout: 30 }
+	}
+}
+
 // TODO: move other fields like `client_id`, `connection_id`, etc. here
 /// Common relayer parameters
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct CommonClientConfig {
 	/// Skip optional client updates
 	#[serde(default = "default_skip_optional_client_updates")]
 	pub skip_optional_client_updates: bool,
 	#[serde(default = "max_packets_to_process")]
 	pub max_packets_to_process: u32,
+	/// Weights used to split `max_packets_to_process` between the whitelisted channels.
+	/// Channels that are not listed here have a weight of 1.
+	#[serde(default)]
+	pub channel_weights: Vec<ChannelWeight>,
+	/// Whitelisted channels that are only relayed in one direction. Channels that are not listed
+	/// here are relayed in both directions.
+	#[serde(default)]
+	pub channel_directions: Vec<ChannelDirection>,
+	/// Maximum age (in blocks of this chain, relative to its latest height) of a packet that is
+	/// waiting for a client update on the counterparty. If the counterparty has no consensus
+	/// state at the packet's height, the relayer builds an update at that height and relays the
+	/// packet in the same cycle, instead of waiting for the next finality event. Older packets
+	/// wait for the updates of the next cycles. Disabled when not set.
+	#[serde(default)]
+	pub max_proof_age: Option<u64>,
+	/// Pauses relaying from this chain after repeated failures, e.g. while its node restarts
+	#[serde(default)]
+	pub circuit_breaker: CircuitBreakerConfig,
+	/// Maximum number of packets, acknowledgements and timeouts whose proofs are queried at the
+	/// same time, across all whitelisted channels
+	#[serde(default = "default_max_concurrent_proof_queries")]
+	pub max_concurrent_proof_queries: usize,
+	/// Interval in seconds at which the timeouts of packets sent from this chain are checked,
+	/// independently of its finality events. Disabled when not set.
+	#[serde(default)]
+	pub timeout_sweep_interval: Option<NonZeroU64>,
+}
+
+/// A message that was left out of a transaction because it failed the simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedMessage {
+	pub type_url: String,
+	/// Error returned by the simulation
+	pub error: String,
 }
 
 /// A common data that all clients should keep.
@@ -133,6 +265,24 @@ pub struct CommonClientState {
 	pub misbehaviour_client_msg_queue: Arc<AsyncMutex<Vec<AnyClientMessage>>>,
 	pub max_packets_to_process: usize,
 	pub skip_tokens_list: Vec<String>,
+	/// Scheduling weights of the whitelisted channels, see [`CommonClientConfig::channel_weights`]
+	pub channel_weights: HashMap<(ChannelId, PortId), u32>,
+	/// Relay directions of the whitelisted channels as `(packets, acks)`, see
+	/// [`CommonClientConfig::channel_directions`]
+	pub channel_directions: HashMap<(ChannelId, PortId), (bool, bool)>,
+	/// See [`CommonClientConfig::max_proof_age`]
+	pub max_proof_age: Option<u64>,
+	/// See [`CommonClientConfig::circuit_breaker`]
+	pub circuit_breaker: CircuitBreakerConfig,
+	/// See [`CommonClientConfig::max_concurrent_proof_queries`]
+	pub max_concurrent_proof_queries: usize,
+	/// See [`CommonClientConfig::timeout_sweep_interval`]
+	pub timeout_sweep_interval: Option<NonZeroU64>,
+	/// Messages left out of submitted transactions since the last
+	/// [`CommonClientState::take_excluded_messages`]
+	pub excluded_messages: Arc<Mutex<Vec<ExcludedMessage>>>,
+	/// Lifecycle of the whitelisted channels that were seen closed
+	pub channel_lifecycles: Arc<Mutex<HashMap<(ChannelId, PortId), ChannelLifecycle>>>,
 }
 
 impl Default for CommonClientState {
@@ -146,6 +296,14 @@ impl Default for CommonClientState {
 			misbehaviour_client_msg_queue: Arc::new(Default::default()),
 			max_packets_to_process: 100,
 			skip_tokens_list: Default::default(),
+			channel_weights: Default::default(),
+			channel_directions: Default::default(),
+			max_proof_age: None,
+			circuit_breaker: Default::default(),
+			max_concurrent_proof_queries: default_max_concurrent_proof_queries(),
+			timeout_sweep_interval: None,
+			excluded_messages: Default::default(),
+			channel_lifecycles: Default::default(),
 		}
 	}
 }
@@ -177,6 +335,57 @@ impl CommonClientState {
 	pub fn set_rpc_call_delay(&mut self, delay: Duration) {
 		self.rpc_call_delay = delay;
 	}
+
+	pub fn on_message_excluded(&self, message: ExcludedMessage) {
+		self.excluded_messages.lock().unwrap().push(message);
+	}
+
+	/// Returns the messages excluded since the last call.
+	pub fn take_excluded_messages(&self) -> Vec<ExcludedMessage> {
+		std::mem::take(&mut *self.excluded_messages.lock().unwrap())
+	}
+
+	pub fn channel_lifecycle(&self, channel_id: ChannelId, port_id: &PortId) -> ChannelLifecycle {
+		self.channel_lifecycles
+			.lock()
+			.unwrap()
+			.get(&(channel_id, port_id.clone()))
+			.copied()
+			.unwrap_or_default()
+	}
+
+	pub fn set_channel_lifecycle(
+		&self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		lifecycle: ChannelLifecycle,
+	) {
+		self.channel_lifecycles.lock().unwrap().insert((channel_id, port_id), lifecycle);
+	}
+
+	/// Returns whether packets sent from this chain on the channel are delivered to the
+	/// counterparty.
+	pub fn relays_packets(&self, channel_id: ChannelId, port_id: &PortId) -> bool {
+		self.channel_directions
+			.get(&(channel_id, port_id.clone()))
+			.map_or(true, |(packets, _)| *packets)
+	}
+
+	/// Returns whether acknowledgements of packets received on the channel are delivered back to
+	/// the counterparty.
+	pub fn relays_acks(&self, channel_id: ChannelId, port_id: &PortId) -> bool {
+		self.channel_directions
+			.get(&(channel_id, port_id.clone()))
+			.map_or(true, |(_, acks)| *acks)
+	}
+
+	/// Returns the scheduling weight of the channel, defaults to 1.
+	pub fn channel_weight(&self, channel_id: ChannelId, port_id: &PortId) -> u32 {
+		self.channel_weights
+			.get(&(channel_id, port_id.clone()))
+			.copied()
+			.unwrap_or_else(default_channel_weight)
+	}
 }
 
 pub fn apply_prefix(mut commitment_prefix: Vec<u8>, path: impl Into<Vec<u8>>) -> Vec<u8> {
@@ -185,6 +394,32 @@ pub fn apply_prefix(mut commitment_prefix: Vec<u8>, path: impl Into<Vec<u8>>) ->
 	commitment_prefix
 }
 
+/// Lifecycle of a whitelisted channel, as tracked by the relayer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelLifecycle {
+	/// Neither end of the channel is closed
+	#[default]
+	Open,
+	/// An end of the channel is closed, but packets sent on it still have to be timed out
+	Draining,
+	/// An end of the channel is closed and no packets are pending. Channels can't be reopened,
+	/// so the relayer stops scheduling the channel.
+	Closed,
+}
+
+impl ChannelLifecycle {
+	/// Returns the next state, given whether an end of the channel is closed and whether packets
+	/// sent on it are still pending.
+	pub fn next(self, closed: bool, has_pending_packets: bool) -> Self {
+		match self {
+			ChannelLifecycle::Closed => ChannelLifecycle::Closed,
+			_ if !closed => ChannelLifecycle::Open,
+			_ if has_pending_packets => ChannelLifecycle::Draining,
+			_ => ChannelLifecycle::Closed,
+		}
+	}
+}
+
 /// A type of undelivered sequences (packets). Can be:
 /// - acknowledgement packet (`Acks`),
 /// - receive packet (`Recvs`)
@@ -221,6 +456,20 @@ pub trait IbcProvider {
 	where
 		T: Chain;
 
+	/// Build the client updates that let the counterparty verify proofs of this chain at
+	/// `height`, trusting the latest height of the client on the counterparty. Used for packets
+	/// that the updates of [`IbcProvider::query_latest_ibc_events`] don't reach yet.
+	async fn query_client_updates_at<T>(
+		&self,
+		_counterparty: &T,
+		height: Height,
+	) -> Result<Vec<Any>, anyhow::Error>
+	where
+		T: Chain,
+	{
+		Err(anyhow::anyhow!("Building client updates at height {height} is not supported"))
+	}
+
 	/// Return a stream that yields when new [`IbcEvents`] are parsed from a finality notification
 	async fn ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>>;
 
@@ -339,7 +588,7 @@ pub trait IbcProvider {
 	) -> Result<Vec<u64>, Self::Error>;
 
 	/// Channel whitelist
-	fn channel_whitelist(&self) -> HashSet<(ChannelId, PortId)>;
+	fn channel_whitelist(&self) -> BTreeSet<(ChannelId, PortId)>;
 
 	/// Query all channels for a connection
 	async fn query_connection_channels(
@@ -391,12 +640,27 @@ pub trait IbcProvider {
 		asset_id: Self::AssetId,
 	) -> Result<Vec<PrefixedCoin>, Self::Error>;
 
+	/// Same as [`IbcProvider::query_ibc_balance`], but reads the balances at `at` instead of the
+	/// latest height.
+	async fn query_ibc_balance_at_height(
+		&self,
+		_asset_id: Self::AssetId,
+		at: Height,
+	) -> Result<Vec<PrefixedCoin>, Self::Error> {
+		Err(Self::Error::from(format!("Querying balances at height {at} is not supported")))
+	}
+
 	/// Return the chain connection prefix
 	fn connection_prefix(&self) -> CommitmentPrefix;
 
 	/// Return the host chain's light client id on counterparty chain
+	///
+	/// Panics if the client id is not set, prefer [`IbcProvider::try_client_id`].
 	fn client_id(&self) -> ClientId;
 
+	/// Return the host chain's light client id on counterparty chain or an error if it's not set
+	fn try_client_id(&self) -> Result<ClientId, Self::Error>;
+
 	/// Set the client id for the relayer task.
 	fn set_client_id(&mut self, client_id: ClientId);
 
@@ -404,7 +668,7 @@ pub trait IbcProvider {
 	fn connection_id(&self) -> Option<ConnectionId>;
 
 	/// Set the channel whitelist for the relayer task.
-	fn set_channel_whitelist(&mut self, channel_whitelist: HashSet<(ChannelId, PortId)>);
+	fn set_channel_whitelist(&mut self, channel_whitelist: BTreeSet<(ChannelId, PortId)>);
 
 	/// Set the channel whitelist for the relayer task.
 	fn add_channel_to_whitelist(&mut self, channel: (ChannelId, PortId));
@@ -625,7 +889,7 @@ pub async fn query_undelivered_sequences(
 			.query_next_sequence_recv(sink_height, &counterparty_port_id, &counterparty_channel_id)
 			.await?
 			.next_sequence_receive;
-		seqs.into_iter().filter(|seq| *seq > next_seq_recv).collect()
+		seqs.into_iter().filter(|seq| *seq >= next_seq_recv).collect()
 	};
 
 	Ok(undelivered_sequences)
@@ -683,7 +947,9 @@ pub async fn query_undelivered_acks(
 	Ok(undelivered_acks)
 }
 
-pub fn packet_info_to_packet(packet_info: &PacketInfo) -> Packet {
+/// Converts the packet info into a packet. The packet data is moved rather than copied, as long as
+/// no clone of the packet info shares it.
+pub fn packet_info_to_packet(packet_info: PacketInfo) -> Packet {
 	Packet {
 		sequence: packet_info.sequence.into(),
 		source_port: PortId::from_str(&packet_info.source_port).expect("Port should be valid"),
@@ -693,7 +959,7 @@ pub fn packet_info_to_packet(packet_info: &PacketInfo) -> Packet {
 			.expect("Port should be valid"),
 		destination_channel: ChannelId::from_str(&packet_info.destination_channel)
 			.expect("Channel should be valid"),
-		data: packet_info.data.clone(),
+		data: packet_info.data.into(),
 		timeout_height: packet_info.timeout_height.clone().into(),
 		timeout_timestamp: Timestamp::from_nanoseconds(packet_info.timeout_timestamp)
 			.expect("Timestamp should be valid"),
@@ -888,7 +1154,7 @@ pub fn filter_events_by_ids(
 	ev: &IbcEvent,
 	client_ids: &[ClientId],
 	connection_ids: &[ConnectionId],
-	channel_and_port_ids: &HashSet<(ChannelId, PortId)>,
+	channel_and_port_ids: &BTreeSet<(ChannelId, PortId)>,
 ) -> bool {
 	use ibc::core::{
 		ics02_client::events::Attributes as ClientAttributes,
@@ -966,3 +1232,65 @@ pub fn filter_events_by_ids(
 	}
 	v
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn closed_channel_is_scheduled_until_drained() {
+		let state = CommonClientState::default();
+		let (channel_id, port_id) = (ChannelId::new(0), PortId::transfer());
+		let mut lifecycle = state.channel_lifecycle(channel_id, &port_id);
+		assert_eq!(lifecycle, ChannelLifecycle::Open);
+
+		// the channel is closed mid-run with two packets left to time out
+		for pending in [2, 1, 0] {
+			lifecycle = lifecycle.next(true, pending > 0);
+			state.set_channel_lifecycle(channel_id, port_id.clone(), lifecycle);
+		}
+		assert_eq!(state.channel_lifecycle(channel_id, &port_id), ChannelLifecycle::Closed);
+
+		// closing is final
+		assert_eq!(lifecycle.next(false, false), ChannelLifecycle::Closed);
+		assert_eq!(ChannelLifecycle::Draining.next(true, true), ChannelLifecycle::Draining);
+		assert_eq!(ChannelLifecycle::Open.next(false, true), ChannelLifecycle::Open);
+	}
+
+	#[test]
+	fn channels_are_relayed_in_both_directions_by_default() {
+		let state = CommonClientState {
+			channel_directions: [((ChannelId::new(1), PortId::transfer()), (true, false))].into(),
+			..Default::default()
+		};
+		assert!(state.relays_packets(ChannelId::new(1), &PortId::transfer()));
+		assert!(!state.relays_acks(ChannelId::new(1), &PortId::transfer()));
+		assert!(state.relays_packets(ChannelId::new(0), &PortId::transfer()));
+		assert!(state.relays_acks(ChannelId::new(0), &PortId::transfer()));
+	}
+
+	#[test]
+	fn packet_data_is_moved_into_the_packet() {
+		let data = vec![7u8; 4096];
+		let data_ptr = data.as_ptr();
+		let packet_info = PacketInfo {
+			height: Some(10),
+			sequence: 1,
+			source_port: "transfer".to_string(),
+			source_channel: "channel-0".to_string(),
+			destination_port: "transfer".to_string(),
+			destination_channel: "channel-1".to_string(),
+			channel_order: "ORDER_UNORDERED".to_string(),
+			data: data.into(),
+			timeout_height: ibc_proto::ibc::core::client::v1::Height {
+				revision_number: 1,
+				revision_height: 1000,
+			},
+			timeout_timestamp: 0,
+			ack: None,
+		};
+		let packet = packet_info_to_packet(packet_info);
+		assert_eq!(packet.data, vec![7u8; 4096]);
+		assert_eq!(packet.data.as_ptr(), data_ptr);
+	}
+}
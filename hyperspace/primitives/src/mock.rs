@@ -15,6 +15,12 @@
 use ibc::core::ics02_client::context::ClientTypes;
 use pallet_ibc::light_clients::{AnyClient, AnyClientMessage, AnyClientState, AnyConsensusState};
 
+#[cfg(feature = "testing")]
+mod chain;
+
+#[cfg(feature = "testing")]
+pub use chain::{MockChain, MockConfig, MockState};
+
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct LocalClientTypes;
 
@@ -0,0 +1,829 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An in-memory [`Chain`] for tests that don't connect to any node.
+
+use crate::{
+	error::Error, mock::LocalClientTypes, Chain, CommonClientState, IbcProvider, KeyProvider,
+	LightClientSync, MisbehaviourHandler, TestProvider, UpdateType,
+};
+use futures::Stream;
+use ibc::{
+	applications::transfer::{msgs::transfer::MsgTransfer, PrefixedCoin},
+	core::{
+		ics02_client::{
+			client_state::{ClientState as ClientStateT, ClientType},
+			events::UpdateClient,
+			msgs::create_client::{MsgCreateAnyClient, TYPE_URL as CREATE_CLIENT_TYPE_URL},
+		},
+		ics03_connection::connection::ConnectionEnd,
+		ics04_channel::channel::ChannelEnd,
+		ics23_commitment::commitment::CommitmentPrefix,
+		ics24_host::identifier::{ChannelId, ClientId, ConnectionId, PortId},
+	},
+	events::IbcEvent,
+	protobuf::Protobuf,
+	signer::Signer,
+	timestamp::Timestamp,
+	Height,
+};
+use ibc_proto::{
+	google::protobuf::Any,
+	ibc::core::{
+		channel::v1::{
+			Channel as RawChannel, IdentifiedChannel, QueryChannelResponse, QueryChannelsResponse,
+			QueryNextSequenceReceiveResponse, QueryPacketAcknowledgementResponse,
+			QueryPacketCommitmentResponse, QueryPacketReceiptResponse,
+		},
+		client::v1::{QueryClientStateResponse, QueryConsensusStateResponse},
+		connection::v1::{
+			ConnectionEnd as RawConnectionEnd, IdentifiedConnection, QueryConnectionResponse,
+		},
+	},
+};
+use ibc_rpc::PacketInfo;
+use ics10_grandpa::{
+	client_state::ClientState as GrandpaClientState,
+	consensus_state::ConsensusState as GrandpaConsensusState,
+};
+use pallet_ibc::{
+	light_clients::{AnyClientMessage, AnyClientState, AnyConsensusState, HostFunctionsManager},
+	Timeout,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	pin::Pin,
+	str::FromStr,
+	sync::{Arc, Mutex, MutexGuard},
+	time::Duration,
+};
+use tokio::sync::Notify;
+
+/// Timestamp of the first block of every mock chain
+const GENESIS_TIMESTAMP_NANOS: u64 = 1_600_000_000_000_000_000;
+
+/// Config of a [`MockChain`], every chain created from a config starts with an empty state.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MockConfig {
+	/// Chain name
+	pub name: String,
+	/// Parachain id the chain identifies with in its client states, also the revision number of
+	/// its heights
+	pub para_id: u32,
+	/// Light client id on counterparty chain
+	#[schemars(with = "Option<String>")]
+	pub client_id: Option<ClientId>,
+	/// Connection Id
+	#[schemars(with = "Option<String>")]
+	pub connection_id: Option<ConnectionId>,
+	/// Channels cleared for packet relay
+	#[serde(default)]
+	#[schemars(with = "Vec<(String, String)>")]
+	pub channel_whitelist: Vec<(ChannelId, PortId)>,
+	/// All the client states and headers will be wrapped in WASM ones using the WASM code ID.
+	#[serde(default)]
+	pub wasm_code_id: Option<String>,
+}
+
+/// IBC state of a [`MockChain`], shared between its clones. Tests change it directly to set up
+/// the scenario they need.
+#[derive(Default)]
+pub struct MockState {
+	/// Latest block height
+	pub height: u64,
+	/// Clients of the counterparties hosted on this chain
+	pub clients: BTreeMap<ClientId, AnyClientState>,
+	/// Consensus states of the hosted clients by client height
+	pub consensus_states: BTreeMap<(ClientId, Height), AnyConsensusState>,
+	pub connections: BTreeMap<ConnectionId, ConnectionEnd>,
+	pub channels: BTreeMap<(ChannelId, PortId), ChannelEnd>,
+	/// Packets sent from this chain whose commitments weren't cleared by an acknowledgement yet
+	pub sent_packets: Vec<PacketInfo>,
+	/// Packets received on this chain, with their acknowledgements
+	pub received_packets: Vec<PacketInfo>,
+	/// Next receive sequences of the ordered channels, `1` if unset
+	pub next_sequence_recv: BTreeMap<(ChannelId, PortId), u64>,
+	/// Messages of every submitted transaction, the index is the transaction id
+	pub transactions: Vec<Vec<Any>>,
+	/// Clients created by a transaction, by transaction id
+	pub created_clients: BTreeMap<usize, ClientId>,
+}
+
+/// A [`Chain`] that keeps its IBC state in memory. Every query is answered from the
+/// [`MockState`], queries of missing state fail like they would on a node. Create client
+/// messages are executed when submitted, other messages are only recorded.
+///
+/// The chain never finalizes a block, its finality notifications pend forever.
+#[derive(Clone)]
+pub struct MockChain {
+	name: String,
+	para_id: u32,
+	client_id: Option<ClientId>,
+	connection_id: Option<ConnectionId>,
+	channel_whitelist: BTreeSet<(ChannelId, PortId)>,
+	common_state: CommonClientState,
+	state: Arc<Mutex<MockState>>,
+	/// Notified when the finality notifications are subscribed to
+	pub subscribed: Arc<Notify>,
+}
+
+impl MockChain {
+	pub async fn new(config: MockConfig) -> Result<Self, Error> {
+		let mut chain = Self::named(&config.name, config.para_id);
+		chain.client_id = config.client_id;
+		chain.connection_id = config.connection_id;
+		chain.channel_whitelist = config.channel_whitelist.into_iter().collect();
+		Ok(chain)
+	}
+
+	/// Returns an unconfigured chain at height 1.
+	pub fn named(name: &str, para_id: u32) -> Self {
+		Self {
+			name: name.to_string(),
+			para_id,
+			client_id: None,
+			connection_id: None,
+			channel_whitelist: BTreeSet::new(),
+			common_state: CommonClientState::default(),
+			state: Arc::new(Mutex::new(MockState { height: 1, ..Default::default() })),
+			subscribed: Arc::new(Notify::new()),
+		}
+	}
+
+	pub fn state(&self) -> MutexGuard<'_, MockState> {
+		self.state.lock().unwrap()
+	}
+
+	/// Returns the height `height` of this chain.
+	pub fn height(&self, height: u64) -> Height {
+		Height::new(self.para_id.into(), height)
+	}
+
+	/// Returns the state of a client tracking this chain at `height`.
+	pub fn host_client_state(&self, height: u64) -> AnyClientState {
+		AnyClientState::Grandpa(GrandpaClientState::<HostFunctionsManager> {
+			para_id: self.para_id,
+			latest_para_height: height as u32,
+			..Default::default()
+		})
+	}
+
+	/// Returns the consensus state of this chain at `height`.
+	pub fn host_consensus_state(&self, height: u64) -> AnyConsensusState {
+		let timestamp = timestamp_at(height)
+			.into_tm_time()
+			.expect("mock timestamps are never zero; qed");
+		AnyConsensusState::Grandpa(GrandpaConsensusState::new(
+			height.to_be_bytes().to_vec(),
+			timestamp,
+		))
+	}
+
+	fn not_found(&self, what: String) -> Error {
+		Error::Custom(format!("{what} not found on {}", self.name))
+	}
+}
+
+fn timestamp_at(height: u64) -> Timestamp {
+	let nanos = GENESIS_TIMESTAMP_NANOS + height * Duration::from_secs(6).as_nanos() as u64;
+	Timestamp::from_nanoseconds(nanos).expect("mock timestamps are valid; qed")
+}
+
+fn on_channel(
+	packet_port: &str,
+	packet_channel: &str,
+	channel_id: &ChannelId,
+	port_id: &PortId,
+) -> bool {
+	packet_channel == channel_id.to_string() && packet_port == port_id.as_str()
+}
+
+#[async_trait::async_trait]
+impl IbcProvider for MockChain {
+	type FinalityEvent = ();
+	type TransactionId = usize;
+	type AssetId = String;
+	type Error = Error;
+
+	async fn query_latest_ibc_events<T>(
+		&mut self,
+		_finality_event: Self::FinalityEvent,
+		_counterparty: &T,
+	) -> Result<Vec<(Any, Height, Vec<IbcEvent>, UpdateType)>, anyhow::Error>
+	where
+		T: Chain,
+	{
+		Ok(vec![])
+	}
+
+	async fn ibc_events(&self) -> Pin<Box<dyn Stream<Item = IbcEvent> + Send + 'static>> {
+		Box::pin(futures::stream::pending())
+	}
+
+	async fn query_client_consensus(
+		&self,
+		_at: Height,
+		client_id: ClientId,
+		consensus_height: Height,
+	) -> Result<QueryConsensusStateResponse, Self::Error> {
+		let consensus_state = self
+			.state()
+			.consensus_states
+			.get(&(client_id.clone(), consensus_height))
+			.cloned()
+			.ok_or_else(|| {
+				self.not_found(format!("Consensus state of {client_id} at {consensus_height}"))
+			})?;
+		Ok(QueryConsensusStateResponse {
+			consensus_state: Some(consensus_state.into()),
+			..Default::default()
+		})
+	}
+
+	async fn query_client_state(
+		&self,
+		_at: Height,
+		client_id: ClientId,
+	) -> Result<QueryClientStateResponse, Self::Error> {
+		let client_state = self
+			.state()
+			.clients
+			.get(&client_id)
+			.cloned()
+			.ok_or_else(|| self.not_found(format!("Client {client_id}")))?;
+		Ok(QueryClientStateResponse {
+			client_state: Some(client_state.into()),
+			..Default::default()
+		})
+	}
+
+	async fn query_connection_end(
+		&self,
+		_at: Height,
+		connection_id: ConnectionId,
+	) -> Result<QueryConnectionResponse, Self::Error> {
+		let connection = self
+			.state()
+			.connections
+			.get(&connection_id)
+			.cloned()
+			.ok_or_else(|| self.not_found(format!("Connection {connection_id}")))?;
+		Ok(QueryConnectionResponse { connection: Some(connection.into()), ..Default::default() })
+	}
+
+	async fn query_channel_end(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<QueryChannelResponse, Self::Error> {
+		let channel = self
+			.state()
+			.channels
+			.get(&(channel_id, port_id.clone()))
+			.cloned()
+			.ok_or_else(|| self.not_found(format!("Channel {channel_id}/{port_id}")))?;
+		Ok(QueryChannelResponse { channel: Some(channel.into()), ..Default::default() })
+	}
+
+	async fn query_proof(&self, _at: Height, keys: Vec<Vec<u8>>) -> Result<Vec<u8>, Self::Error> {
+		Ok(keys.concat())
+	}
+
+	async fn query_packet_commitment(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seq: u64,
+	) -> Result<QueryPacketCommitmentResponse, Self::Error> {
+		let commitment = self
+			.state()
+			.sent_packets
+			.iter()
+			.find(|packet| {
+				packet.sequence == seq &&
+					on_channel(&packet.source_port, &packet.source_channel, channel_id, port_id)
+			})
+			.map(|packet| packet.data.to_vec())
+			.unwrap_or_default();
+		Ok(QueryPacketCommitmentResponse { commitment, ..Default::default() })
+	}
+
+	async fn query_packet_acknowledgement(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seq: u64,
+	) -> Result<QueryPacketAcknowledgementResponse, Self::Error> {
+		let acknowledgement = self
+			.state()
+			.received_packets
+			.iter()
+			.find(|packet| {
+				packet.sequence == seq &&
+					on_channel(
+						&packet.destination_port,
+						&packet.destination_channel,
+						channel_id,
+						port_id,
+					)
+			})
+			.and_then(|packet| packet.ack.as_ref().map(|ack| ack.to_vec()))
+			.unwrap_or_default();
+		Ok(QueryPacketAcknowledgementResponse { acknowledgement, ..Default::default() })
+	}
+
+	async fn query_next_sequence_recv(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+	) -> Result<QueryNextSequenceReceiveResponse, Self::Error> {
+		let next_sequence_receive = self
+			.state()
+			.next_sequence_recv
+			.get(&(*channel_id, port_id.clone()))
+			.copied()
+			.unwrap_or(1);
+		Ok(QueryNextSequenceReceiveResponse { next_sequence_receive, ..Default::default() })
+	}
+
+	async fn query_packet_receipt(
+		&self,
+		_at: Height,
+		port_id: &PortId,
+		channel_id: &ChannelId,
+		seq: u64,
+	) -> Result<QueryPacketReceiptResponse, Self::Error> {
+		let received = self.state().received_packets.iter().any(|packet| {
+			packet.sequence == seq &&
+				on_channel(
+					&packet.destination_port,
+					&packet.destination_channel,
+					channel_id,
+					port_id,
+				)
+		});
+		Ok(QueryPacketReceiptResponse { received, ..Default::default() })
+	}
+
+	async fn latest_height_and_timestamp(&self) -> Result<(Height, Timestamp), Self::Error> {
+		let height = self.state().height;
+		Ok((self.height(height), timestamp_at(height)))
+	}
+
+	async fn query_packet_commitments(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Vec<u64>, Self::Error> {
+		Ok(self
+			.state()
+			.sent_packets
+			.iter()
+			.filter(|packet| {
+				on_channel(&packet.source_port, &packet.source_channel, &channel_id, &port_id)
+			})
+			.map(|packet| packet.sequence)
+			.collect())
+	}
+
+	async fn query_packet_acknowledgements(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+	) -> Result<Vec<u64>, Self::Error> {
+		Ok(self
+			.state()
+			.received_packets
+			.iter()
+			.filter(|packet| {
+				packet.ack.is_some() &&
+					on_channel(
+						&packet.destination_port,
+						&packet.destination_channel,
+						&channel_id,
+						&port_id,
+					)
+			})
+			.map(|packet| packet.sequence)
+			.collect())
+	}
+
+	async fn query_unreceived_packets(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<u64>, Self::Error> {
+		let state = self.state();
+		Ok(seqs
+			.into_iter()
+			.filter(|seq| {
+				!state.received_packets.iter().any(|packet| {
+					packet.sequence == *seq &&
+						on_channel(
+							&packet.destination_port,
+							&packet.destination_channel,
+							&channel_id,
+							&port_id,
+						)
+				})
+			})
+			.collect())
+	}
+
+	async fn query_unreceived_acknowledgements(
+		&self,
+		_at: Height,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<u64>, Self::Error> {
+		// the commitment of a packet is cleared once its acknowledgement is received
+		let state = self.state();
+		Ok(seqs
+			.into_iter()
+			.filter(|seq| {
+				state.sent_packets.iter().any(|packet| {
+					packet.sequence == *seq &&
+						on_channel(
+							&packet.source_port,
+							&packet.source_channel,
+							&channel_id,
+							&port_id,
+						)
+				})
+			})
+			.collect())
+	}
+
+	fn channel_whitelist(&self) -> BTreeSet<(ChannelId, PortId)> {
+		self.channel_whitelist.clone()
+	}
+
+	async fn query_connection_channels(
+		&self,
+		_at: Height,
+		connection_id: &ConnectionId,
+	) -> Result<QueryChannelsResponse, Self::Error> {
+		let channels = self
+			.state()
+			.channels
+			.iter()
+			.filter(|(_, channel)| channel.connection_hops.first() == Some(connection_id))
+			.map(|((channel_id, port_id), channel)| {
+				let channel = RawChannel::from(channel.clone());
+				IdentifiedChannel {
+					state: channel.state,
+					ordering: channel.ordering,
+					counterparty: channel.counterparty,
+					connection_hops: channel.connection_hops,
+					version: channel.version,
+					port_id: port_id.to_string(),
+					channel_id: channel_id.to_string(),
+				}
+			})
+			.collect();
+		Ok(QueryChannelsResponse { channels, ..Default::default() })
+	}
+
+	async fn query_send_packets(
+		&self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<PacketInfo>, Self::Error> {
+		Ok(self
+			.state()
+			.sent_packets
+			.iter()
+			.filter(|packet| {
+				seqs.contains(&packet.sequence) &&
+					on_channel(
+						&packet.source_port,
+						&packet.source_channel,
+						&channel_id,
+						&port_id,
+					)
+			})
+			.cloned()
+			.collect())
+	}
+
+	async fn query_received_packets(
+		&self,
+		channel_id: ChannelId,
+		port_id: PortId,
+		seqs: Vec<u64>,
+	) -> Result<Vec<PacketInfo>, Self::Error> {
+		Ok(self
+			.state()
+			.received_packets
+			.iter()
+			.filter(|packet| {
+				seqs.contains(&packet.sequence) &&
+					on_channel(
+						&packet.destination_port,
+						&packet.destination_channel,
+						&channel_id,
+						&port_id,
+					)
+			})
+			.cloned()
+			.collect())
+	}
+
+	fn expected_block_time(&self) -> Duration {
+		Duration::from_secs(6)
+	}
+
+	/// Every client update is reported as happened in the latest block.
+	async fn query_client_update_time_and_height(
+		&self,
+		client_id: ClientId,
+		client_height: Height,
+	) -> Result<(Height, Timestamp), Self::Error> {
+		let state = self.state();
+		if !state.consensus_states.contains_key(&(client_id.clone(), client_height)) {
+			return Err(self.not_found(format!("Update of {client_id} to {client_height}")))
+		}
+		Ok((self.height(state.height), timestamp_at(state.height)))
+	}
+
+	async fn query_host_consensus_state_proof(
+		&self,
+		_client_state: &AnyClientState,
+	) -> Result<Option<Vec<u8>>, Self::Error> {
+		Ok(None)
+	}
+
+	async fn query_ibc_balance(
+		&self,
+		_asset_id: Self::AssetId,
+	) -> Result<Vec<PrefixedCoin>, Self::Error> {
+		Ok(vec![])
+	}
+
+	fn connection_prefix(&self) -> CommitmentPrefix {
+		CommitmentPrefix::try_from(b"ibc".to_vec()).expect("prefix is not empty; qed")
+	}
+
+	fn client_id(&self) -> ClientId {
+		self.client_id.clone().expect("Client id should be set")
+	}
+
+	fn try_client_id(&self) -> Result<ClientId, Self::Error> {
+		self.client_id
+			.clone()
+			.ok_or_else(|| Error::Custom(format!("Client id of {} is not set", self.name)))
+	}
+
+	fn set_client_id(&mut self, client_id: ClientId) {
+		self.client_id = Some(client_id);
+	}
+
+	fn connection_id(&self) -> Option<ConnectionId> {
+		self.connection_id.clone()
+	}
+
+	fn set_channel_whitelist(&mut self, channel_whitelist: BTreeSet<(ChannelId, PortId)>) {
+		self.channel_whitelist = channel_whitelist;
+	}
+
+	fn add_channel_to_whitelist(&mut self, channel: (ChannelId, PortId)) {
+		self.channel_whitelist.insert(channel);
+	}
+
+	fn set_connection_id(&mut self, connection_id: ConnectionId) {
+		self.connection_id = Some(connection_id);
+	}
+
+	fn client_type(&self) -> ClientType {
+		GrandpaClientState::<HostFunctionsManager>::client_type()
+	}
+
+	async fn query_timestamp_at(&self, block_number: u64) -> Result<u64, Self::Error> {
+		Ok(timestamp_at(block_number).nanoseconds())
+	}
+
+	async fn query_clients(&self) -> Result<Vec<ClientId>, Self::Error> {
+		Ok(self.state().clients.keys().cloned().collect())
+	}
+
+	async fn query_channels(&self) -> Result<Vec<(ChannelId, PortId)>, Self::Error> {
+		Ok(self.state().channels.keys().cloned().collect())
+	}
+
+	async fn query_connection_using_client(
+		&self,
+		_height: u32,
+		client_id: String,
+	) -> Result<Vec<IdentifiedConnection>, Self::Error> {
+		Ok(self
+			.state()
+			.connections
+			.iter()
+			.filter(|(_, connection)| connection.client_id().as_str() == client_id)
+			.map(|(connection_id, connection)| {
+				let connection = RawConnectionEnd::from(connection.clone());
+				IdentifiedConnection {
+					id: connection_id.to_string(),
+					client_id: connection.client_id,
+					versions: connection.versions,
+					state: connection.state,
+					counterparty: connection.counterparty,
+					delay_period: connection.delay_period,
+				}
+			})
+			.collect())
+	}
+
+	async fn is_update_required(
+		&self,
+		_latest_height: u64,
+		_latest_client_height_on_counterparty: u64,
+	) -> Result<bool, Self::Error> {
+		Ok(false)
+	}
+
+	async fn initialize_client_state(
+		&self,
+	) -> Result<(AnyClientState, AnyConsensusState), Self::Error> {
+		let height = self.state().height;
+		Ok((self.host_client_state(height), self.host_consensus_state(height)))
+	}
+
+	async fn query_client_id_from_tx_hash(
+		&self,
+		tx_id: Self::TransactionId,
+	) -> Result<ClientId, Self::Error> {
+		self.state()
+			.created_clients
+			.get(&tx_id)
+			.cloned()
+			.ok_or_else(|| self.not_found(format!("Client created in transaction {tx_id}")))
+	}
+
+	async fn query_connection_id_from_tx_hash(
+		&self,
+		tx_id: Self::TransactionId,
+	) -> Result<ConnectionId, Self::Error> {
+		Err(self.not_found(format!("Connection created in transaction {tx_id}")))
+	}
+
+	async fn query_channel_id_from_tx_hash(
+		&self,
+		tx_id: Self::TransactionId,
+	) -> Result<(ChannelId, PortId), Self::Error> {
+		Err(self.not_found(format!("Channel created in transaction {tx_id}")))
+	}
+
+	async fn upload_wasm(&self, _wasm: Vec<u8>) -> Result<Vec<u8>, Self::Error> {
+		Err(Error::Custom(format!("{} doesn't support WASM clients", self.name)))
+	}
+}
+
+impl KeyProvider for MockChain {
+	fn account_id(&self) -> Signer {
+		Signer::from_str(&format!("relayer-{}", self.name)).expect("signer is not empty; qed")
+	}
+}
+
+#[async_trait::async_trait]
+impl MisbehaviourHandler for MockChain {
+	async fn check_for_misbehaviour<C: Chain>(
+		&self,
+		_counterparty: &C,
+		_client_message: AnyClientMessage,
+	) -> Result<(), anyhow::Error> {
+		Ok(())
+	}
+}
+
+#[async_trait::async_trait]
+impl LightClientSync for MockChain {
+	async fn is_synced<C: Chain>(&self, _counterparty: &C) -> Result<bool, anyhow::Error> {
+		Ok(true)
+	}
+
+	async fn fetch_mandatory_updates<C: Chain>(
+		&self,
+		_counterparty: &C,
+	) -> Result<(Vec<Any>, Vec<IbcEvent>), anyhow::Error> {
+		Ok((vec![], vec![]))
+	}
+}
+
+#[async_trait::async_trait]
+impl Chain for MockChain {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn block_max_weight(&self) -> u64 {
+		u64::MAX
+	}
+
+	async fn estimate_weight(&self, _msg: Vec<Any>) -> Result<u64, Self::Error> {
+		Ok(0)
+	}
+
+	async fn finality_notifications(
+		&self,
+	) -> Result<Pin<Box<dyn Stream<Item = Self::FinalityEvent> + Send + Sync>>, Self::Error> {
+		self.subscribed.notify_one();
+		Ok(Box::pin(futures::stream::pending()))
+	}
+
+	/// Includes the messages in a new block. Only create client messages are executed.
+	async fn submit(&self, messages: Vec<Any>) -> Result<Self::TransactionId, Self::Error> {
+		let mut state = self.state();
+		state.height += 1;
+		let tx_id = state.transactions.len();
+		for message in &messages {
+			if message.type_url != CREATE_CLIENT_TYPE_URL {
+				continue
+			}
+			let msg = MsgCreateAnyClient::<LocalClientTypes>::decode_vec(&message.value)
+				.map_err(|e| Error::Custom(format!("Invalid create client message: {e}")))?;
+			let client_id =
+				ClientId::new(&msg.client_state.client_type(), state.clients.len() as u64)
+					.map_err(|e| Error::Custom(e.to_string()))?;
+			let height = msg.client_state.latest_height();
+			state.consensus_states.insert((client_id.clone(), height), msg.consensus_state);
+			state.clients.insert(client_id.clone(), msg.client_state);
+			state.created_clients.insert(tx_id, client_id);
+		}
+		state.transactions.push(messages);
+		Ok(tx_id)
+	}
+
+	async fn query_client_message(
+		&self,
+		update: UpdateClient,
+	) -> Result<AnyClientMessage, Self::Error> {
+		Err(self.not_found(format!("Header of {}", update.client_id())))
+	}
+
+	async fn get_proof_height(&self, block_height: Height) -> Height {
+		block_height
+	}
+
+	async fn handle_error(&mut self, _error: &anyhow::Error) -> Result<(), anyhow::Error> {
+		Ok(())
+	}
+
+	fn common_state(&self) -> &CommonClientState {
+		&self.common_state
+	}
+
+	fn common_state_mut(&mut self) -> &mut CommonClientState {
+		&mut self.common_state
+	}
+
+	async fn reconnect(&mut self) -> anyhow::Result<()> {
+		Ok(())
+	}
+}
+
+#[async_trait::async_trait]
+impl TestProvider for MockChain {
+	async fn send_transfer(&self, _params: MsgTransfer<PrefixedCoin>) -> Result<(), Self::Error> {
+		Err(Error::Custom(format!("{} doesn't support transfers", self.name)))
+	}
+
+	async fn send_ordered_packet(
+		&self,
+		_channel_id: ChannelId,
+		_timeout: Timeout,
+	) -> Result<(), Self::Error> {
+		Err(Error::Custom(format!("{} doesn't support sending packets", self.name)))
+	}
+
+	async fn subscribe_blocks(&self) -> Pin<Box<dyn Stream<Item = u64> + Send + Sync>> {
+		Box::pin(futures::stream::pending())
+	}
+
+	async fn increase_counters(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
@@ -0,0 +1,153 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Size-capped descriptions of large IBC types for logs and error messages. Client messages and
+//! packets can be hundreds of kilobytes, so their `Debug` output isn't fit for logging.
+
+use ibc::core::{ics02_client::client_state::ClientState, ics04_channel::packet::Packet};
+use ibc_proto::google::protobuf::Any;
+use pallet_ibc::light_clients::AnyClientState;
+use std::{
+	collections::hash_map::DefaultHasher,
+	fmt,
+	hash::{Hash, Hasher},
+};
+
+/// Longest summary in bytes, the rest is cut off.
+pub const MAX_SUMMARY_LEN: usize = 256;
+
+/// Hash of `bytes` that correlates a summary with the full payload, e.g. in a trace log. It's
+/// stable for a build of the relayer, but not meant to be compared across builds.
+pub fn content_hash(bytes: &[u8]) -> String {
+	let mut hasher = DefaultHasher::new();
+	bytes.hash(&mut hasher);
+	format!("{:016x}", hasher.finish())
+}
+
+/// Writes `args` to `f`, cut off after [`MAX_SUMMARY_LEN`] bytes.
+fn write_capped(f: &mut fmt::Formatter<'_>, args: fmt::Arguments<'_>) -> fmt::Result {
+	let summary = args.to_string();
+	if summary.len() <= MAX_SUMMARY_LEN {
+		return f.write_str(&summary)
+	}
+	let mut end = MAX_SUMMARY_LEN;
+	while !summary.is_char_boundary(end) {
+		end -= 1;
+	}
+	write!(f, "{}...", &summary[..end])
+}
+
+/// Type url, size and content hash of an encoded message.
+pub struct AnySummary<'a>(pub &'a Any);
+
+impl fmt::Display for AnySummary<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write_capped(
+			f,
+			format_args!(
+				"{} ({} bytes, hash {})",
+				self.0.type_url,
+				self.0.value.len(),
+				content_hash(&self.0.value)
+			),
+		)
+	}
+}
+
+/// Identifiers and timeouts of a packet, with the size and content hash of its data.
+pub struct PacketSummary<'a>(pub &'a Packet);
+
+impl fmt::Display for PacketSummary<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let packet = self.0;
+		write_capped(
+			f,
+			format_args!(
+				"packet {} {}/{} -> {}/{} ({} bytes of data, hash {}, timeout height {}, timeout timestamp {})",
+				packet.sequence,
+				packet.source_port,
+				packet.source_channel,
+				packet.destination_port,
+				packet.destination_channel,
+				packet.data.len(),
+				content_hash(&packet.data),
+				packet.timeout_height,
+				packet.timeout_timestamp.nanoseconds()
+			),
+		)
+	}
+}
+
+/// Type and heights of a client state. Wasm client states are described by the state they wrap.
+pub struct ClientStateSummary<'a>(pub &'a AnyClientState);
+
+impl fmt::Display for ClientStateSummary<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let client_state = self.0;
+		let inner = client_state.unpack_recursive();
+		let client_type = if matches!(client_state, AnyClientState::Wasm(_)) {
+			format!("{} in {}", inner.client_type(), client_state.client_type())
+		} else {
+			client_state.client_type()
+		};
+		let frozen = match client_state.frozen_height() {
+			Some(height) => format!(", frozen at {height}"),
+			None => String::new(),
+		};
+		write_capped(
+			f,
+			format_args!("{client_type} client state at {}{frozen}", client_state.latest_height()),
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ibc::{
+		core::ics24_host::identifier::{ChannelId, PortId},
+		timestamp::Timestamp,
+		Height,
+	};
+
+	/// Summaries end up in logs, so they have to stay short no matter how large the payload is.
+	#[test]
+	fn summaries_of_large_payloads_are_short() {
+		let update = Any {
+			type_url: "/ibc.core.client.v1.MsgUpdateClient".to_string(),
+			value: vec![7; 512 * 1024],
+		};
+		let summary = AnySummary(&update).to_string();
+		assert!(summary.len() < 300, "{summary}");
+		assert!(summary.starts_with("/ibc.core.client.v1.MsgUpdateClient (524288 bytes, hash "));
+
+		let packet = Packet {
+			sequence: u64::MAX.into(),
+			source_port: PortId::transfer(),
+			source_channel: ChannelId::new(u64::MAX),
+			destination_port: PortId::transfer(),
+			destination_channel: ChannelId::new(u64::MAX),
+			data: vec![b'x'; 1024 * 1024],
+			timeout_height: Height::new(u64::MAX, u64::MAX),
+			timeout_timestamp: Timestamp::from_nanoseconds(u64::MAX).unwrap(),
+		};
+		let summary = PacketSummary(&packet).to_string();
+		assert!(summary.len() <= MAX_SUMMARY_LEN + 3, "{summary}");
+		assert!(summary.contains("1048576 bytes of data"));
+
+		// a malicious type url is cut off as well
+		let update = Any { type_url: "x".repeat(10_000), value: vec![] };
+		assert_eq!(AnySummary(&update).to_string().len(), MAX_SUMMARY_LEN + 3);
+	}
+}
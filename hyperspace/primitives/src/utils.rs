@@ -15,6 +15,7 @@
 #[cfg(any(test, feature = "testing"))]
 use crate::TestProvider;
 use crate::{mock::LocalClientTypes, Chain};
+use anyhow::anyhow;
 use futures::{future, StreamExt};
 use ibc::{
 	core::{
@@ -33,6 +34,7 @@ use ibc::{
 	tx_msg::Msg,
 };
 use ibc_proto::google::protobuf::Any;
+use pallet_ibc::light_clients::{AnyClientState, AnyConsensusState};
 use std::{future::Future, time::Duration};
 
 pub async fn timeout_future<T: Future>(future: T, secs: u64, reason: String) -> T::Output {
@@ -63,36 +65,88 @@ pub async fn timeout_after<C: TestProvider, T: Future + Send + 'static>(
 	}
 }
 
-pub async fn create_clients(
-	chain_a: &mut impl Chain,
-	chain_b: &mut impl Chain,
-) -> Result<(ClientId, ClientId), anyhow::Error> {
-	let (client_state_a, cs_state_a) = chain_a.initialize_client_state().await?;
-	let (client_state_b, cs_state_b) = chain_b.initialize_client_state().await?;
-
-	let msg = MsgCreateAnyClient::<LocalClientTypes> {
-		client_state: client_state_b,
-		consensus_state: cs_state_b,
-		signer: chain_a.account_id(),
-	};
+/// Error returned by [`create_clients`] when the first client was created but the creation of the
+/// second one failed. The first client is orphaned, nothing refers to it.
+#[derive(Debug)]
+pub struct PartialClientCreation {
+	/// Name of the chain the orphaned client lives on
+	pub chain: String,
+	/// Id of the orphaned client
+	pub client_id: ClientId,
+	/// Name of the chain the second client should have been created on
+	pub counterparty: String,
+	pub error: anyhow::Error,
+}
 
-	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec()? };
+impl std::fmt::Display for PartialClientCreation {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Client {} was created on {}, but creating the client on {} failed: {:?}. \
+			The config was left untouched, client {} is orphaned and can be ignored, \
+			or reused by setting it as `client_id` of {} once the client on {} exists",
+			self.client_id,
+			self.chain,
+			self.counterparty,
+			self.error,
+			self.client_id,
+			self.counterparty,
+			self.counterparty
+		)
+	}
+}
 
-	let tx_id = chain_a.submit(vec![msg]).await?;
-	let client_id_b_on_a = chain_a.query_client_id_from_tx_hash(tx_id).await?;
-	chain_a.set_client_id(client_id_b_on_a.clone());
+impl std::error::Error for PartialClientCreation {}
 
+/// Creates a client of the counterparty on `chain` and makes sure its state can be queried.
+async fn create_client(
+	chain: &impl Chain,
+	client_state: AnyClientState,
+	consensus_state: AnyConsensusState,
+) -> Result<ClientId, anyhow::Error> {
 	let msg = MsgCreateAnyClient::<LocalClientTypes> {
-		client_state: client_state_a,
-		consensus_state: cs_state_a,
-		signer: chain_b.account_id(),
+		client_state,
+		consensus_state,
+		signer: chain.account_id(),
 	};
 
 	let msg = Any { type_url: msg.type_url(), value: msg.encode_vec()? };
 
-	let tx_id = chain_b.submit(vec![msg]).await?;
-	let client_id_a_on_b = chain_b.query_client_id_from_tx_hash(tx_id).await?;
+	let tx_id = chain.submit(vec![msg]).await?;
+	let client_id = chain.query_client_id_from_tx_hash(tx_id).await?;
+	let (height, _) = chain.latest_height_and_timestamp().await?;
+	let response = chain.query_client_state(height, client_id.clone()).await?;
+	if response.client_state.is_none() {
+		return Err(anyhow!(
+			"Client {client_id} was created on {}, but its state can't be queried",
+			chain.name()
+		))
+	}
+	Ok(client_id)
+}
+
+/// Creates a client of `chain_b` on `chain_a` and then a client of `chain_a` on `chain_b`, returns
+/// their ids as `(client_id_a_on_b, client_id_b_on_a)`. If the second creation fails, the error is
+/// a [`PartialClientCreation`] naming the orphaned client.
+pub async fn create_clients(
+	chain_a: &mut impl Chain,
+	chain_b: &mut impl Chain,
+) -> Result<(ClientId, ClientId), anyhow::Error> {
+	let (client_state_a, cs_state_a) = chain_a.initialize_client_state().await?;
+	let (client_state_b, cs_state_b) = chain_b.initialize_client_state().await?;
+
+	let client_id_b_on_a = create_client(chain_a, client_state_b, cs_state_b).await?;
+	let client_id_a_on_b =
+		create_client(chain_b, client_state_a, cs_state_a).await.map_err(|error| {
+			PartialClientCreation {
+				chain: chain_a.name().to_string(),
+				client_id: client_id_b_on_a.clone(),
+				counterparty: chain_b.name().to_string(),
+				error,
+			}
+		})?;
 	chain_a.set_client_id(client_id_b_on_a.clone());
+	chain_b.set_client_id(client_id_a_on_b.clone());
 
 	Ok((client_id_a_on_b, client_id_b_on_a))
 }
@@ -197,5 +251,109 @@ pub async fn create_channel(
 		got => panic!("Last event should be OpenConfirmChannel: {got:?}"),
 	};
 
+	let channel_a = query_open_channel_end(chain_a, channel_id_a, port_id.clone()).await?;
+	let port_id_b = channel_a.counterparty().port_id().clone();
+	let channel_b = query_open_channel_end(chain_b, channel_id_b, port_id_b).await?;
+	check_channel_ends(order, &channel_a, &channel_b)?;
+
 	Ok((channel_id_a, channel_id_b))
 }
+
+async fn query_open_channel_end(
+	chain: &impl Chain,
+	channel_id: ChannelId,
+	port_id: PortId,
+) -> Result<ChannelEnd, anyhow::Error> {
+	let (height, ..) = chain.latest_height_and_timestamp().await?;
+	let channel = chain
+		.query_channel_end(height, channel_id, port_id.clone())
+		.await?
+		.channel
+		.ok_or_else(|| anyhow!("Channel {channel_id}/{port_id} not found on {}", chain.name()))?;
+	ChannelEnd::try_from(channel)
+		.map_err(|e| anyhow!("Invalid channel end {channel_id}/{port_id}: {e:?}"))
+}
+
+/// Checks that both ends of a new channel are open with the requested ordering and agree on the
+/// negotiated version.
+pub fn check_channel_ends(
+	order: Order,
+	channel_a: &ChannelEnd,
+	channel_b: &ChannelEnd,
+) -> Result<(), anyhow::Error> {
+	if channel_a.state != State::Open || channel_b.state != State::Open {
+		return Err(anyhow!(
+			"Channel ends are {} and {}, expected both to be open",
+			channel_a.state,
+			channel_b.state
+		))
+	}
+	if channel_a.ordering != order || channel_b.ordering != order {
+		return Err(anyhow!(
+			"Channel ends are {} and {}, but {order} was requested",
+			channel_a.ordering,
+			channel_b.ordering
+		))
+	}
+	if channel_a.version != channel_b.version {
+		return Err(anyhow!(
+			"Channel ends negotiated different versions: {} and {}",
+			channel_a.version,
+			channel_b.version
+		))
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	#[test]
+	fn channel_ends_must_agree_on_order_and_version() {
+		let end = |state, order, version: &str| {
+			ChannelEnd::new(
+				state,
+				order,
+				channel::Counterparty::new(PortId::transfer(), Some(ChannelId::new(0))),
+				vec![ConnectionId::new(0)],
+				ics04_channel::Version::new(version.to_string()),
+			)
+		};
+		let open = end(State::Open, Order::Ordered, "ping-1");
+		assert!(check_channel_ends(Order::Ordered, &open, &open).is_ok());
+
+		let unordered = end(State::Open, Order::Unordered, "ping-1");
+		let error = check_channel_ends(Order::Ordered, &open, &unordered).unwrap_err();
+		assert!(error.to_string().contains("but ORDER_ORDERED was requested"), "{error}");
+
+		let other_version = end(State::Open, Order::Ordered, "ping-2");
+		let error = check_channel_ends(Order::Ordered, &open, &other_version).unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"Channel ends negotiated different versions: ping-1 and ping-2"
+		);
+
+		let try_open = end(State::TryOpen, Order::Ordered, "ping-1");
+		assert!(check_channel_ends(Order::Ordered, &open, &try_open).is_err());
+	}
+
+	#[test]
+	fn partial_client_creation_names_orphaned_client() {
+		let error = anyhow::Error::from(PartialClientCreation {
+			chain: "parachain".to_string(),
+			client_id: ClientId::from_str("07-tendermint-3").unwrap(),
+			counterparty: "cosmos".to_string(),
+			error: anyhow!("transaction reverted"),
+		});
+		let message = error.to_string();
+		assert!(message.contains("Client 07-tendermint-3 was created on parachain"));
+		assert!(message.contains("transaction reverted"));
+		assert!(message.contains("config was left untouched"));
+		assert_eq!(
+			error.downcast_ref::<PartialClientCreation>().unwrap().client_id,
+			ClientId::from_str("07-tendermint-3").unwrap()
+		);
+	}
+}
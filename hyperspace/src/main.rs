@@ -14,7 +14,7 @@
 use anyhow::Result;
 use clap::Parser;
 use hyperspace_core::{
-	command::{Cli, Subcommand},
+	command::{print_config_schema, Cli, KeysSubcommand, Subcommand},
 	logging,
 };
 
@@ -24,23 +24,32 @@ async fn main() -> Result<()> {
 	let cli = Cli::parse();
 
 	match &cli.subcommand {
-		Subcommand::Relay(cmd) => cmd.run().await,
+		Subcommand::Relay(cmd) => cmd.run(cli.json).await,
 		Subcommand::UploadWasm(cmd) => {
-			let new_config = cmd.run().await?;
-			cmd.save_config(&new_config).await
+			let (new_config, output) = cmd.run().await?;
+			cmd.save_config(&new_config).await?;
+			output.print(cli.json)
 		},
 		Subcommand::CreateClients(cmd) => {
-			let new_config = cmd.create_clients().await?;
-			cmd.save_config(&new_config).await
+			let (new_config, output) = cmd.create_clients().await?;
+			cmd.save_config(&new_config).await?;
+			output.print(cli.json)
 		},
 		Subcommand::CreateConnection(cmd) => {
-			let new_config = cmd.create_connection().await?;
-			cmd.save_config(&new_config).await
+			let (new_config, output) = cmd.create_connection().await?;
+			cmd.save_config(&new_config).await?;
+			output.print(cli.json)
 		},
 		Subcommand::CreateChannel(cmd) => {
-			let new_config = cmd.create_channel().await?;
-			cmd.save_config(&new_config).await
+			let (new_config, output) = cmd.create_channel().await?;
+			cmd.save_config(&new_config).await?;
+			output.print(cli.json)
 		},
 		Subcommand::Fish(cmd) => cmd.fish().await,
+		Subcommand::Schema => print_config_schema(),
+		Subcommand::Doctor(cmd) => cmd.run(cli.json).await,
+		Subcommand::Keys(cmd) => match &cmd.subcommand {
+			KeysSubcommand::Encrypt(cmd) => cmd.run()?.print(cli.json),
+		},
 	}
 }
@@ -0,0 +1,70 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs the binary against mock chains, the way scripts use it.
+
+use serde_json::{json, Value};
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+fn write_mock_config(dir: &Path, name: &str, para_id: u32) -> PathBuf {
+	let path = dir.join(format!("{name}.toml"));
+	fs::write(&path, format!("type = \"mock\"\nname = \"{name}\"\npara_id = {para_id}\n")).unwrap();
+	path
+}
+
+#[test]
+fn create_clients_prints_client_ids_as_json() {
+	let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("create_clients");
+	fs::create_dir_all(&dir).unwrap();
+	let config_a = write_mock_config(&dir, "mock_a", 2000);
+	let config_b = write_mock_config(&dir, "mock_b", 2001);
+	let config_core = dir.join("core.toml");
+	fs::write(&config_core, "").unwrap();
+	let (out_config_a, out_config_b) = (dir.join("out_a.toml"), dir.join("out_b.toml"));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_hyperspace"))
+		.arg("create-clients")
+		.arg("--config-a")
+		.arg(&config_a)
+		.arg("--config-b")
+		.arg(&config_b)
+		.arg("--config-core")
+		.arg(&config_core)
+		.arg("--out-config-a")
+		.arg(&out_config_a)
+		.arg("--out-config-b")
+		.arg(&out_config_b)
+		.arg("--json")
+		.output()
+		.unwrap();
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+	// logs go to stderr, stdout only has the result
+	let stdout = String::from_utf8(output.stdout).unwrap();
+	let lines = stdout.lines().collect::<Vec<_>>();
+	assert_eq!(lines.len(), 1, "expected a single JSON object, got {stdout}");
+	let result: Value = serde_json::from_str(lines[0]).unwrap();
+	assert_eq!(result, json!({ "client_a": "10-grandpa-0", "client_b": "10-grandpa-0" }));
+
+	// the client ids are saved to the configs of the tracked chains
+	for out_config in [out_config_a, out_config_b] {
+		let config: toml::Value = toml::from_str(&fs::read_to_string(out_config).unwrap()).unwrap();
+		assert_eq!(config["type"].as_str(), Some("mock"));
+		assert_eq!(config["client_id"].as_str(), Some("10-grandpa-0"));
+	}
+}
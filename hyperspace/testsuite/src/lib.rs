@@ -15,8 +15,10 @@
 #![allow(clippy::all)]
 
 use crate::utils::assert_timeout_packet;
-use futures::{future, StreamExt};
-use hyperspace_core::send_packet_relay::set_relay_status;
+use futures::{future, FutureExt, StreamExt};
+use hyperspace_core::send_packet_relay::{
+	set_halted_chain, set_relay_status, set_timeout_relay_status,
+};
 use hyperspace_primitives::{
 	utils::{create_channel, create_connection, timeout_after, timeout_future},
 	TestProvider,
@@ -24,6 +26,7 @@ use hyperspace_primitives::{
 use ibc::{
 	applications::transfer::{msgs::transfer::MsgTransfer, Amount, PrefixedCoin, VERSION},
 	core::{
+		ics02_client::client_state::ClientState as _,
 		ics04_channel::{
 			channel::{ChannelEnd, Order, State},
 			msgs::chan_close_init::MsgChannelCloseInit,
@@ -32,15 +35,17 @@ use ibc::{
 	},
 	events::IbcEvent,
 	tx_msg::Msg,
+	Height,
 };
 use ibc_proto::google::protobuf::Any;
-use pallet_ibc::Timeout;
-use std::{str::FromStr, time::Duration};
+use pallet_ibc::{light_clients::AnyClientState, Timeout};
+use std::{num::NonZeroU64, str::FromStr, time::Duration};
 use tendermint_proto::Protobuf;
 use tokio::task::JoinHandle;
 
 pub mod misbehaviour;
 pub mod ordered_channels;
+pub mod soak;
 mod utils;
 
 /// This will set up a connection and ics20 channel in-between the two chains.
@@ -203,22 +208,36 @@ where
 	(amount, msg)
 }
 
+/// Waits for the acknowledgement of a transfer sent after `sent_after` and asserts that the
+/// signer's balance at the height of the acknowledgement dropped by at least `amount` compared to
+/// its balance at `sent_after`. Pinning both balances to heights keeps packets relayed in the
+/// meantime from skewing the comparison. The difference only exceeds `amount` by the fees if they
+/// are paid in the transferred asset.
+///
+/// On chains that can't query balances at past heights, `balance_before` (read right before the
+/// transfer was sent) and the latest balance are compared instead.
 async fn assert_send_transfer<A>(
 	chain: &A,
 	asset_id: A::AssetId,
-	previous_balance: u128,
+	sent_after: Height,
+	balance_before: u128,
+	amount: u128,
 	wait_blocks: u64,
 ) where
 	A: TestProvider,
 	A::FinalityEvent: Send + Sync,
 {
 	// wait for the acknowledgment
+	let (tx, rx) = tokio::sync::oneshot::channel();
 	let future = chain
 		.ibc_events()
 		.await
 		.skip_while(|ev| future::ready(!matches!(ev, IbcEvent::AcknowledgePacket(_))))
 		.take(1)
-		.collect::<Vec<_>>();
+		.collect::<Vec<_>>()
+		.map(move |events| {
+			let _ = tx.send(events);
+		});
 	timeout_after(
 		chain,
 		future,
@@ -226,16 +245,39 @@ async fn assert_send_transfer<A>(
 		format!("Didn't see AcknowledgePacket on {}", chain.name()),
 	)
 	.await;
-
-	let balance = chain
-		.query_ibc_balance(asset_id)
+	let acknowledged_at = rx
 		.await
-		.expect("Can't query ibc balance")
+		.expect("Acknowledgement wasn't observed")
 		.pop()
-		.expect("No Ibc balances");
+		.expect("AcknowledgePacket event")
+		.height();
 
-	let new_amount = balance.amount.as_u256().as_u128();
-	assert!(new_amount <= (previous_balance * 80) / 100);
+	assert_debited(chain, asset_id, sent_after, acknowledged_at, balance_before, amount).await;
+}
+
+/// Asserts that the signer's balance at `settled_at` dropped by at least `amount` compared to its
+/// balance at `sent_after`, falling back to `balance_before` and the latest balance on chains that
+/// can't query balances at past heights. See [`assert_send_transfer`].
+async fn assert_debited<A: TestProvider>(
+	chain: &A,
+	asset_id: A::AssetId,
+	sent_after: Height,
+	settled_at: Height,
+	balance_before: u128,
+	amount: u128,
+) {
+	let (previous_balance, new_balance) = match (
+		query_balance_at(chain, asset_id.clone(), sent_after).await,
+		query_balance_at(chain, asset_id.clone(), settled_at).await,
+	) {
+		(Some(previous_balance), Some(new_balance)) => (previous_balance, new_balance),
+		_ => (balance_before, query_balance(chain, asset_id).await),
+	};
+	assert!(
+		previous_balance.saturating_sub(new_balance) >= amount,
+		"Balance on {} went from {previous_balance} at {sent_after} to {new_balance} at {settled_at}, expected a transfer of {amount}",
+		chain.name()
+	);
 }
 
 /// Send a packet using a height timeout that has already passed
@@ -364,13 +406,17 @@ async fn send_packet_with_connection_delay<A, B>(
 	B::Error: From<A::Error>,
 {
 	log::info!(target: "hyperspace", "Sending transfer from {}", chain_a.name());
-	let (previous_balance, ..) =
+	let (sent_after, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+	let (balance_before, msg) =
 		send_transfer(chain_a, chain_b, asset_a.clone(), channel_id_a, None).await;
-	assert_send_transfer(chain_a, asset_a, previous_balance, 220).await;
+	let amount = msg.token.amount.as_u256().as_u128();
+	assert_send_transfer(chain_a, asset_a, sent_after, balance_before, amount, 220).await;
 	log::info!(target: "hyperspace", "Sending transfer from {}", chain_b.name());
-	let (previous_balance, ..) =
+	let (sent_after, ..) = chain_b.latest_height_and_timestamp().await.unwrap();
+	let (balance_before, msg) =
 		send_transfer(chain_b, chain_a, asset_b.clone(), channel_id_b, None).await;
-	assert_send_transfer(chain_b, asset_b, previous_balance, 220).await;
+	let amount = msg.token.amount.as_u256().as_u128();
+	assert_send_transfer(chain_b, asset_b, sent_after, balance_before, amount, 220).await;
 	// now send from chain b.
 	log::info!(target: "hyperspace", "🚀🚀 Token Transfer successful with connection delay");
 }
@@ -533,6 +579,142 @@ pub async fn ibc_messaging_packet_timestamp_timeout_with_connection_delay<A, B>(
 	handle.abort()
 }
 
+/// Send a packet to chain B, halt chain B after the packet timed out and assert chain A still sees
+/// the timeout packet. While chain B is halted, its finality events are ignored and every message
+/// to it is rejected.
+///
+/// Timeouts are paused until chain B's client on chain A has passed the timeout height, since the
+/// timeout can't be proven before that.
+pub async fn ibc_messaging_packet_timeout_with_halted_counterparty<A, B>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	asset_a: A::AssetId,
+	channel_a: ChannelId,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	chain_a.common_state_mut().timeout_sweep_interval = NonZeroU64::new(6);
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None, None)
+			.await
+			.unwrap()
+	});
+
+	log::info!(target: "hyperspace", "Suspending send packet and timeout relay");
+	set_relay_status(false);
+	set_timeout_relay_status(false);
+	let (.., msg) = send_transfer(
+		chain_a,
+		chain_b,
+		asset_a,
+		channel_a,
+		Some(Timeout::Offset { timestamp: Some(120 * 60), height: Some(20) }),
+	)
+	.await;
+
+	log::info!(target: "hyperspace", "Waiting for the client of {} to pass the timeout height", chain_b.name());
+	let client_updated = async {
+		loop {
+			let (height, _) = chain_a.latest_height_and_timestamp().await.unwrap();
+			let response = chain_a.query_client_state(height, chain_b.client_id()).await.unwrap();
+			let client_state =
+				AnyClientState::decode_recursive(response.client_state.unwrap(), |_| true).unwrap();
+			if client_state.latest_height() >= msg.timeout_height {
+				break
+			}
+			tokio::time::sleep(Duration::from_secs(6)).await;
+		}
+	};
+	timeout_future(
+		client_updated,
+		20 * 60,
+		format!("Client of {} didn't pass the timeout height", chain_b.name()),
+	)
+	.await;
+
+	log::info!(target: "hyperspace", "Halting {} and resuming timeout relay", chain_b.name());
+	set_halted_chain(Some(chain_b.name().to_string()));
+	set_timeout_relay_status(true);
+
+	assert_timeout_packet(chain_a, 75).await;
+	log::info!(target: "hyperspace", "🚀🚀 Timeout packet successfully processed while {} is halted", chain_b.name());
+
+	set_halted_chain(None);
+	set_relay_status(true);
+	chain_a.common_state_mut().timeout_sweep_interval = None;
+	handle.abort()
+}
+
+/// Send a packet on a channel whose outgoing direction is disabled on the sending chain and assert
+/// the packet isn't delivered, but its timeout is still relayed. A packet that was received on
+/// the counterparty can't be timed out, so the timeout also shows the packet stayed pending.
+pub async fn ibc_messaging_packet_in_disabled_direction_is_timed_out<A, B>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	asset_a: A::AssetId,
+	channel_a: ChannelId,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	chain_a
+		.common_state_mut()
+		.channel_directions
+		.insert((channel_a, PortId::transfer()), (false, true));
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None, None)
+			.await
+			.unwrap()
+	});
+
+	let (.., msg) = send_transfer(
+		chain_a,
+		chain_b,
+		asset_a,
+		channel_a,
+		Some(Timeout::Offset { timestamp: Some(120 * 60), height: Some(20) }),
+	)
+	.await;
+
+	let future = chain_b
+		.subscribe_blocks()
+		.await
+		.skip_while(|block_number| {
+			future::ready(*block_number < msg.timeout_height.revision_height)
+		})
+		.take(1)
+		.collect::<Vec<_>>();
+	log::info!(target: "hyperspace", "Waiting for packet timeout to elapse on counterparty");
+	timeout_future(
+		future,
+		20 * 60,
+		format!("Timeout height was not reached on {}", chain_b.name()),
+	)
+	.await;
+
+	assert_timeout_packet(chain_a, 75).await;
+	log::info!(target: "hyperspace", "🚀🚀 Packet in a disabled direction stayed pending and was timed out");
+
+	chain_a
+		.common_state_mut()
+		.channel_directions
+		.remove(&(channel_a, PortId::transfer()));
+	handle.abort()
+}
+
 /// Send a packet over a connection with a connection delay and assert the sending chain only sees
 /// the packet after the delay has elapsed.
 pub async fn ibc_messaging_with_connection_delay<A, B>(
@@ -618,6 +800,232 @@ pub async fn ibc_messaging_packet_timeout_on_channel_close<A, B>(
 	handle.abort()
 }
 
+async fn query_balance<A: TestProvider>(chain: &A, asset_id: A::AssetId) -> u128 {
+	chain
+		.query_ibc_balance(asset_id)
+		.await
+		.expect("Can't query ibc balance")
+		.pop()
+		.map(|balance| balance.amount.as_u256().as_u128())
+		.unwrap_or_default()
+}
+
+/// Balance of the signer at `at`, `None` on chains that can't query balances at past heights.
+async fn query_balance_at<A: TestProvider>(
+	chain: &A,
+	asset_id: A::AssetId,
+	at: Height,
+) -> Option<u128> {
+	match chain.query_ibc_balance_at_height(asset_id, at).await {
+		Ok(mut balances) => Some(
+			balances
+				.pop()
+				.map(|balance| balance.amount.as_u256().as_u128())
+				.unwrap_or_default(),
+		),
+		Err(e) => {
+			log::debug!(target: "hyperspace", "Can't query the balance on {} at {at}: {e:?}", chain.name());
+			None
+		},
+	}
+}
+
+/// Send a packet, stop the relayer right after the packet is received on chain B and start it
+/// again. Assert the acknowledgement is still relayed and the packet is delivered exactly once.
+pub async fn ibc_messaging_survives_relayer_restart<A, B>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	asset_a: A::AssetId,
+	asset_b: B::AssetId,
+	channel_a: ChannelId,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	let previous_balance_b = query_balance(chain_b, asset_b.clone()).await;
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None, None)
+			.await
+			.unwrap()
+	});
+
+	let receive_packet = chain_b
+		.ibc_events()
+		.await
+		.filter_map(|ev| {
+			future::ready(match ev {
+				IbcEvent::ReceivePacket(ev) if ev.packet.source_channel == channel_a =>
+					Some(ev.packet),
+				_ => None,
+			})
+		})
+		.take(1)
+		.collect::<Vec<_>>();
+	log::info!(target: "hyperspace", "Sending transfer from {}", chain_a.name());
+	let (sent_after, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+	let (previous_balance_a, msg) =
+		send_transfer(chain_a, chain_b, asset_a.clone(), channel_a, None).await;
+	let amount = msg.token.amount.as_u256().as_u128();
+	let packet = timeout_future(
+		receive_packet,
+		20 * 60,
+		format!("Didn't see ReceivePacket on {}", chain_b.name()),
+	)
+	.await
+	.pop()
+	.expect("Packet was received");
+
+	// simulate a crash before the acknowledgement is relayed back
+	handle.abort();
+	let _ = handle.await;
+	log::info!(target: "hyperspace", "Restarting the relayer after packet {} was received", packet.sequence);
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None, None)
+			.await
+			.unwrap()
+	});
+
+	// the packet commitment is removed on chain A once the acknowledgement is delivered
+	let sequence = u64::from(packet.sequence);
+	let cleared_at = timeout_future(
+		async {
+			loop {
+				let (height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+				let commitments = chain_a
+					.query_packet_commitments(height, channel_a, PortId::transfer())
+					.await
+					.unwrap();
+				if !commitments.contains(&sequence) {
+					break height
+				}
+				tokio::time::sleep(chain_a.expected_block_time()).await;
+			}
+		},
+		20 * 60,
+		format!("Packet commitment {sequence} wasn't cleared on {}", chain_a.name()),
+	)
+	.await;
+
+	assert_debited(chain_a, asset_a, sent_after, cleared_at, previous_balance_a, amount).await;
+	assert_eq!(query_balance(chain_b, asset_b).await, previous_balance_b + amount);
+	log::info!(target: "hyperspace", "🚀🚀 Packet was delivered exactly once across a relayer restart");
+	handle.abort()
+}
+
+/// With the relayer stopped, wait until chain A is `blocks_behind` blocks past its client on chain
+/// B and send `count` packets. Assert they're received once the relayer is started again, proven
+/// at a client update built at their height in the first cycle instead of the updates of the
+/// following cycles. Requires `max_proof_age` on chain A and a connection without delay.
+pub async fn ibc_messaging_packets_sent_while_stopped_relay_in_first_cycle<A, B>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	asset_a: A::AssetId,
+	channel_a: ChannelId,
+	blocks_behind: u64,
+	count: usize,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	let (height_b, ..) = chain_b.latest_height_and_timestamp().await.unwrap();
+	let response = chain_b.query_client_state(height_b, chain_a.client_id()).await.unwrap();
+	let client_height = AnyClientState::decode_recursive(response.client_state.unwrap(), |_| true)
+		.unwrap()
+		.latest_height();
+	let target_height = client_height.revision_height + blocks_behind;
+	log::info!(target: "hyperspace", "Waiting for {} to reach {target_height} with the relayer stopped", chain_a.name());
+	let future = chain_a
+		.subscribe_blocks()
+		.await
+		.skip_while(|block_number| future::ready(*block_number <= target_height))
+		.take(1)
+		.collect::<Vec<_>>();
+	timeout_future(future, 20 * 60, format!("{} didn't reach {target_height}", chain_a.name()))
+		.await;
+
+	let sent_packets = chain_a
+		.ibc_events()
+		.await
+		.filter_map(|ev| {
+			future::ready(match ev {
+				IbcEvent::SendPacket(ref send) if send.packet.source_channel == channel_a =>
+					Some(ev.height()),
+				_ => None,
+			})
+		})
+		.take(count)
+		.collect::<Vec<_>>();
+	let received_packets = chain_b
+		.ibc_events()
+		.await
+		.filter_map(|ev| {
+			future::ready(match ev {
+				IbcEvent::ReceivePacket(ev) if ev.packet.source_channel == channel_a =>
+					Some(ev.packet.sequence),
+				_ => None,
+			})
+		})
+		.take(count)
+		.collect::<Vec<_>>();
+	for _ in 0..count {
+		send_transfer(chain_a, chain_b, asset_a.clone(), channel_a, None).await;
+	}
+	let packet_height = timeout_future(
+		sent_packets,
+		5 * 60,
+		format!("Didn't see SendPacket on {}", chain_a.name()),
+	)
+	.await
+	.into_iter()
+	.max()
+	.expect("Packets were sent");
+
+	log::info!(target: "hyperspace", "Starting the relayer after {count} packets were sent up to {packet_height}");
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None, None)
+			.await
+			.unwrap()
+	});
+	let received = timeout_future(
+		received_packets,
+		5 * 60,
+		format!("Packets weren't received on {}", chain_b.name()),
+	)
+	.await;
+	assert_eq!(received.len(), count);
+
+	// the updates of a cycle only land on the packets' height by chance, the relayer builds an
+	// update there when they're out of the cycle's reach
+	let (height_b, ..) = chain_b.latest_height_and_timestamp().await.unwrap();
+	let consensus_state = chain_b
+		.query_client_consensus(height_b, chain_a.client_id(), packet_height)
+		.await
+		.ok()
+		.and_then(|response| response.consensus_state);
+	assert!(
+		consensus_state.is_some(),
+		"No consensus state of {} at {packet_height} on {}",
+		chain_a.name(),
+		chain_b.name()
+	);
+	log::info!(target: "hyperspace", "🚀🚀 Packets sent while the relayer was stopped were relayed in the first cycle");
+	handle.abort()
+}
+
 pub async fn client_synchronization_test<A, B>(chain_a: &mut A, chain_b: &mut B)
 where
 	A: TestProvider,
@@ -51,14 +51,14 @@ where
 	let client_id = chain_b.client_id();
 	let latest_height = chain_a.latest_height_and_timestamp().await.unwrap().0;
 	let response = chain_a.query_client_state(latest_height, client_id).await.unwrap();
-	let AnyClientState::Grandpa(client_state) =
-		AnyClientState::decode_recursive(response.client_state.unwrap(), |cs| {
+	let client_state =
+		match AnyClientState::try_decode_recursive(response.client_state.unwrap(), |cs| {
 			matches!(cs, AnyClientState::Grandpa(_))
-		})
-		.unwrap()
-	else {
-		unreachable!()
-	};
+		}) {
+			Ok(AnyClientState::Grandpa(client_state)) => client_state,
+			Ok(cs) => panic!("Expected a GRANDPA client state, found {cs:?}"),
+			Err(e) => panic!("Expected a GRANDPA client state: {e}"),
+		};
 
 	let finality_event =
 		chain_b.finality_notifications().await.unwrap().next().await.expect("no event");
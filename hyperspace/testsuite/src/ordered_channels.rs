@@ -294,3 +294,142 @@ pub async fn ibc_messaging_ordered_packet_timeout<A, B>(
 	send_ordered_packet_and_assert_timeout(chain_a, chain_b, channel_id).await;
 	handle.abort()
 }
+
+async fn query_next_sequence_recv<B: TestProvider>(
+	chain: &B,
+	channel_id: ChannelId,
+	port_id: &PortId,
+) -> u64 {
+	let (height, ..) = chain.latest_height_and_timestamp().await.unwrap();
+	chain
+		.query_next_sequence_recv(height, port_id, &channel_id)
+		.await
+		.unwrap()
+		.next_sequence_receive
+}
+
+/// Waits until the next receive sequence of the channel on `chain` reaches `expected`.
+async fn wait_for_next_sequence_recv<B: TestProvider>(
+	chain: &B,
+	channel_id: ChannelId,
+	port_id: &PortId,
+	expected: u64,
+) {
+	timeout_future(
+		async {
+			loop {
+				let next_sequence_recv = query_next_sequence_recv(chain, channel_id, port_id).await;
+				assert!(
+					next_sequence_recv <= expected,
+					"Next receive sequence {next_sequence_recv} on {} went past {expected}",
+					chain.name()
+				);
+				if next_sequence_recv == expected {
+					break
+				}
+				tokio::time::sleep(chain.expected_block_time()).await;
+			}
+		},
+		20 * 60,
+		format!("Next receive sequence on {} didn't reach {expected}", chain.name()),
+	)
+	.await;
+}
+
+/// Send three packets on an ordered channel, holding back the second and third packet until the
+/// relayer is resumed. Assert the held back packets are received in order and the next receive
+/// sequence on chain B advances one packet at a time.
+pub async fn ibc_messaging_ordered_packets_delivered_in_sequence<A, B>(
+	chain_a: &mut A,
+	chain_b: &mut B,
+	port_id: PortId,
+	version: String,
+) where
+	A: TestProvider,
+	A::FinalityEvent: Send + Sync,
+	A::Error: From<B::Error>,
+	B: TestProvider,
+	B::FinalityEvent: Send + Sync,
+	B::Error: From<A::Error>,
+{
+	let (handle, channel_id, channel_b, _connection_id) = setup_connection_and_channel(
+		chain_a,
+		chain_b,
+		Duration::from_secs(60 * 2),
+		port_id.clone(),
+		version,
+	)
+	.await;
+	// Set channel whitelist and restart relayer loop
+	handle.abort();
+	chain_a.set_channel_whitelist(vec![(channel_id, port_id.clone())].into_iter().collect());
+	chain_b.set_channel_whitelist(vec![(channel_b, port_id.clone())].into_iter().collect());
+	let client_a_clone = chain_a.clone();
+	let client_b_clone = chain_b.clone();
+	let handle = tokio::task::spawn(async move {
+		hyperspace_core::relay(client_a_clone, client_b_clone, None, None, None)
+			.await
+			.unwrap()
+	});
+	let timeout = || Timeout::Offset { height: Some(500), timestamp: Some(60 * 60) };
+	let first = query_next_sequence_recv(chain_b, channel_b, &port_id).await;
+
+	chain_a.send_ordered_packet(channel_id, timeout()).await.unwrap();
+	wait_for_next_sequence_recv(chain_b, channel_b, &port_id, first + 1).await;
+	log::info!(target: "hyperspace", "Packet {first} was received on {}", chain_b.name());
+
+	log::info!(target: "hyperspace", "Suspending send packet relay");
+	set_relay_status(false);
+	chain_a.send_ordered_packet(channel_id, timeout()).await.unwrap();
+	chain_a.send_ordered_packet(channel_id, timeout()).await.unwrap();
+
+	// nothing may be received while the second packet is held back
+	let blocks = chain_b.subscribe_blocks().await.take(10).collect::<Vec<_>>();
+	timeout_future(blocks, 10 * 60, format!("{} didn't produce blocks", chain_b.name())).await;
+	assert_eq!(query_next_sequence_recv(chain_b, channel_b, &port_id).await, first + 1);
+
+	let received = chain_b
+		.ibc_events()
+		.await
+		.filter_map(|ev| {
+			future::ready(match ev {
+				IbcEvent::ReceivePacket(ev) if ev.packet.destination_channel == channel_b =>
+					Some(u64::from(ev.packet.sequence)),
+				_ => None,
+			})
+		})
+		.take(2)
+		.collect::<Vec<_>>();
+	log::info!(target: "hyperspace", "Resuming send packet relay");
+	set_relay_status(true);
+	let received = timeout_future(
+		received,
+		20 * 60,
+		format!("Didn't see ReceivePacket events on {}", chain_b.name()),
+	)
+	.await;
+	assert_eq!(received, vec![first + 1, first + 2]);
+	wait_for_next_sequence_recv(chain_b, channel_b, &port_id, first + 3).await;
+
+	// the packet commitments are removed on chain A once the acknowledgements are delivered
+	timeout_future(
+		async {
+			loop {
+				let (height, ..) = chain_a.latest_height_and_timestamp().await.unwrap();
+				let commitments = chain_a
+					.query_packet_commitments(height, channel_id, port_id.clone())
+					.await
+					.unwrap();
+				if commitments.iter().all(|seq| *seq < first || *seq > first + 2) {
+					break
+				}
+				tokio::time::sleep(chain_a.expected_block_time()).await;
+			}
+		},
+		20 * 60,
+		format!("Packet commitments weren't cleared on {}", chain_a.name()),
+	)
+	.await;
+	log::info!(target: "hyperspace", "🚀🚀 Ordered packets were delivered in sequence");
+	handle.abort()
+}
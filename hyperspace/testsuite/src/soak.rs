@@ -0,0 +1,473 @@
+// Copyright 2022 ComposableFi
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Long running traffic between two chains that checks the relayer's invariants while it runs.
+
+use crate::query_balance;
+use futures::StreamExt;
+use hyperspace_core::{
+	chain::{AnyAssetId, AnyChain},
+	RelayerBuilder,
+};
+use hyperspace_primitives::{Chain, IbcProvider, KeyProvider, TestProvider};
+use ibc::{
+	applications::transfer::{msgs::transfer::MsgTransfer, Amount, PrefixedCoin},
+	core::{
+		ics02_client::client_state::ClientState as _,
+		ics24_host::identifier::{ChannelId, PortId},
+	},
+	Height,
+};
+use pallet_ibc::light_clients::AnyClientState;
+use prometheus::Registry;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+	collections::{BTreeMap, VecDeque},
+	str::FromStr,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+/// Number of IBC events kept for the diagnostic bundle.
+const RECENT_EVENTS: usize = 100;
+
+/// Settings of a soak run, read from `SOAK_*` environment variables by [`SoakConfig::from_env`].
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+	/// How long transfers are sent for.
+	pub duration: Duration,
+	/// Number of transfers sent per minute, in both directions together.
+	pub transfers_per_minute: u32,
+	/// Share of transfers, in percent, sent with a timeout that has passed by the time they
+	/// could be received.
+	pub timeout_percent: u8,
+	/// Maximum length of the random memo of a transfer.
+	pub max_memo_size: usize,
+	/// Age after which a packet commitment without receipt or timeout fails the run.
+	pub max_commitment_age: Duration,
+	/// Resident set size of the process running the relayer that fails the run.
+	pub max_rss_bytes: u64,
+	/// Number of messages the relayer may leave out of transactions because they failed the
+	/// simulation, e.g. packets that were relayed by someone else in the meantime.
+	pub allowed_failed_messages: u64,
+	/// Seed of the random traffic, logged so a failing run can be replayed.
+	pub seed: u64,
+	/// Directory the diagnostic bundle of a failed run is written to.
+	pub diagnostics_dir: String,
+}
+
+impl Default for SoakConfig {
+	fn default() -> Self {
+		Self {
+			duration: Duration::from_secs(10 * 60),
+			transfers_per_minute: 6,
+			timeout_percent: 10,
+			max_memo_size: 256,
+			max_commitment_age: Duration::from_secs(10 * 60),
+			max_rss_bytes: 2 * 1024 * 1024 * 1024,
+			allowed_failed_messages: 0,
+			seed: rand::random(),
+			diagnostics_dir: std::env::temp_dir().to_string_lossy().into_owned(),
+		}
+	}
+}
+
+impl SoakConfig {
+	/// The default config is a short smoke run, `SOAK_DURATION_SECS` and the other `SOAK_*`
+	/// variables turn it into a proper soak.
+	pub fn from_env() -> Self {
+		fn var<T: FromStr>(name: &str) -> Option<T> {
+			std::env::var(name).ok().and_then(|value| value.parse().ok())
+		}
+		let default = Self::default();
+		Self {
+			duration: var("SOAK_DURATION_SECS")
+				.map(Duration::from_secs)
+				.unwrap_or(default.duration),
+			transfers_per_minute: var("SOAK_TRANSFERS_PER_MINUTE")
+				.unwrap_or(default.transfers_per_minute),
+			timeout_percent: var("SOAK_TIMEOUT_PERCENT").unwrap_or(default.timeout_percent),
+			max_memo_size: var("SOAK_MAX_MEMO_SIZE").unwrap_or(default.max_memo_size),
+			max_commitment_age: var("SOAK_MAX_COMMITMENT_AGE_SECS")
+				.map(Duration::from_secs)
+				.unwrap_or(default.max_commitment_age),
+			max_rss_bytes: var("SOAK_MAX_RSS_BYTES").unwrap_or(default.max_rss_bytes),
+			allowed_failed_messages: var("SOAK_ALLOWED_FAILED_MESSAGES")
+				.unwrap_or(default.allowed_failed_messages),
+			seed: var("SOAK_SEED").unwrap_or(default.seed),
+			diagnostics_dir: std::env::var("SOAK_DIAGNOSTICS_DIR")
+				.unwrap_or(default.diagnostics_dir),
+		}
+	}
+}
+
+/// Remembers when a packet commitment was first seen, to find packets the relayer doesn't
+/// deliver.
+#[derive(Debug, Default)]
+pub struct CommitmentTracker {
+	first_seen: BTreeMap<(String, u64), Instant>,
+}
+
+impl CommitmentTracker {
+	/// Replaces the commitments of `chain` with `sequences` and returns those older than
+	/// `max_age` together with their age.
+	pub fn update(
+		&mut self,
+		chain: &str,
+		sequences: &[u64],
+		now: Instant,
+		max_age: Duration,
+	) -> Vec<(u64, Duration)> {
+		self.first_seen
+			.retain(|(name, sequence), _| name != chain || sequences.contains(sequence));
+		sequences
+			.iter()
+			.filter_map(|sequence| {
+				let first_seen =
+					*self.first_seen.entry((chain.to_string(), *sequence)).or_insert(now);
+				let age = now.saturating_duration_since(first_seen);
+				(age > max_age).then_some((*sequence, age))
+			})
+			.collect()
+	}
+
+	/// Sequences of the commitments of `chain` that are still pending.
+	pub fn pending(&self, chain: &str) -> Vec<u64> {
+		self.first_seen
+			.keys()
+			.filter(|(name, _)| name == chain)
+			.map(|(_, sequence)| *sequence)
+			.collect()
+	}
+}
+
+/// Parses the resident set size from the contents of `/proc/<pid>/status`.
+pub fn parse_vm_rss(status: &str) -> Option<u64> {
+	let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+	let kilobytes = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+	Some(kilobytes * 1024)
+}
+
+/// A transfer of the soak traffic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlannedTransfer {
+	a_to_b: bool,
+	/// Share of the sender's balance that is sent, in percent.
+	percent: u8,
+	memo_size: usize,
+	times_out: bool,
+}
+
+fn plan_transfer(rng: &mut StdRng, config: &SoakConfig) -> PlannedTransfer {
+	PlannedTransfer {
+		a_to_b: rng.gen_bool(0.5),
+		percent: rng.gen_range(1..=5),
+		memo_size: rng.gen_range(0..=config.max_memo_size),
+		times_out: rng.gen_range(0..100) < config.timeout_percent,
+	}
+}
+
+/// Sends the planned transfer from `source`. Returns `false` if the signer has nothing to send.
+async fn send_planned_transfer<A: TestProvider, B: TestProvider>(
+	source: &A,
+	sink: &B,
+	asset: A::AssetId,
+	channel_id: ChannelId,
+	transfer: &PlannedTransfer,
+	rng: &mut StdRng,
+) -> bool {
+	let Some(balance) =
+		source.query_ibc_balance(asset).await.expect("Can't query ibc balance").pop()
+	else {
+		return false
+	};
+	let amount = balance.amount.as_u256().as_u128() * transfer.percent as u128 / 100;
+	if amount == 0 {
+		return false
+	}
+
+	let (mut timeout_height, timestamp) =
+		sink.latest_height_and_timestamp().await.expect("Can't query latest height");
+	// a timeout one block ahead passes before the packet can be relayed
+	let (height_offset, time_offset) = if transfer.times_out { (1, 1) } else { (200, 60 * 60) };
+	timeout_height.revision_height += height_offset;
+	let timeout_timestamp =
+		(timestamp + Duration::from_secs(time_offset)).expect("Overflow evaluating timeout");
+	let memo = (0..transfer.memo_size)
+		.map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
+		.collect();
+
+	let msg = MsgTransfer {
+		source_port: PortId::transfer(),
+		source_channel: channel_id,
+		token: PrefixedCoin {
+			denom: balance.denom,
+			amount: Amount::from_str(&amount.to_string()).expect("Infallible"),
+		},
+		sender: source.account_id(),
+		receiver: sink.account_id(),
+		timeout_height,
+		timeout_timestamp,
+		memo,
+	};
+	source.send_transfer(msg).await.expect("Failed to send transfer");
+	true
+}
+
+/// Total number of messages the relayer left out of transactions because they failed the
+/// simulation.
+fn failed_messages(registry: &Registry) -> u64 {
+	registry
+		.gather()
+		.iter()
+		.filter(|family| family.get_name().ends_with("_number_of_excluded_messages"))
+		.flat_map(|family| {
+			family.get_metric().iter().map(|metric| metric.get_counter().get_value())
+		})
+		.sum::<f64>() as u64
+}
+
+/// Latest height of the client `chain` tracks its counterparty with.
+async fn client_height(chain: &AnyChain) -> Option<Height> {
+	let (height, ..) = chain.latest_height_and_timestamp().await.ok()?;
+	let client_id = chain.try_client_id().ok()?;
+	let response = chain.query_client_state(height, client_id).await.ok()?;
+	let client_state = AnyClientState::try_decode_recursive(response.client_state?, |_| true);
+	client_state.ok().map(|client_state| client_state.latest_height())
+}
+
+/// Sends random transfers between `chain_a` and `chain_b` over `channel_a` and `channel_b` for
+/// `config.duration` while a relayer runs, then waits for the pending packets to be delivered.
+///
+/// Every cycle checks that
+/// - the signers together never hold more of the transferred asset than at the start, which a
+///   packet relayed twice would cause, and hold exactly as much once all packets are delivered,
+/// - no packet commitment is older than `config.max_commitment_age`,
+/// - the resident set size of the process stays below `config.max_rss_bytes`,
+/// - the relayer didn't leave more than `config.allowed_failed_messages` messages out of its
+///   transactions.
+///
+/// A failed check writes a diagnostic bundle with the recent IBC events, the pending packets and
+/// the client heights to `config.diagnostics_dir` and panics.
+pub async fn ibc_messaging_soak(
+	chain_a: &AnyChain,
+	chain_b: &AnyChain,
+	asset_a: AnyAssetId,
+	asset_b: AnyAssetId,
+	channel_a: ChannelId,
+	channel_b: ChannelId,
+	config: SoakConfig,
+) {
+	log::info!(target: "hyperspace", "Starting soak run with seed {} for {:?}", config.seed, config.duration);
+	let mut rng = StdRng::seed_from_u64(config.seed);
+
+	let registry = Registry::new();
+	let relayer = RelayerBuilder::new()
+		.with_chains(chain_a.clone(), chain_b.clone())
+		.with_metrics(registry.clone())
+		.build()
+		.await
+		.expect("Failed to build relayer");
+	let relayer = Arc::new(relayer);
+	let handle = tokio::task::spawn({
+		let relayer = relayer.clone();
+		async move { relayer.run().await }
+	});
+
+	let recent_events = Arc::new(Mutex::new(VecDeque::new()));
+	for chain in [chain_a, chain_b] {
+		let name = chain.name().to_string();
+		let recent_events = recent_events.clone();
+		let mut events = chain.ibc_events().await;
+		tokio::task::spawn(async move {
+			while let Some(event) = events.next().await {
+				let mut recent_events = recent_events.lock().unwrap();
+				if recent_events.len() == RECENT_EVENTS {
+					recent_events.pop_front();
+				}
+				recent_events.push_back(format!("{name}: {event:?}"));
+			}
+		});
+	}
+
+	let supply = |chain_a: &AnyChain, chain_b: &AnyChain| {
+		let (asset_a, asset_b) = (asset_a.clone(), asset_b.clone());
+		let (chain_a, chain_b) = (chain_a.clone(), chain_b.clone());
+		async move { query_balance(&chain_a, asset_a).await + query_balance(&chain_b, asset_b).await }
+	};
+	let initial_supply = supply(chain_a, chain_b).await;
+	let mut tracker = CommitmentTracker::default();
+	let interval = Duration::from_secs(60) / config.transfers_per_minute.max(1);
+	let started = Instant::now();
+	let mut sent = 0u64;
+
+	loop {
+		let sending = started.elapsed() < config.duration;
+		if sending {
+			let transfer = plan_transfer(&mut rng, &config);
+			let sent_transfer = if transfer.a_to_b {
+				send_planned_transfer(
+					chain_a,
+					chain_b,
+					asset_a.clone(),
+					channel_a,
+					&transfer,
+					&mut rng,
+				)
+				.await
+			} else {
+				send_planned_transfer(
+					chain_b,
+					chain_a,
+					asset_b.clone(),
+					channel_b,
+					&transfer,
+					&mut rng,
+				)
+				.await
+			};
+			if sent_transfer {
+				sent += 1;
+				log::debug!(target: "hyperspace", "Soak transfer {sent}: {transfer:?}");
+			}
+		}
+		tokio::time::sleep(interval).await;
+
+		let now = Instant::now();
+		let mut failures = vec![];
+		let mut pending = 0;
+		for (chain, channel_id) in [(chain_a, channel_a), (chain_b, channel_b)] {
+			let (height, ..) = chain.latest_height_and_timestamp().await.unwrap();
+			let sequences = chain
+				.query_packet_commitments(height, channel_id, PortId::transfer())
+				.await
+				.unwrap();
+			pending += sequences.len();
+			for (sequence, age) in
+				tracker.update(chain.name(), &sequences, now, config.max_commitment_age)
+			{
+				failures.push(format!(
+					"packet {sequence} on {}/{channel_id} is pending for {age:?}",
+					chain.name()
+				));
+			}
+		}
+
+		let current_supply = supply(chain_a, chain_b).await;
+		if current_supply > initial_supply {
+			failures.push(format!(
+				"signers hold {current_supply}, more than the initial {initial_supply}"
+			));
+		}
+		if !sending && pending == 0 && current_supply != initial_supply {
+			failures.push(format!(
+				"signers hold {current_supply} after all packets were delivered, expected {initial_supply}"
+			));
+		}
+		let rss = std::fs::read_to_string("/proc/self/status")
+			.ok()
+			.and_then(|status| parse_vm_rss(&status));
+		if let Some(rss) = rss.filter(|rss| *rss > config.max_rss_bytes) {
+			failures.push(format!("resident set size is {rss} bytes"));
+		}
+		let failed = failed_messages(&registry);
+		if failed > config.allowed_failed_messages {
+			failures.push(format!("{failed} messages failed the simulation"));
+		}
+		if handle.is_finished() {
+			failures.push("the relayer stopped".to_string());
+		}
+
+		if !failures.is_empty() {
+			relayer.shutdown();
+			let path =
+				dump_diagnostics(chain_a, chain_b, &config, &failures, &tracker, &recent_events)
+					.await;
+			panic!(
+				"Soak run with seed {} failed, diagnostics in {path}: {failures:?}",
+				config.seed
+			);
+		}
+		if !sending && pending == 0 {
+			break
+		}
+	}
+
+	relayer.shutdown();
+	let _ = handle.await;
+	log::info!(target: "hyperspace", "🚀🚀 Soak run sent {sent} transfers without violating an invariant");
+}
+
+/// Writes the state of the run to a JSON file in `config.diagnostics_dir` and returns its path.
+async fn dump_diagnostics(
+	chain_a: &AnyChain,
+	chain_b: &AnyChain,
+	config: &SoakConfig,
+	failures: &[String],
+	tracker: &CommitmentTracker,
+	recent_events: &Mutex<VecDeque<String>>,
+) -> String {
+	let mut chains = vec![];
+	for chain in [chain_a, chain_b] {
+		let latest_height =
+			chain.latest_height_and_timestamp().await.ok().map(|(height, _)| height);
+		chains.push(json::json!({
+			"name": chain.name(),
+			"latest_height": latest_height.map(|height| height.to_string()),
+			"client_height": client_height(chain).await.map(|height| height.to_string()),
+			"pending_packets": tracker.pending(chain.name()),
+		}));
+	}
+	let bundle = json::json!({
+		"seed": config.seed,
+		"failures": failures,
+		"chains": chains,
+		"recent_events": recent_events.lock().unwrap().iter().collect::<Vec<_>>(),
+	});
+	let path = format!("{}/hyperspace-soak-{}.json", config.diagnostics_dir, config.seed);
+	if let Err(e) = std::fs::write(&path, json::to_string_pretty(&bundle).unwrap()) {
+		log::error!(target: "hyperspace", "Failed to write soak diagnostics to {path}: {e}");
+	}
+	path
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn commitments_older_than_max_age_are_reported() {
+		let mut tracker = CommitmentTracker::default();
+		let start = Instant::now();
+		let max_age = Duration::from_secs(60);
+		assert!(tracker.update("a", &[1, 2], start, max_age).is_empty());
+		assert!(tracker.update("b", &[1], start, max_age).is_empty());
+
+		// packet 1 on `a` was delivered, packet 2 is still pending
+		let later = start + Duration::from_secs(61);
+		assert_eq!(
+			tracker.update("a", &[2, 3], later, max_age),
+			vec![(2, Duration::from_secs(61))]
+		);
+		assert_eq!(tracker.pending("a"), vec![2, 3]);
+		assert_eq!(tracker.pending("b"), vec![1]);
+	}
+
+	#[test]
+	fn rss_is_parsed_from_proc_status() {
+		let status = "Name:\thyperspace\nVmPeak:\t  204800 kB\nVmRSS:\t   10240 kB\nThreads:\t8\n";
+		assert_eq!(parse_vm_rss(status), Some(10240 * 1024));
+		assert_eq!(parse_vm_rss("Name:\thyperspace\n"), None);
+	}
+}
@@ -19,18 +19,28 @@ use hyperspace_core::{
 	logging,
 	substrate::DefaultConfig,
 };
-use hyperspace_cosmos::client::{CosmosClient, CosmosClientConfig};
+use hyperspace_cosmos::{
+	client::{CosmosClient, CosmosClientConfig},
+	provider::NUMBER_OF_BLOCKS_TO_PROCESS_PER_ITER,
+};
 use hyperspace_parachain::{finality_protocol::FinalityProtocol, ParachainClientConfig};
-use hyperspace_primitives::{utils::create_clients, CommonClientConfig, IbcProvider};
+use hyperspace_primitives::{utils::create_clients, Chain, CommonClientConfig, IbcProvider};
 use hyperspace_testsuite::{
 	ibc_channel_close, ibc_messaging_packet_height_timeout_with_connection_delay,
+	ibc_messaging_packet_in_disabled_direction_is_timed_out,
 	ibc_messaging_packet_timeout_on_channel_close,
+	ibc_messaging_packet_timeout_with_halted_counterparty,
 	ibc_messaging_packet_timestamp_timeout_with_connection_delay,
-	ibc_messaging_with_connection_delay, misbehaviour::ibc_messaging_submit_misbehaviour,
+	ibc_messaging_packets_sent_while_stopped_relay_in_first_cycle,
+	ibc_messaging_survives_relayer_restart, ibc_messaging_with_connection_delay,
+	misbehaviour::ibc_messaging_submit_misbehaviour,
+	ordered_channels::ibc_messaging_ordered_packets_delivered_in_sequence,
 	setup_connection_and_channel,
+	soak::{ibc_messaging_soak, SoakConfig},
 };
 use ibc::core::ics24_host::identifier::PortId;
 use sp_core::hashing::sha2_256;
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct Args {
@@ -84,9 +94,19 @@ async fn setup_clients() -> (AnyChain, AnyChain) {
 		ss58_version: 42,
 		channel_whitelist: vec![],
 		finality_protocol: FinalityProtocol::Grandpa,
-		private_key: "//Alice".to_string(),
+		private_key: "//Alice".to_string().into(),
 		key_type: "sr25519".to_string(),
 		wasm_code_id: None,
+		common: CommonClientConfig {
+			skip_optional_client_updates: true,
+			max_packets_to_process: 100,
+			channel_weights: vec![],
+			channel_directions: vec![],
+			max_proof_age: None,
+			circuit_breaker: Default::default(),
+			max_concurrent_proof_queries: 16,
+			timeout_sweep_interval: None,
+		},
 	};
 
 	let mut config_b = CosmosClientConfig {
@@ -103,14 +123,25 @@ async fn setup_clients() -> (AnyChain, AnyChain) {
 		gas_limit: (i64::MAX - 1) as u64,
 		store_prefix: args.connection_prefix_b,
 		max_tx_size: 200000,
-		mnemonic:
+		mnemonic: Some(
 			"oxygen fall sure lava energy veteran enroll frown question detail include maximum"
-				.to_string(),
+				.to_string()
+				.into(),
+		),
+		mnemonic_env: None,
+		key_file: None,
+		key_file_passphrase_env: None,
 		wasm_code_id: None,
 		channel_whitelist: vec![],
 		common: CommonClientConfig {
 			skip_optional_client_updates: true,
 			max_packets_to_process: 200,
+			channel_weights: vec![],
+			channel_directions: vec![],
+			max_proof_age: None,
+			circuit_breaker: Default::default(),
+			max_concurrent_proof_queries: 16,
+			timeout_sweep_interval: None,
 		},
 		skip_tokens_list: None,
 	};
@@ -203,6 +234,16 @@ async fn parachain_to_cosmos_ibc_messaging_full_integration_test() {
 	)
 	.await;
 
+	// relayer crash recovery
+	ibc_messaging_survives_relayer_restart(
+		&mut chain_a,
+		&mut chain_b,
+		asset_id_a.clone(),
+		asset_id_b.clone(),
+		channel_a,
+	)
+	.await;
+
 	// timeouts + connection delay
 	ibc_messaging_packet_height_timeout_with_connection_delay(
 		&mut chain_a,
@@ -220,6 +261,20 @@ async fn parachain_to_cosmos_ibc_messaging_full_integration_test() {
 		channel_b,
 	)
 	.await;
+	ibc_messaging_packet_timeout_with_halted_counterparty(
+		&mut chain_a,
+		&mut chain_b,
+		asset_id_a.clone(),
+		channel_a,
+	)
+	.await;
+	ibc_messaging_packet_in_disabled_direction_is_timed_out(
+		&mut chain_a,
+		&mut chain_b,
+		asset_id_a.clone(),
+		channel_a,
+	)
+	.await;
 
 	// channel closing semantics
 	ibc_messaging_packet_timeout_on_channel_close(
@@ -270,6 +325,16 @@ async fn cosmos_to_parachain_ibc_messaging_full_integration_test() {
 	)
 	.await;
 
+	// relayer crash recovery
+	ibc_messaging_survives_relayer_restart(
+		&mut chain_a,
+		&mut chain_b,
+		asset_id_a.clone(),
+		asset_id_b.clone(),
+		channel_a,
+	)
+	.await;
+
 	// timeouts + connection delay
 	ibc_messaging_packet_height_timeout_with_connection_delay(
 		&mut chain_a,
@@ -295,3 +360,88 @@ async fn cosmos_to_parachain_ibc_messaging_full_integration_test() {
 
 	ibc_messaging_submit_misbehaviour(&mut chain_a, &mut chain_b).await;
 }
+
+/// Packets sent while the relayer is stopped and the client on the parachain is more than a
+/// cycle behind are relayed as soon as it starts again.
+#[tokio::test]
+#[ignore]
+async fn cosmos_to_parachain_packets_sent_while_stopped_test() {
+	logging::setup_logging();
+
+	let (chain_a, chain_b) = setup_clients().await;
+	let (mut chain_b, mut chain_a) = (chain_a, chain_b);
+	// proofs can't be submitted in the cycle that updates the client if there's a delay
+	let (handle, channel_a, channel_b, connection_id_a, connection_id_b) =
+		setup_connection_and_channel(&mut chain_a, &mut chain_b, Duration::ZERO).await;
+	handle.abort();
+
+	chain_a.set_connection_id(connection_id_a);
+	chain_b.set_connection_id(connection_id_b);
+	chain_a.set_channel_whitelist(vec![(channel_a, PortId::transfer())].into_iter().collect());
+	chain_b.set_channel_whitelist(vec![(channel_b, PortId::transfer())].into_iter().collect());
+	chain_a.common_state_mut().max_proof_age = Some(100);
+
+	ibc_messaging_packets_sent_while_stopped_relay_in_first_cycle(
+		&mut chain_a,
+		&mut chain_b,
+		AnyAssetId::Cosmos("stake".to_string()),
+		channel_a,
+		NUMBER_OF_BLOCKS_TO_PROCESS_PER_ITER + 10,
+		3,
+	)
+	.await;
+}
+
+/// Sends random transfers in both directions for `SOAK_DURATION_SECS` (10 minutes by default)
+/// while checking the relayer's invariants, see [`SoakConfig::from_env`] for the other settings.
+///
+/// `cargo test -p hyperspace-testsuite --test parachain_cosmos soak -- --ignored`
+#[tokio::test]
+#[ignore]
+async fn parachain_cosmos_soak_test() {
+	logging::setup_logging();
+
+	let asset_id_a = AnyAssetId::Parachain(1);
+	let asset_id_b = AnyAssetId::Cosmos(
+		"ibc/47B97D8FF01DA03FCB2F4B1FFEC931645F254E21EF465FA95CBA6888CB964DC4".to_string(),
+	);
+	let (mut chain_a, mut chain_b) = setup_clients().await;
+	let (handle, channel_a, channel_b, connection_id_a, connection_id_b) =
+		setup_connection_and_channel(&mut chain_a, &mut chain_b, Duration::from_secs(60 * 2)).await;
+	handle.abort();
+
+	chain_a.set_connection_id(connection_id_a);
+	chain_b.set_connection_id(connection_id_b);
+	chain_a.set_channel_whitelist(vec![(channel_a, PortId::transfer())].into_iter().collect());
+	chain_b.set_channel_whitelist(vec![(channel_b, PortId::transfer())].into_iter().collect());
+
+	ibc_messaging_soak(
+		&chain_a,
+		&chain_b,
+		asset_id_a,
+		asset_id_b,
+		channel_a,
+		channel_b,
+		SoakConfig::from_env(),
+	)
+	.await;
+}
+
+/// Requires a module bound to `ORDERED_PORT_ID` (`ping` by default) on the cosmos chain that
+/// accepts ordered channels with version `ORDERED_CHANNEL_VERSION` (`ping-1` by default).
+#[tokio::test]
+#[ignore]
+async fn parachain_to_cosmos_ordered_channel_test() {
+	logging::setup_logging();
+
+	let port_id = std::env::var("ORDERED_PORT_ID").unwrap_or_else(|_| "ping".to_string());
+	let version = std::env::var("ORDERED_CHANNEL_VERSION").unwrap_or_else(|_| "ping-1".to_string());
+	let (mut chain_a, mut chain_b) = setup_clients().await;
+	ibc_messaging_ordered_packets_delivered_in_sequence(
+		&mut chain_a,
+		&mut chain_b,
+		PortId::from_str(&port_id).unwrap(),
+		version,
+	)
+	.await;
+}
@@ -17,7 +17,7 @@ use hyperspace_core::{logging, substrate::DefaultConfig};
 use hyperspace_parachain::{
 	finality_protocol::FinalityProtocol, ParachainClient, ParachainClientConfig,
 };
-use hyperspace_primitives::{utils::create_clients, IbcProvider, TestProvider};
+use hyperspace_primitives::{utils::create_clients, CommonClientConfig, IbcProvider, TestProvider};
 use hyperspace_testsuite::{
 	client_synchronization_test, ibc_channel_close,
 	ibc_messaging_packet_height_timeout_with_connection_delay,
@@ -71,9 +71,19 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		ss58_version: 42,
 		channel_whitelist: vec![],
 		finality_protocol: FinalityProtocol::Grandpa,
-		private_key: "//Alice".to_string(),
+		private_key: "//Alice".to_string().into(),
 		key_type: "sr25519".to_string(),
 		wasm_code_id: None,
+		common: CommonClientConfig {
+			skip_optional_client_updates: true,
+			max_packets_to_process: 100,
+			channel_weights: vec![],
+			channel_directions: vec![],
+			max_proof_age: None,
+			circuit_breaker: Default::default(),
+			max_concurrent_proof_queries: 16,
+			timeout_sweep_interval: None,
+		},
 	};
 	let config_b = ParachainClientConfig {
 		name: "9188".to_string(),
@@ -83,12 +93,22 @@ async fn setup_clients() -> (ParachainClient<DefaultConfig>, ParachainClient<Def
 		client_id: None,
 		connection_id: None,
 		commitment_prefix: args.connection_prefix_b.as_bytes().to_vec().into(),
-		private_key: "//Alice".to_string(),
+		private_key: "//Alice".to_string().into(),
 		ss58_version: 42,
 		channel_whitelist: vec![],
 		finality_protocol: FinalityProtocol::Grandpa,
 		key_type: "sr25519".to_string(),
 		wasm_code_id: None,
+		common: CommonClientConfig {
+			skip_optional_client_updates: true,
+			max_packets_to_process: 100,
+			channel_weights: vec![],
+			channel_directions: vec![],
+			max_proof_age: None,
+			circuit_breaker: Default::default(),
+			max_concurrent_proof_queries: 16,
+			timeout_sweep_interval: None,
+		},
 	};
 
 	let mut chain_a = ParachainClient::<DefaultConfig>::new(config_a).await.unwrap();
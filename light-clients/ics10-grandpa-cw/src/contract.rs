@@ -272,24 +272,23 @@ fn process_message(
 			let substitute_client_state = ctx
 				.client_state_prefixed(SUBSTITUTE_PREFIX)
 				.map_err(|e| ContractError::Grandpa(e.to_string()))?;
+			check_substitute(&old_client_state, &substitute_client_state)?;
 
-			// Check that the substitute client state is valid:
-			// all fields should be the same as in the old state, except for the `relay_chain`,
-			// `para_id`, `latest_para_height`, `latest_relay_height`, `latest_relay_hash`,
-			// `frozen_height`, `current_authorities`, `current_set_id`
+			// Copy the state of the substitute over the old one. `check_substitute` already made
+			// sure that both clients track the same chain, so `relay_chain` and `para_id` are kept,
+			// and only the `latest_para_height`, `latest_relay_height`, `latest_relay_hash`,
+			// `frozen_height`, `current_authorities` and `current_set_id` are taken over
 			let ClientState {
-				relay_chain,
+				relay_chain: _,
 				latest_relay_height,
 				latest_relay_hash,
 				frozen_height,
 				latest_para_height,
-				para_id,
+				para_id: _,
 				current_set_id,
 				current_authorities,
 				_phantom,
 			} = substitute_client_state.clone();
-			old_client_state.relay_chain = relay_chain;
-			old_client_state.para_id = para_id;
 			old_client_state.latest_para_height = latest_para_height;
 			old_client_state.latest_relay_height = latest_relay_height;
 			old_client_state.latest_relay_hash = latest_relay_hash;
@@ -406,6 +405,38 @@ pub extern "C" fn ext_hashing_blake2_256_version_1(data: i64) -> i32 {
 	out_ptr as i32
 }
 
+/// Checks that the `substitute` client may replace the `subject` client: the substitute must not
+/// be frozen, must be ahead of the subject, and must track the same chain.
+///
+/// The trusting period of a client depends on its relay chain only, which is part of the chain id,
+/// so a substitute with the same chain id always has the same trusting period as the subject and
+/// the trusting period needs no check of its own.
+fn check_substitute<H>(
+	subject: &ClientState<H>,
+	substitute: &ClientState<H>,
+) -> Result<(), ContractError> {
+	if substitute.chain_id() != subject.chain_id() {
+		return Err(ContractError::Grandpa(format!(
+			"substitute client chain id {} doesn't match subject client chain id {}",
+			substitute.chain_id(),
+			subject.chain_id()
+		)))
+	}
+	if let Some(frozen_height) = substitute.frozen_height {
+		return Err(ContractError::Grandpa(format!(
+			"substitute client is frozen at height {frozen_height}"
+		)))
+	}
+	if substitute.latest_height() <= subject.latest_height() {
+		return Err(ContractError::Grandpa(format!(
+			"substitute client latest height {} must be greater than subject client latest height {}",
+			substitute.latest_height(),
+			subject.latest_height()
+		)))
+	}
+	Ok(())
+}
+
 pub fn twox_64_into(data: &[u8], dest: &mut [u8; 8]) {
 	let r0 = twox_hash::XxHash::with_seed(0).chain_update(data).finish();
 	LittleEndian::write_u64(&mut dest[0..8], r0);
@@ -439,3 +470,63 @@ pub extern "C" fn ext_hashing_twox_64_version_1(data: i64) -> i32 {
 	let out_ptr = Box::leak(hash).as_ptr();
 	out_ptr as i32
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use light_client_common::RelayChain;
+
+	fn client_state(
+		relay_chain: RelayChain,
+		para_id: u32,
+		latest_para_height: u32,
+	) -> ClientState<HostFunctions> {
+		ClientState { relay_chain, para_id, latest_para_height, ..Default::default() }
+	}
+
+	fn error(result: Result<(), ContractError>) -> String {
+		result.unwrap_err().to_string()
+	}
+
+	#[test]
+	fn substitute_replacing_subject_is_accepted() {
+		let subject = client_state(RelayChain::Polkadot, 2000, 10);
+		let substitute = client_state(RelayChain::Polkadot, 2000, 20);
+		assert!(check_substitute(&subject, &substitute).is_ok());
+	}
+
+	#[test]
+	fn frozen_substitute_is_rejected() {
+		let subject = client_state(RelayChain::Polkadot, 2000, 10);
+		let substitute = ClientState {
+			frozen_height: Some(Height::new(2000, 15)),
+			..client_state(RelayChain::Polkadot, 2000, 20)
+		};
+		assert!(error(check_substitute(&subject, &substitute)).contains("frozen"));
+	}
+
+	#[test]
+	fn substitute_behind_subject_is_rejected() {
+		let subject = client_state(RelayChain::Polkadot, 2000, 10);
+		for height in [5, 10] {
+			let substitute = client_state(RelayChain::Polkadot, 2000, height);
+			assert!(error(check_substitute(&subject, &substitute)).contains("latest height"));
+		}
+	}
+
+	#[test]
+	fn substitute_of_another_relay_chain_is_rejected() {
+		// a relay chain with a shorter trusting period is a different chain
+		let subject = client_state(RelayChain::Polkadot, 2000, 10);
+		let substitute = client_state(RelayChain::Kusama, 2000, 20);
+		assert!(substitute.relay_chain.trusting_period() < subject.relay_chain.trusting_period());
+		assert!(error(check_substitute(&subject, &substitute)).contains("chain id"));
+	}
+
+	#[test]
+	fn substitute_of_another_chain_is_rejected() {
+		let subject = client_state(RelayChain::Polkadot, 2000, 10);
+		let substitute = client_state(RelayChain::Polkadot, 2001, 20);
+		assert!(error(check_substitute(&subject, &substitute)).contains("chain id"));
+	}
+}
@@ -51,10 +51,22 @@ use ibc::{
 		ics26_routing::context::ReaderContext,
 	},
 	prelude::*,
+	timestamp::Timestamp,
 	Height,
 };
 use light_client_common::{verify_delay_passed, verify_membership, verify_non_membership};
 
+/// Returns true if no more than the trusting period of the relay chain has passed between the
+/// timestamp of `cs` and `now`.
+pub fn is_within_trust_period<H>(
+	cs: &ConsensusState,
+	client_state: &ClientState<H>,
+	now: Timestamp,
+) -> bool {
+	let elapsed = now.duration_since(&cs.timestamp()).unwrap_or_default();
+	!client_state.expired(elapsed)
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct BeefyClient<T>(PhantomData<T>);
 
@@ -68,13 +80,26 @@ where
 
 	fn verify_client_message<Ctx: ReaderContext>(
 		&self,
-		_ctx: &Ctx,
-		_client_id: ClientId,
+		ctx: &Ctx,
+		client_id: ClientId,
 		client_state: Self::ClientState,
 		message: Self::ClientMessage,
 	) -> Result<(), Ics02Error> {
 		match message {
 			ClientMessage::Header(header) => {
+				// The client can't be updated once its latest consensus state is past the
+				// trusting period
+				if let Some(cs) =
+					ctx.maybe_consensus_state(&client_id, client_state.latest_height())?
+				{
+					let cs: ConsensusState = cs.downcast().ok_or_else(|| {
+						Ics02Error::client_args_type_mismatch(client_state.client_type().to_owned())
+					})?;
+					if !is_within_trust_period(&cs, &client_state, ctx.host_timestamp()) {
+						return Err(Error::HeaderExpired { at: cs.timestamp() }.into())
+					}
+				}
+
 				let light_client_state = LightClientState {
 					latest_beefy_height: client_state.latest_beefy_height,
 					mmr_root_hash: client_state.mmr_root_hash,
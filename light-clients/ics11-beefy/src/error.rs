@@ -19,7 +19,7 @@ use crate::client_state::ClientState;
 use alloc::string::String;
 use ibc::{
 	core::{ics02_client, ics04_channel, ics24_host::error::ValidationError},
-	timestamp::{ParseTimestampError, TimestampOverflowError},
+	timestamp::{ParseTimestampError, Timestamp, TimestampOverflowError},
 };
 use prost::DecodeError;
 
@@ -34,6 +34,12 @@ pub enum Error {
 	Ics04(ics04_channel::error::Error),
 	ProtoBuf(DecodeError),
 	Anyhow(anyhow::Error),
+	/// The latest consensus state of the client is older than the trusting period
+	#[from(ignore)]
+	#[display(fmt = "Header expired, latest consensus state is from {}", at)]
+	HeaderExpired {
+		at: Timestamp,
+	},
 	Custom(String),
 }
 
@@ -306,3 +306,21 @@ async fn test_continuous_update_of_beefy_client() {
 		}
 	}
 }
+
+#[test]
+fn consensus_state_within_trust_period() {
+	use crate::client_def::is_within_trust_period;
+	use ibc::timestamp::Timestamp;
+
+	let client_state = ClientState::<HostFunctionsManager>::default();
+	let trusting_period = client_state.relay_chain.trusting_period();
+	let cs_timestamp = Timestamp::from_nanoseconds(1_000_000_000).unwrap();
+	let cs = ConsensusState::new(vec![0; 32], cs_timestamp.into_tm_time().unwrap());
+
+	let at_boundary = (cs_timestamp + trusting_period).unwrap();
+	assert!(is_within_trust_period(&cs, &client_state, cs_timestamp));
+	assert!(is_within_trust_period(&cs, &client_state, at_boundary));
+
+	let past_boundary = (at_boundary + Duration::from_nanos(1)).unwrap();
+	assert!(!is_within_trust_period(&cs, &client_state, past_boundary));
+}